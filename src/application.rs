@@ -17,14 +17,17 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::backend::Backend;
 use crate::config;
+use crate::mount_watcher::MountWatcher;
+use crate::notifications;
+use crate::session_monitor::SessionMonitor;
 use crate::ui::ApplicationWindow;
 use crate::ui::VaultsSettingsWindow;
 use crate::ui::pages::VaultsPageRowPasswordPromptWindow;
 use crate::user_config_manager::UserConfigManager;
+use crate::vault::{self, Vault, VaultState};
 
-use adw::prelude::AdwDialogExt;
+use adw::prelude::{AdwDialogExt, AlertDialogExt};
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gio::ApplicationFlags;
@@ -38,6 +41,7 @@ use gtk::prelude::*;
 use gtk::{gio, glib};
 use gtk_macros::action;
 use std::cell::RefCell;
+use uuid::Uuid;
 
 mod imp {
     use super::*;
@@ -105,6 +109,9 @@ mod imp {
                 OnlyPromptType::Open => {
                     log::trace!("OnlyPromptType::Open");
 
+                    // Quits right after unlocking
+                    notifications::disable_actions();
+
                     let window = ApplicationWindow::new(&app);
                     self.window.replace(Some(window));
 
@@ -113,13 +120,12 @@ mod imp {
                         .iter()
                         .find(|&kv| kv.1.name == *self.only_pompt_vault.borrow());
 
-                    match vault {
+                    match vault.and_then(|kv| UserConfigManager::instance().get_vault(*kv.0)) {
                         Some(vault) => {
-                            let vault_config = vault.1;
                             log::debug!(
                                 "Opening vault {:?}: {:?}",
                                 *self.only_pompt_vault.borrow(),
-                                &vault_config
+                                &vault.config()
                             );
 
                             let dialog = VaultsPageRowPasswordPromptWindow::new();
@@ -128,16 +134,24 @@ mod imp {
                                 "unlock",
                                 false,
                                 closure_local!(
-                                    #[strong(rename_to = vc)]
-                                    vault_config,
+                                    #[strong]
+                                    vault,
                                     #[strong(rename_to = a)]
                                     app,
                                     move |dialog: VaultsPageRowPasswordPromptWindow| {
                                         let password = dialog.get_password();
-                                        let result = Backend::open(&vc, password);
-                                        match result {
-                                            Ok(_) => log::info!("Opened vault successfully."),
-                                            Err(e) => log::error!("{e}"),
+                                        vault.set_state(VaultState::Unlocking);
+                                        match vault.unlock(password) {
+                                            Ok(_) => {
+                                                log::info!("Opened vault successfully.");
+                                                vault.set_state(VaultState::Unlocked);
+                                                notifications::unlocked(&vault);
+                                            }
+                                            Err(e) => {
+                                                log::error!("{e}");
+                                                vault.set_error(e.to_string());
+                                                notifications::unlock_failed(&vault, &e);
+                                            }
                                         }
                                         a.quit();
                                     }
@@ -158,6 +172,8 @@ mod imp {
                 OnlyPromptType::Close => {
                     log::trace!("OnlyPromptType::Close");
 
+                    notifications::disable_actions();
+
                     let window = ApplicationWindow::new(&app);
                     self.window.replace(Some(window));
 
@@ -166,19 +182,25 @@ mod imp {
                         .iter()
                         .find(|&kv| kv.1.name == *self.only_pompt_vault.borrow());
 
-                    match vault {
+                    match vault.and_then(|kv| UserConfigManager::instance().get_vault(*kv.0)) {
                         Some(vault) => {
-                            let vault_config = vault.1;
                             log::debug!(
                                 "Closing vault {:?}: {:?}",
                                 *self.only_pompt_vault.borrow(),
-                                &vault_config
+                                &vault.config()
                             );
 
-                            let result = Backend::close(vault_config);
-                            match result {
-                                Ok(_) => log::info!("Closed vault successfully."),
-                                Err(e) => log::error!("{e}"),
+                            vault.set_state(VaultState::Locking);
+                            match vault.lock() {
+                                Ok(_) => {
+                                    log::info!("Closed vault successfully.");
+                                    vault.set_state(VaultState::Locked);
+                                }
+                                Err(e) => {
+                                    log::error!("{e}");
+                                    vault.set_error(e.to_string());
+                                    notifications::lock_failed(&vault, &e);
+                                }
                             }
                         }
                         None => {
@@ -197,6 +219,20 @@ mod imp {
             log::debug!("startup()");
 
             self.parent_startup();
+
+            self.obj().setup_vault_actions();
+
+            let mount_watcher = MountWatcher::instance();
+            mount_watcher.connect_vault_changed(|uuid, _| {
+                if let Some(vault) = UserConfigManager::instance().get_vault(uuid) {
+                    vault.refresh_state();
+                }
+            });
+            mount_watcher.start();
+
+            UserConfigManager::instance().start_monitoring();
+
+            SessionMonitor::instance().start();
         }
 
         fn handle_local_options(
@@ -300,12 +336,196 @@ impl VApplication {
                 #[weak(rename_to = obj)]
                 self,
                 move |_, _| {
-                    obj.quit();
+                    obj.request_quit();
                 }
             )
         );
     }
 
+    // Actions of notifications, which pass the vault UUID. They can be
+    // activated after the window was closed or without any window.
+    fn setup_vault_actions(&self) {
+        let vault_action = |name: &str, activate: fn(&Self, Vault)| {
+            let action = gio::SimpleAction::new(name, Some(VariantTy::STRING));
+            action.connect_activate(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, parameter| {
+                    let Some(vault) = parameter
+                        .and_then(|p| p.get::<String>())
+                        .and_then(|uuid| Uuid::parse_str(&uuid).ok())
+                        .and_then(|uuid| UserConfigManager::instance().get_vault(uuid))
+                    else {
+                        log::error!("Vault of action does not exist: {:?}", parameter);
+                        return;
+                    };
+                    vault.refresh_state();
+                    activate(&obj, vault);
+                }
+            ));
+            self.add_action(&action);
+        };
+
+        vault_action("open-folder", |_, vault| {
+            if vault.state() == VaultState::Unlocked {
+                vault.open_folder();
+            }
+        });
+
+        vault_action("lock-vault", |_, vault| {
+            if vault.state() != VaultState::Unlocked {
+                return;
+            }
+
+            notifications::withdraw(vault.get_uuid());
+            vault.lock_in_background(|vault, result| {
+                if let Err(e) = result {
+                    log::error!("Error closing vault: {}", &e);
+                    notifications::lock_failed(vault, &e);
+                }
+            });
+        });
+
+        vault_action("unlock-vault", |obj, vault| {
+            if vault.state() != VaultState::Locked {
+                return;
+            }
+
+            obj.activate();
+            let row = obj
+                .imp()
+                .window
+                .borrow()
+                .as_ref()
+                .and_then(|window| window.find_row(vault.get_uuid()));
+            if let Some(row) = row {
+                row.unlock();
+            }
+        });
+    }
+
+    /// Whether quitting would ask the user or lock vaults.
+    pub fn needs_quit_confirmation(&self) -> bool {
+        self.imp().settings.borrow().string("quit-action") != "keep"
+            && !UserConfigManager::instance()
+                .get_unlocked_vaults()
+                .is_empty()
+    }
+
+    /// Quits after dealing with unlocked vaults the way the user chose.
+    pub fn request_quit(&self) {
+        log::trace!("request_quit()");
+
+        let vaults = UserConfigManager::instance().get_unlocked_vaults();
+        if vaults.is_empty() {
+            self.quit();
+            return;
+        }
+
+        let quit_action = self.imp().settings.borrow().string("quit-action");
+        match quit_action.as_str() {
+            "lock" => self.lock_and_quit(vaults),
+            "keep" => self.quit(),
+            _ => self.show_quit_dialog(vaults),
+        }
+    }
+
+    fn lock_and_quit(&self, vaults: Vec<Vault>) {
+        log::trace!("lock_and_quit({})", vaults.len());
+
+        vault::lock_all(
+            vaults,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |failures| {
+                    if failures.is_empty() {
+                        obj.quit();
+                        return;
+                    }
+
+                    // Keep running, the user has to decide about the vaults left
+                    let window = obj.imp().window.borrow().clone();
+                    match window {
+                        Some(window) => {
+                            window.present();
+                            window.show_lock_failures(&failures);
+                        }
+                        None => obj.quit(),
+                    }
+                }
+            ),
+        );
+    }
+
+    fn show_quit_dialog(&self, vaults: Vec<Vault>) {
+        log::trace!("show_quit_dialog({})", vaults.len());
+
+        let names = vaults
+            .iter()
+            .map(|vault| vault.name())
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let dialog = adw::AlertDialog::new(
+            Some(&gettext("Lock Vaults Before Quitting?")),
+            Some(&format!(
+                "{}\n\n{}",
+                gettext("These vaults are still unlocked:"),
+                names
+            )),
+        );
+        dialog.add_responses(&[
+            ("cancel", &gettext("_Cancel")),
+            ("keep", &gettext("_Quit and Keep Mounted")),
+            ("lock", &gettext("_Lock and Quit")),
+        ]);
+        dialog.set_response_appearance("lock", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("lock"));
+        dialog.set_close_response("cancel");
+
+        let remember_check_button =
+            gtk::CheckButton::with_mnemonic(&gettext("_Remember my choice"));
+        dialog.set_extra_child(Some(&remember_check_button));
+
+        dialog.connect_response(
+            None,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[weak]
+                remember_check_button,
+                move |_, response| {
+                    if response == "cancel" {
+                        return;
+                    }
+
+                    if remember_check_button.is_active() {
+                        if let Err(e) = obj
+                            .imp()
+                            .settings
+                            .borrow()
+                            .set_string("quit-action", response)
+                        {
+                            log::error!("Could not remember quit action: {}", e);
+                        }
+                    }
+
+                    match response {
+                        "lock" => obj.lock_and_quit(vaults.clone()),
+                        _ => obj.quit(),
+                    }
+                }
+            ),
+        );
+
+        let window = self.imp().window.borrow().clone();
+        if let Some(window) = &window {
+            window.present();
+        }
+        AdwDialogExt::present(&dialog, window.as_ref());
+    }
+
     fn setup_gsettings(&self) {
         let settings = self.imp().settings.borrow();
 
@@ -349,6 +569,7 @@ impl VApplication {
         self.set_accels_for_action("win.search", &["<primary>f"]);
         self.set_accels_for_action("win.escape", &["Escape"]);
         self.set_accels_for_action("win.refresh", &["<primary>r"]);
+        self.set_accels_for_action("win.lock-all", &["<primary>l"]);
 
         self.set_accels_for_action("app.preferences", &["<primary>comma"]);
         self.set_accels_for_action("win.show-help-overlay", &["<primary>question"]);
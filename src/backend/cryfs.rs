@@ -26,9 +26,13 @@ use gtk::gio::prelude::SettingsExt;
 use std::process::Command;
 use std::{self, io::Write, process::Stdio};
 
-fn get_binary_path(settings: &Settings, vault_config: &VaultConfig) -> Option<String> {
+pub fn get_binary_path(settings: &Settings, vault_config: &VaultConfig) -> Option<String> {
     log::trace!("get_binary_path({:?})", vault_config);
 
+    if vault_config.use_custom_binary && !vault_config.custom_binary_path.is_empty() {
+        return Some(vault_config.custom_binary_path.clone());
+    }
+
     if settings.boolean("use-custom-cryfs-binary") {
         return Some(settings.string("custom-cryfs-binary-path").to_string());
     }
@@ -45,14 +49,7 @@ pub fn is_available(settings: &Settings, vault_config: &VaultConfig) -> Result<b
         return Err(BackendError::ToUser(gettext("No CryFs binary path set")));
     }
 
-    let output = Command::new("flatpak-spawn")
-        .arg("--host")
-        .arg(binary_path.unwrap())
-        .arg("--version")
-        .output()?;
-    log::debug!("CryFS output: {:?}", output);
-
-    let success = output.status.success();
+    let success = super::info::probe(&binary_path.unwrap()).is_available();
     log::info!("CryFS is available: {}", success);
     Ok(success)
 }
@@ -98,7 +95,7 @@ pub fn init(
     log::debug!("CryFS output: {:?}", output);
     if output.status.success() {
         log::info!("CryFS init successful. Closing now");
-        close(settings, vault_config)
+        super::unmount::close(settings, vault_config)
     } else {
         std::io::stdout().write_all(&output.stdout).unwrap();
         std::io::stderr().write_all(&output.stderr).unwrap();
@@ -156,32 +153,6 @@ pub fn open(
     }
 }
 
-pub fn close(_settings: &Settings, vault_config: &VaultConfig) -> Result<(), BackendError> {
-    log::trace!("close({:?})", vault_config);
-
-    let child = Command::new("flatpak-spawn")
-        .arg("--host")
-        .arg("fusermount")
-        .arg("-u")
-        .stdout(Stdio::piped())
-        .arg(&vault_config.mount_directory)
-        .spawn()?;
-
-    let output = child.wait_with_output()?;
-    log::debug!("CryFS output: {:?}", output);
-    if output.status.success() {
-        log::info!("CryFS close successful");
-        Ok(())
-    } else {
-        std::io::stdout().write_all(&output.stdout).unwrap();
-        std::io::stderr().write_all(&output.stderr).unwrap();
-
-        let err_code = output.status.code();
-        log::error!("CryFS close failed: {:?}", err_code);
-        Err(status_to_err(err_code))
-    }
-}
-
 fn status_to_err(status: Option<i32>) -> BackendError {
     log::trace!("status_to_err({:?})", status);
 
@@ -26,9 +26,13 @@ use gtk::gio::prelude::SettingsExt;
 use std::process::Command;
 use std::{io::Write, process::Stdio};
 
-fn get_binary_path(settings: &Settings, vault_config: &VaultConfig) -> Option<String> {
+pub fn get_binary_path(settings: &Settings, vault_config: &VaultConfig) -> Option<String> {
     log::trace!("get_binary_path({:?})", vault_config);
 
+    if vault_config.use_custom_binary && !vault_config.custom_binary_path.is_empty() {
+        return Some(vault_config.custom_binary_path.clone());
+    }
+
     if settings.boolean("use-custom-gocryptfs-binary") {
         return Some(settings.string("custom-gocryptfs-binary-path").to_string());
     }
@@ -47,14 +51,7 @@ pub fn is_available(settings: &Settings, vault_config: &VaultConfig) -> Result<b
         )));
     }
 
-    let output = Command::new("flatpak-spawn")
-        .arg("--host")
-        .arg(binary_path.unwrap())
-        .arg("--version")
-        .output()?;
-    log::debug!("gocryptfs output: {:?}", output);
-
-    let success = output.status.success();
+    let success = super::info::probe(&binary_path.unwrap()).is_available();
     log::info!("gocryptfs is available: {}", success);
     Ok(success)
 }
@@ -161,31 +158,6 @@ pub fn open(
     }
 }
 
-pub fn close(_settings: &Settings, vault_config: &VaultConfig) -> Result<(), BackendError> {
-    log::trace!("close({:?}, password: <redacted>)", vault_config);
-
-    let child = Command::new("flatpak-spawn")
-        .arg("--host")
-        .arg("umount")
-        .stdout(Stdio::piped())
-        .arg(&vault_config.mount_directory)
-        .spawn()?;
-
-    let output = child.wait_with_output()?;
-    log::debug!("umount output: {:?}", output);
-    if output.status.success() {
-        log::info!("umount close successful");
-        Ok(())
-    } else {
-        std::io::stdout().write_all(&output.stdout).unwrap();
-        std::io::stderr().write_all(&output.stderr).unwrap();
-
-        let err_code = output.status.code();
-        log::error!("gocryptfs close failed: {:?}", err_code);
-        Err(umount_ret_status_to_err(err_code))
-    }
-}
-
 fn gocryptfs_ret_status_to_err(status: Option<i32>) -> BackendError {
     log::trace!("status_to_err({:?})", status);
 
@@ -231,24 +203,3 @@ fn gocryptfs_ret_status_to_err(status: Option<i32>) -> BackendError {
         None => BackendError::Generic,
     }
 }
-
-fn umount_ret_status_to_err(status: Option<i32>) -> BackendError {
-    log::trace!("umount_ret_status_to_err({:?})", status);
-
-    // We are guaranteed to have a non-zero errno here
-    if let Some(status) = status {
-        match status {
-            1 => BackendError::ToUser(gettext(
-                "You don't have the necessary privileges to unmount the directory.",
-            )),
-            2 => BackendError::ToUser(gettext("The data directory (mount point) does not exist.")),
-            4 => BackendError::ToUser(gettext("Internal error.")),
-            32 => BackendError::ToUser(gettext(
-                "The data directory (mount point) is busy. There are open files or processes using the filesystem.",
-            )),
-            _ => BackendError::Generic,
-        }
-    } else {
-        BackendError::Generic
-    }
-}
@@ -0,0 +1,225 @@
+// info.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::Backend;
+use gettextrs::gettext;
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, fmt, process::Command, sync::Mutex};
+
+// Results of `--version` per binary path, so refreshing the vault list does
+// not spawn one process per row
+static PROBES: Lazy<Mutex<HashMap<String, Probe>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendStatus {
+    Available,
+    NotInstalled,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub status: BackendStatus,
+    pub version: Option<Version>,
+}
+
+impl Probe {
+    pub fn is_available(&self) -> bool {
+        self.status == BackendStatus::Available
+    }
+}
+
+pub struct Feature {
+    pub name: String,
+    pub since: Version,
+}
+
+/// Returns the cached probe of the binary, running it on first use.
+pub fn probe(binary_path: &str) -> Probe {
+    log::trace!("probe({})", binary_path);
+
+    if let Some(probe) = cached_probe(binary_path) {
+        return probe;
+    }
+
+    test(binary_path)
+}
+
+pub fn cached_probe(binary_path: &str) -> Option<Probe> {
+    PROBES.lock().unwrap().get(binary_path).cloned()
+}
+
+/// Runs `--version` on the binary and replaces its cached probe.
+pub fn test(binary_path: &str) -> Probe {
+    log::trace!("test({})", binary_path);
+
+    let output = Command::new("flatpak-spawn")
+        .arg("--host")
+        .arg(binary_path)
+        .arg("--version")
+        .output();
+    log::debug!("{} output: {:?}", binary_path, output);
+
+    let probe = match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if output.status.success() {
+                Probe {
+                    status: BackendStatus::Available,
+                    version: parse_version(&stdout).or_else(|| parse_version(&stderr)),
+                }
+            } else if output.status.code() == Some(127)
+                || stderr.contains("No such file or directory")
+            {
+                Probe {
+                    status: BackendStatus::NotInstalled,
+                    version: None,
+                }
+            } else {
+                Probe {
+                    status: BackendStatus::Failed(stderr.trim().to_string()),
+                    version: None,
+                }
+            }
+        }
+        Err(e) => Probe {
+            status: BackendStatus::Failed(e.to_string()),
+            version: None,
+        },
+    };
+    log::info!("{} probe: {:?}", binary_path, probe);
+
+    PROBES
+        .lock()
+        .unwrap()
+        .insert(binary_path.to_string(), probe.clone());
+
+    probe
+}
+
+/// Finds the first version number in `--version` output.
+///
+/// CryFS prints `CryFS Version 0.11.4`, gocryptfs prints
+/// `gocryptfs v2.4.0; go-fuse v2.4.2; ...`.
+pub fn parse_version(output: &str) -> Option<Version> {
+    output.split_whitespace().find_map(|word| {
+        let word = word.trim_end_matches([';', ',']);
+        let word = word.strip_prefix('v').unwrap_or(word);
+        let word = word.split(['-', '+', '~']).next()?;
+        if !word.contains('.') {
+            return None;
+        }
+
+        let mut numbers = word.split('.').map(|n| n.parse::<u32>().ok());
+        let major = numbers.next()??;
+        let minor = numbers.next()??;
+        let patch = numbers.next().unwrap_or(Some(0))?;
+        Some(Version::new(major, minor, patch))
+    })
+}
+
+/// Features Vaults relies on, with the version they were introduced in.
+pub fn features(backend: &Backend) -> Vec<Feature> {
+    match backend {
+        Backend::Cryfs => vec![
+            Feature {
+                name: gettext("Detection of missing blocks as integrity violations"),
+                since: Version::new(0, 10, 0),
+            },
+            Feature {
+                name: gettext("Unmounting with cryfs-unmount"),
+                since: Version::new(0, 11, 0),
+            },
+        ],
+        Backend::Gocryptfs => vec![
+            Feature {
+                name: gettext("FIDO2 security keys"),
+                since: Version::new(2, 0, 0),
+            },
+            Feature {
+                name: gettext("Deterministic file names"),
+                since: Version::new(2, 0, 0),
+            },
+            Feature {
+                name: gettext("XChaCha20-Poly1305 encryption"),
+                since: Version::new(2, 2, 0),
+            },
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cryfs_version() {
+        let output = "CryFS Version 0.11.4\n\nWARNING! This version is not considered stable.";
+        assert_eq!(parse_version(output), Some(Version::new(0, 11, 4)));
+    }
+
+    #[test]
+    fn parse_gocryptfs_version() {
+        let output = "gocryptfs v2.4.0; go-fuse v2.4.2; 2023-06-10 go1.20.7 linux/amd64";
+        assert_eq!(parse_version(output), Some(Version::new(2, 4, 0)));
+
+        let output = "gocryptfs v2.3.1-12-g1ebb2ec; go-fuse [vendored]; 2023-01-02";
+        assert_eq!(parse_version(output), Some(Version::new(2, 3, 1)));
+
+        let output = "gocryptfs 1.8; go-fuse 2.0.3; 2021-01-15 go1.15.9 linux/amd64";
+        assert_eq!(parse_version(output), Some(Version::new(1, 8, 0)));
+    }
+
+    #[test]
+    fn parse_no_version() {
+        assert_eq!(parse_version(""), None);
+        assert_eq!(parse_version("command not found"), None);
+    }
+
+    #[test]
+    fn compare_versions() {
+        assert!(Version::new(2, 2, 0) > Version::new(2, 1, 9));
+        assert!(Version::new(0, 11, 0) > Version::new(0, 10, 3));
+        assert_eq!(Version::new(1, 8, 0).to_string(), "1.8.0");
+    }
+}
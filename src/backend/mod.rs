@@ -19,12 +19,18 @@
 
 pub mod cryfs;
 pub mod gocryptfs;
+pub mod info;
+pub mod options;
+pub mod unmount;
 
 use crate::{config::APP_ID, vault::VaultConfig};
 use gettextrs::gettext;
-use gtk::{gio::Settings, glib};
+use gtk::{
+    gio::{Settings, prelude::SettingsExt},
+    glib,
+};
 use serde::{Deserialize, Serialize};
-use std::string::String;
+use std::{path::Path, string::String};
 use strum_macros::EnumIter;
 
 quick_error! {
@@ -33,6 +39,9 @@ quick_error! {
         ToUser(e: String) {
             display("{}", e)
         }
+        Busy {
+            display("{}", gettext("The mount directory is busy. There are open files or processes using the filesystem."))
+        }
         Generic {
             from(std::io::Error)
         }
@@ -48,6 +57,8 @@ quick_error! {
     strum_macros::EnumString,
     Copy,
     Clone,
+    PartialEq,
+    Eq,
     glib::Enum,
     Default,
 )]
@@ -72,6 +83,34 @@ impl Backend {
         }
     }
 
+    pub fn get_binary_path(&self, vault_config: &VaultConfig) -> Option<String> {
+        log::trace!("get_binary_path({:?}, {:?})", self, vault_config);
+
+        let settings = Settings::new(APP_ID);
+
+        match &self {
+            Backend::Cryfs => cryfs::get_binary_path(&settings, vault_config),
+            Backend::Gocryptfs => gocryptfs::get_binary_path(&settings, vault_config),
+        }
+    }
+
+    /// Whether the vault runs a binary other than the bundled one, either its
+    /// own or the custom binary set in the preferences.
+    pub fn is_custom_binary(&self, vault_config: &VaultConfig) -> bool {
+        log::trace!("is_custom_binary({:?}, {:?})", self, vault_config);
+
+        if vault_config.use_custom_binary && !vault_config.custom_binary_path.is_empty() {
+            return true;
+        }
+
+        let settings = Settings::new(APP_ID);
+
+        match &self {
+            Backend::Cryfs => settings.boolean("use-custom-cryfs-binary"),
+            Backend::Gocryptfs => settings.boolean("use-custom-gocryptfs-binary"),
+        }
+    }
+
     pub fn init(vault_config: &VaultConfig, password: String) -> Result<(), BackendError> {
         log::trace!("init({:?}, password: <redacted>)", vault_config);
 
@@ -115,11 +154,50 @@ impl Backend {
 
         let settings = Settings::new(APP_ID);
 
-        match vault_config.backend {
-            Backend::Cryfs => cryfs::close(&settings, vault_config),
-            Backend::Gocryptfs => gocryptfs::close(&settings, vault_config),
+        unmount::close(&settings, vault_config)
+    }
+
+    pub fn cleanup(vault_config: &VaultConfig) -> Result<(), BackendError> {
+        log::trace!("cleanup({:?})", vault_config);
+
+        let settings = Settings::new(APP_ID);
+
+        unmount::cleanup(&settings, vault_config)
+    }
+}
+
+/// Detects the backend of an existing vault by its configuration file.
+pub fn detect_backend(encrypted_data_directory: &Path) -> Option<Backend> {
+    log::trace!("detect_backend({:?})", encrypted_data_directory);
+
+    let dir = match std::fs::read_dir(encrypted_data_directory) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::debug!("Could not read path {:?}: {}", encrypted_data_directory, e);
+            return None;
+        }
+    };
+
+    for file in dir {
+        match file {
+            Ok(f) => {
+                let file_name = f.file_name();
+
+                if file_name == "gocryptfs.conf" {
+                    return Some(Backend::Gocryptfs);
+                }
+
+                if file_name == "cryfs.config" {
+                    return Some(Backend::Cryfs);
+                }
+            }
+            Err(e) => {
+                log::debug!("Invalid file: {}", e);
+            }
         }
     }
+
+    None
 }
 
 pub fn get_ui_string_from_backend(backend: &Backend) -> String {
@@ -0,0 +1,183 @@
+// options.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::Backend;
+use gettextrs::gettext;
+use serde::Deserialize;
+use std::path::Path;
+
+/// An option a vault was created with, as shown to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreationOption {
+    pub name: String,
+    pub value: String,
+}
+
+// The unencrypted part of gocryptfs.conf
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GocryptfsConfig {
+    #[serde(default)]
+    creator: String,
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    feature_flags: Vec<String>,
+    scrypt_object: Option<ScryptObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScryptObject {
+    #[serde(rename = "N")]
+    n: u64,
+}
+
+/// Reads the options from the backend's config file. Only the parts that are
+/// stored unencrypted are available.
+pub fn creation_options(
+    backend: &Backend,
+    encrypted_data_directory: &Path,
+) -> Result<Vec<CreationOption>, String> {
+    log::trace!(
+        "creation_options({:?}, {:?})",
+        backend,
+        encrypted_data_directory
+    );
+
+    match backend {
+        Backend::Cryfs => {
+            let content = std::fs::read(encrypted_data_directory.join("cryfs.config"))
+                .map_err(|e| e.to_string())?;
+            parse_cryfs(&content)
+        }
+        Backend::Gocryptfs => {
+            let content = std::fs::read_to_string(encrypted_data_directory.join("gocryptfs.conf"))
+                .map_err(|e| e.to_string())?;
+            parse_gocryptfs(&content)
+        }
+    }
+}
+
+// The whole config is encrypted, except for a header like
+// `cryfs.config;1;scrypt` followed by a null byte
+fn parse_cryfs(content: &[u8]) -> Result<Vec<CreationOption>, String> {
+    let header_end = content
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(content.len());
+    let header = String::from_utf8_lossy(&content[..header_end]);
+
+    let mut fields = header.split(';');
+    if fields.next() != Some("cryfs.config") {
+        return Err(String::from("Not a CryFS config file"));
+    }
+
+    let mut options = vec![];
+    if let Some(version) = fields.next() {
+        options.push(CreationOption {
+            name: gettext("Config Format"),
+            value: version.to_string(),
+        });
+    }
+    if let Some(key_derivation) = fields.next() {
+        options.push(CreationOption {
+            name: gettext("Key Derivation"),
+            value: key_derivation.to_string(),
+        });
+    }
+
+    Ok(options)
+}
+
+fn parse_gocryptfs(content: &str) -> Result<Vec<CreationOption>, String> {
+    let config: GocryptfsConfig = serde_json::from_str(content).map_err(|e| e.to_string())?;
+
+    let mut options = vec![];
+    if !config.creator.is_empty() {
+        options.push(CreationOption {
+            name: gettext("Created With"),
+            value: config.creator,
+        });
+    }
+    options.push(CreationOption {
+        name: gettext("Config Format"),
+        value: config.version.to_string(),
+    });
+    if let Some(scrypt_object) = config.scrypt_object {
+        options.push(CreationOption {
+            name: gettext("Key Derivation"),
+            value: format!("scrypt, N = {}", scrypt_object.n),
+        });
+    }
+    if !config.feature_flags.is_empty() {
+        options.push(CreationOption {
+            name: gettext("Features"),
+            value: config.feature_flags.join(", "),
+        });
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(options: &[CreationOption]) -> Vec<&str> {
+        options.iter().map(|o| o.value.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_gocryptfs_config() {
+        let content = r#"{
+	"Creator": "gocryptfs v2.4.0",
+	"EncryptedKey": "c2VjcmV0",
+	"ScryptObject": {
+		"Salt": "c2FsdA==",
+		"N": 65536,
+		"R": 8,
+		"P": 1,
+		"KeyLen": 32
+	},
+	"Version": 2,
+	"FeatureFlags": ["HKDF", "GCMIV128", "DirIV", "EMENames", "LongNames", "Raw64"]
+}"#;
+
+        let options = parse_gocryptfs(content).unwrap();
+        assert_eq!(
+            values(&options),
+            vec![
+                "gocryptfs v2.4.0",
+                "2",
+                "scrypt, N = 65536",
+                "HKDF, GCMIV128, DirIV, EMENames, LongNames, Raw64"
+            ]
+        );
+        assert!(parse_gocryptfs("not json").is_err());
+    }
+
+    #[test]
+    fn parse_cryfs_header() {
+        let mut content = b"cryfs.config;1;scrypt".to_vec();
+        content.extend_from_slice(&[0, 0xff, 0x13, 0x37]);
+
+        assert_eq!(values(&parse_cryfs(&content).unwrap()), vec!["1", "scrypt"]);
+        assert!(parse_cryfs(b"\x00garbage").is_err());
+    }
+}
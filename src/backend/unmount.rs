@@ -0,0 +1,495 @@
+// unmount.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{Backend, BackendError, cryfs};
+use crate::vault::VaultConfig;
+use gettextrs::gettext;
+use gtk::gio::Settings;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+const FUSERMOUNT3: &str = "fusermount3";
+const FUSERMOUNT: &str = "fusermount";
+const CRYFS_UNMOUNT: &str = "cryfs-unmount";
+
+// errno of a mount that is still in use
+const EBUSY: i32 = 16;
+// Exit code of the shell and flatpak-spawn for a missing program
+const COMMAND_NOT_FOUND: i32 = 127;
+
+// Unmount tool found per unmounter and candidates, so locking does not spawn
+// one shell per candidate every time. Missing tools are looked up again, they
+// may have been installed in the meantime.
+static FOUND_PROGRAMS: Lazy<Mutex<HashMap<String, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct Unmounter {
+    launcher: Vec<String>,
+    search_path: Option<String>,
+}
+
+impl Default for Unmounter {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+impl Unmounter {
+    pub fn host() -> Self {
+        Self {
+            launcher: vec![String::from("flatpak-spawn"), String::from("--host")],
+            search_path: None,
+        }
+    }
+
+    #[cfg(test)]
+    fn local(search_path: &str) -> Self {
+        Self {
+            launcher: vec![],
+            search_path: Some(search_path.to_owned()),
+        }
+    }
+
+    fn command(&self, program: &str) -> Command {
+        let mut command = match self.launcher.split_first() {
+            Some((launcher, args)) => {
+                let mut command = Command::new(launcher);
+                command.args(args).arg(program);
+                command
+            }
+            None => Command::new(program),
+        };
+
+        if let Some(search_path) = &self.search_path {
+            command.env("PATH", search_path);
+        }
+
+        command
+    }
+
+    fn is_program_available(&self, program: &str) -> bool {
+        log::trace!("is_program_available({})", program);
+
+        let status = self
+            .command("/bin/sh")
+            .arg("-c")
+            .arg("command -v \"$1\"")
+            .arg("sh")
+            .arg(program)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        match status {
+            Ok(status) => status.success(),
+            Err(e) => {
+                log::error!("Could not look up {}: {}", program, e);
+                false
+            }
+        }
+    }
+
+    fn cache_key(&self, candidates: &[String]) -> String {
+        format!(
+            "{:?} {:?} {:?}",
+            self.launcher, self.search_path, candidates
+        )
+    }
+
+    /// Returns the first available candidate, looking it up on first use.
+    pub fn find_program(&self, candidates: &[String]) -> Option<String> {
+        log::trace!("find_program({:?})", candidates);
+
+        let key = self.cache_key(candidates);
+        if let Some(program) = FOUND_PROGRAMS.lock().unwrap().get(&key) {
+            return Some(program.clone());
+        }
+
+        let program = candidates
+            .iter()
+            .find(|candidate| self.is_program_available(candidate))
+            .cloned()?;
+        FOUND_PROGRAMS.lock().unwrap().insert(key, program.clone());
+
+        Some(program)
+    }
+
+    fn forget_program(&self, candidates: &[String]) {
+        FOUND_PROGRAMS
+            .lock()
+            .unwrap()
+            .remove(&self.cache_key(candidates));
+    }
+
+    pub fn unmount(
+        &self,
+        candidates: &[String],
+        mount_directory: &str,
+        lazy: bool,
+    ) -> Result<(), BackendError> {
+        log::trace!("unmount({:?}, {}, {})", candidates, mount_directory, lazy);
+
+        let candidates: Vec<String> = candidates
+            .iter()
+            .filter(|candidate| !lazy || !is_cryfs_unmount(candidate))
+            .cloned()
+            .collect();
+
+        let program = match self.find_program(&candidates) {
+            Some(program) => program,
+            None => {
+                log::error!("None of {:?} is available", candidates);
+                return Err(BackendError::ToUser(gettext(
+                    "No unmount tool found. Please install fusermount3.",
+                )));
+            }
+        };
+        log::debug!("Unmounting {} with {}", mount_directory, program);
+
+        let mut command = self.command(&program);
+        if !is_cryfs_unmount(&program) {
+            command.arg("-u");
+            if lazy {
+                command.arg("-z");
+            }
+        }
+
+        let output = match command
+            .arg(mount_directory)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) => {
+                // The cached tool was removed
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    self.forget_program(&candidates);
+                }
+                return Err(e.into());
+            }
+        };
+        log::debug!("{} output: {:?}", program, output);
+        if output.status.success() {
+            log::info!("Unmounting {} successful", mount_directory);
+            Ok(())
+        } else {
+            std::io::stdout().write_all(&output.stdout).unwrap();
+            std::io::stderr().write_all(&output.stderr).unwrap();
+
+            let err_code = output.status.code();
+            log::error!("Unmounting {} failed: {:?}", mount_directory, err_code);
+            if err_code == Some(COMMAND_NOT_FOUND) {
+                self.forget_program(&candidates);
+            }
+            Err(unmount_status_to_err(
+                err_code,
+                &String::from_utf8_lossy(&output.stderr),
+            ))
+        }
+    }
+}
+
+pub fn unmount_candidates(settings: &Settings, vault_config: &VaultConfig) -> Vec<String> {
+    log::trace!("unmount_candidates({:?})", vault_config);
+
+    let mut candidates = vec![];
+
+    if let Backend::Cryfs = vault_config.backend {
+        let sibling = cryfs::get_binary_path(settings, vault_config)
+            .as_ref()
+            .and_then(|binary_path| std::path::Path::new(binary_path).parent())
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map(|parent| parent.join(CRYFS_UNMOUNT).to_string_lossy().to_string());
+        if let Some(sibling) = sibling {
+            candidates.push(sibling);
+        }
+        candidates.push(String::from(CRYFS_UNMOUNT));
+    }
+
+    candidates.push(String::from(FUSERMOUNT3));
+    candidates.push(String::from(FUSERMOUNT));
+
+    candidates
+}
+
+pub fn close(settings: &Settings, vault_config: &VaultConfig) -> Result<(), BackendError> {
+    log::trace!("close({:?})", vault_config);
+
+    Unmounter::host().unmount(
+        &unmount_candidates(settings, vault_config),
+        &vault_config.mount_directory,
+        false,
+    )
+}
+
+pub fn cleanup(settings: &Settings, vault_config: &VaultConfig) -> Result<(), BackendError> {
+    log::trace!("cleanup({:?})", vault_config);
+
+    Unmounter::host().unmount(
+        &unmount_candidates(settings, vault_config),
+        &vault_config.mount_directory,
+        true,
+    )
+}
+
+fn is_cryfs_unmount(program: &str) -> bool {
+    std::path::Path::new(program)
+        .file_name()
+        .map(|file_name| file_name == CRYFS_UNMOUNT)
+        .unwrap_or(false)
+}
+
+fn unmount_status_to_err(status: Option<i32>, stderr: &str) -> BackendError {
+    log::trace!("unmount_status_to_err({:?}, {:?})", status, stderr);
+
+    // Killed by a signal
+    let Some(status) = status else {
+        return BackendError::Generic;
+    };
+
+    // fusermount and cryfs-unmount exit with 1 on most failures, so the cause
+    // is mostly told apart by the message they print.
+    let stderr = stderr.to_lowercase();
+
+    if status == EBUSY || stderr.contains("device or resource busy") {
+        BackendError::Busy
+    } else if status == COMMAND_NOT_FOUND {
+        BackendError::ToUser(gettext(
+            "No unmount tool found. Please install fusermount3.",
+        ))
+    } else if stderr.contains("not found in")
+        || stderr.contains("not mounted")
+        || stderr.contains("invalid argument")
+    {
+        BackendError::ToUser(gettext("The vault is not mounted."))
+    } else if stderr.contains("permission denied") || stderr.contains("operation not permitted") {
+        BackendError::ToUser(gettext(
+            "You don't have the necessary privileges to unmount the directory.",
+        ))
+    } else if stderr.contains("no such file or directory") {
+        BackendError::ToUser(gettext("The mount directory does not exist."))
+    } else if stderr.contains("transport endpoint is not connected") {
+        BackendError::ToUser(gettext(
+            "The connection to the vault was lost. Clean up the mount and try again.",
+        ))
+    } else {
+        BackendError::ToUser(gettext("An unknown error occurred."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    fn fake_bin_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "vaults-unmount-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn fake_binary(dir: &Path, name: &str, exit_code: i32, stderr: &str) {
+        let path = dir.join(name);
+        let script = format!(
+            "#!/bin/sh\necho \"{} $*\" >> \"{}\"\necho \"{}\" >&2\nexit {}\n",
+            name,
+            dir.join("calls").display(),
+            stderr,
+            exit_code
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn calls(dir: &Path) -> String {
+        std::fs::read_to_string(dir.join("calls")).unwrap_or_default()
+    }
+
+    fn candidates(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn prefers_fusermount3() {
+        let dir = fake_bin_dir("prefers_fusermount3");
+        fake_binary(&dir, FUSERMOUNT3, 0, "");
+        fake_binary(&dir, FUSERMOUNT, 0, "");
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let result = unmounter.unmount(&candidates(&[FUSERMOUNT3, FUSERMOUNT]), "/mnt/a", false);
+
+        assert!(result.is_ok());
+        assert_eq!(calls(&dir), "fusermount3 -u /mnt/a\n");
+    }
+
+    #[test]
+    fn falls_back_to_fusermount() {
+        let dir = fake_bin_dir("falls_back_to_fusermount");
+        fake_binary(&dir, FUSERMOUNT, 0, "");
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let result = unmounter.unmount(&candidates(&[FUSERMOUNT3, FUSERMOUNT]), "/mnt/a", false);
+
+        assert!(result.is_ok());
+        assert_eq!(calls(&dir), "fusermount -u /mnt/a\n");
+    }
+
+    #[test]
+    fn uses_cryfs_unmount_without_flags() {
+        let dir = fake_bin_dir("uses_cryfs_unmount_without_flags");
+        fake_binary(&dir, CRYFS_UNMOUNT, 0, "");
+        fake_binary(&dir, FUSERMOUNT3, 0, "");
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let result = unmounter.unmount(
+            &candidates(&[CRYFS_UNMOUNT, FUSERMOUNT3, FUSERMOUNT]),
+            "/mnt/a",
+            false,
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(calls(&dir), "cryfs-unmount /mnt/a\n");
+    }
+
+    #[test]
+    fn lazy_skips_cryfs_unmount() {
+        let dir = fake_bin_dir("lazy_skips_cryfs_unmount");
+        fake_binary(&dir, CRYFS_UNMOUNT, 0, "");
+        fake_binary(&dir, FUSERMOUNT3, 0, "");
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let result = unmounter.unmount(
+            &candidates(&[CRYFS_UNMOUNT, FUSERMOUNT3, FUSERMOUNT]),
+            "/mnt/a",
+            true,
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(calls(&dir), "fusermount3 -u -z /mnt/a\n");
+    }
+
+    #[test]
+    fn reports_busy_mount() {
+        let dir = fake_bin_dir("reports_busy_mount");
+        fake_binary(
+            &dir,
+            FUSERMOUNT3,
+            1,
+            "fusermount3: failed to unmount /mnt/a: Device or resource busy",
+        );
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let result = unmounter.unmount(&candidates(&[FUSERMOUNT3]), "/mnt/a", false);
+
+        assert!(matches!(result.unwrap_err(), BackendError::Busy));
+    }
+
+    #[test]
+    fn caches_found_tool() {
+        let dir = fake_bin_dir("caches_found_tool");
+        fake_binary(&dir, FUSERMOUNT3, 0, "");
+        fake_binary(&dir, FUSERMOUNT, 0, "");
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let candidates = candidates(&[FUSERMOUNT3, FUSERMOUNT]);
+        assert!(unmounter.unmount(&candidates, "/mnt/a", false).is_ok());
+
+        // The removed tool is still used once, then looked up again
+        std::fs::remove_file(dir.join(FUSERMOUNT3)).unwrap();
+        assert!(unmounter.unmount(&candidates, "/mnt/a", false).is_err());
+        assert!(unmounter.unmount(&candidates, "/mnt/a", false).is_ok());
+
+        assert_eq!(calls(&dir), "fusermount3 -u /mnt/a\nfusermount -u /mnt/a\n");
+    }
+
+    #[test]
+    fn reports_missing_tool() {
+        let dir = fake_bin_dir("reports_missing_tool");
+
+        let unmounter = Unmounter::local(dir.to_str().unwrap());
+        let result = unmounter.unmount(&candidates(&[FUSERMOUNT3, FUSERMOUNT]), "/mnt/a", false);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No unmount tool found. Please install fusermount3."
+        );
+    }
+
+    #[test]
+    fn maps_stderr_to_errors() {
+        assert_eq!(
+            unmount_status_to_err(
+                Some(1),
+                "fusermount: entry for /mnt/a not found in /etc/mtab"
+            )
+            .to_string(),
+            "The vault is not mounted."
+        );
+        assert_eq!(
+            unmount_status_to_err(
+                Some(1),
+                "fusermount3: failed to unmount /mnt/a: Invalid argument"
+            )
+            .to_string(),
+            "The vault is not mounted."
+        );
+        assert_eq!(
+            unmount_status_to_err(
+                Some(1),
+                "fusermount3: failed to unmount /mnt/a: Operation not permitted"
+            )
+            .to_string(),
+            "You don't have the necessary privileges to unmount the directory."
+        );
+        assert_eq!(
+            unmount_status_to_err(
+                Some(1),
+                "fusermount3: bad mount point /mnt/a: No such file or directory"
+            )
+            .to_string(),
+            "The mount directory does not exist."
+        );
+        assert!(matches!(
+            unmount_status_to_err(Some(EBUSY), ""),
+            BackendError::Busy
+        ));
+        assert_eq!(
+            unmount_status_to_err(Some(COMMAND_NOT_FOUND), "").to_string(),
+            "No unmount tool found. Please install fusermount3."
+        );
+        assert_eq!(
+            unmount_status_to_err(Some(1), "").to_string(),
+            "An unknown error occurred."
+        );
+        assert!(matches!(
+            unmount_status_to_err(None, ""),
+            BackendError::Generic
+        ));
+    }
+}
@@ -0,0 +1,323 @@
+// config_file.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use gtk::glib;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+pub const MAX_BACKUPS: usize = 5;
+
+const BACKUP_DIRECTORY: &str = "backups";
+const BACKUP_EXTENSION: &str = ".bak";
+const SET_ASIDE_EXTENSION: &str = ".broken";
+
+/// Backs up the current file, then replaces it with the new contents.
+pub fn write(path: &Path, contents: &str) -> io::Result<()> {
+    log::trace!("write({:?})", path);
+
+    if path.exists() {
+        backup(path, &timestamp(), MAX_BACKUPS)?;
+    }
+
+    write_atomic(path, contents)
+}
+
+/// Writes to a temporary file next to `path`, flushes it to disk and renames
+/// it over `path`, so readers see either the old or the new contents.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    log::trace!("write_atomic({:?})", path);
+
+    let directory = parent_directory(path);
+    let file_name = file_name(path)?;
+    let temp_path = directory.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+        return result;
+    }
+
+    // Persist the rename itself
+    File::open(directory)?.sync_all()
+}
+
+/// Reads a file that maps vaults to some state kept apart from the user
+/// config. A missing file is empty, a damaged one is an error.
+pub fn read_map<T: DeserializeOwned>(path: &Path) -> io::Result<HashMap<Uuid, T>> {
+    log::trace!("read_map({:?})", path);
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+
+    toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_map<T: Serialize>(path: &Path, map: &HashMap<Uuid, T>) -> io::Result<()> {
+    log::trace!("write_map({:?})", path);
+
+    std::fs::create_dir_all(parent_directory(path))?;
+    let content = toml::to_string_pretty(map).map_err(io::Error::other)?;
+    write_atomic(path, &content)
+}
+
+/// Changes the state of the vaults in the file. Nothing is written if the file
+/// could not be read, so what it holds is not lost.
+pub fn update_map<T: DeserializeOwned + Serialize>(
+    path: &Path,
+    f: impl FnOnce(&mut HashMap<Uuid, T>),
+) -> io::Result<()> {
+    let mut map = read_map(path)?;
+    f(&mut map);
+    write_map(path, &map)
+}
+
+/// Removes a vault from the file, which is only written if it held the vault.
+pub fn remove_from_map<T: DeserializeOwned + Serialize>(
+    path: &Path,
+    uuid: &Uuid,
+) -> io::Result<()> {
+    let mut map: HashMap<Uuid, T> = read_map(path)?;
+    if map.remove(uuid).is_none() {
+        return Ok(());
+    }
+
+    write_map(path, &map)
+}
+
+/// Returns the backups of `path`, newest first.
+pub fn backups(path: &Path) -> Vec<PathBuf> {
+    let Ok(file_name) = file_name(path) else {
+        return vec![];
+    };
+    let prefix = format!("{}.", file_name);
+
+    let Ok(entries) = std::fs::read_dir(backup_directory(path)) else {
+        return vec![];
+    };
+
+    let mut backups: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(BACKUP_EXTENSION))
+        })
+        .collect();
+
+    // Timestamps sort lexicographically
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// Renames `path` out of the way so it is kept for inspection but no longer
+/// read.
+pub fn set_aside(path: &Path) -> io::Result<PathBuf> {
+    log::trace!("set_aside({:?})", path);
+
+    let set_aside_path = parent_directory(path).join(format!(
+        "{}.{}{}",
+        file_name(path)?,
+        timestamp(),
+        SET_ASIDE_EXTENSION
+    ));
+    std::fs::rename(path, &set_aside_path)?;
+
+    Ok(set_aside_path)
+}
+
+/// Converts a byte offset into a 1-based line and column.
+pub fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(content.len());
+    while !content.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &content[..offset];
+
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+pub fn backup_directory(path: &Path) -> PathBuf {
+    parent_directory(path).join(BACKUP_DIRECTORY)
+}
+
+fn backup(path: &Path, timestamp: &str, max_backups: usize) -> io::Result<()> {
+    log::trace!("backup({:?}, {}, {})", path, timestamp, max_backups);
+
+    let backup_directory = backup_directory(path);
+    std::fs::create_dir_all(&backup_directory)?;
+
+    let contents = std::fs::read_to_string(path)?;
+    let backup_path = backup_directory.join(format!(
+        "{}.{}{}",
+        file_name(path)?,
+        timestamp,
+        BACKUP_EXTENSION
+    ));
+    write_atomic(&backup_path, &contents)?;
+
+    for old_backup in backups(path).iter().skip(max_backups) {
+        log::debug!("Removing old backup: {:?}", old_backup);
+        std::fs::remove_file(old_backup)?;
+    }
+
+    Ok(())
+}
+
+fn timestamp() -> String {
+    glib::DateTime::now_utc()
+        .and_then(|now| now.format("%Y%m%dT%H%M%S%f"))
+        .map(|t| t.to_string())
+        .unwrap_or_default()
+}
+
+fn parent_directory(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn file_name(path: &Path) -> io::Result<&str> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid config file path"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "vaults-config-file-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn damaged_maps_are_not_overwritten() {
+        let directory = temp_directory("map");
+        let path = directory.join("state").join("state.toml");
+        let uuid = Uuid::new_v4();
+
+        assert!(read_map::<u64>(&path).unwrap().is_empty());
+        update_map(&path, |map: &mut HashMap<Uuid, u64>| {
+            map.insert(uuid, 1);
+        })
+        .unwrap();
+        assert_eq!(read_map::<u64>(&path).unwrap()[&uuid], 1);
+
+        std::fs::write(&path, "not = [toml").unwrap();
+        assert!(read_map::<u64>(&path).is_err());
+        assert!(
+            update_map(&path, |map: &mut HashMap<Uuid, u64>| {
+                map.insert(uuid, 2);
+            })
+            .is_err()
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not = [toml");
+    }
+
+    #[test]
+    fn write_atomic_replaces_contents() {
+        let directory = temp_directory("atomic");
+        let path = directory.join("config.toml");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn backups_are_rotated() {
+        let directory = temp_directory("rotate");
+        let path = directory.join("config.toml");
+
+        for i in 0..4 {
+            write_atomic(&path, &format!("version {}", i)).unwrap();
+            backup(&path, &format!("2024010{}T000000000000", i), 2).unwrap();
+        }
+
+        let backups = backups(&path);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), "version 3");
+        assert_eq!(std::fs::read_to_string(&backups[1]).unwrap(), "version 2");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn line_column_of_offset() {
+        let content = "[a]\nname = \"ä\"\nbroken";
+
+        assert_eq!(line_column(content, 0), (1, 1));
+        assert_eq!(line_column(content, 2), (1, 3));
+        assert_eq!(line_column(content, 4), (2, 1));
+        assert_eq!(line_column(content, 16), (3, 1));
+        assert_eq!(line_column(content, 1000), (3, 7));
+    }
+
+    #[test]
+    fn backups_of_other_files_are_ignored() {
+        let directory = temp_directory("other");
+        let path = directory.join("config.toml");
+        let other_path = directory.join("other.toml");
+
+        write_atomic(&path, "config").unwrap();
+        write_atomic(&other_path, "other").unwrap();
+        backup(&path, "20240101T000000000000", 5).unwrap();
+        backup(&other_path, "20240102T000000000000", 5).unwrap();
+
+        let backups = backups(&path);
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), "config");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
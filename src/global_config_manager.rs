@@ -17,13 +17,14 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::config_file;
 use gtk::{
     gio::subclass::prelude::*,
     glib::{self, home_dir, user_config_dir, user_data_dir},
 };
 use ini::Ini;
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
+use std::{cell::RefCell, path::Path};
 use toml::de::Error;
 
 use self::imp::GlobalConfig;
@@ -185,7 +186,7 @@ impl GlobalConfigManager {
                     }
 
                     match toml::to_string_pretty(&global_config) {
-                        Ok(contents) => match std::fs::write(path, &contents) {
+                        Ok(contents) => match config_file::write(Path::new(path), &contents) {
                             Ok(_) => {
                                 log::debug!("Successfully wrote user config: {}", &contents);
                             }
@@ -207,7 +208,7 @@ impl GlobalConfigManager {
 
         if let Some(path) = self.imp().user_config_directory.borrow().as_ref() {
             match toml::to_string_pretty(&self.imp().global_config.borrow().clone()) {
-                Ok(contents) => match std::fs::write(path, &contents) {
+                Ok(contents) => match config_file::write(Path::new(path), &contents) {
                     Ok(_) => {
                         log::debug!("Successfully wrote user config: {}", &contents);
                     }
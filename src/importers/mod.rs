@@ -0,0 +1,101 @@
+// mod.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+pub mod plasma_vault;
+pub mod sirikali;
+
+use crate::{
+    backend::Backend,
+    vault::VaultConfig,
+    vault_bundle::{self, BundleEntry, ImportIssue},
+};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportSource {
+    PlasmaVault,
+    SiriKali,
+}
+
+/// A vault as configured in another application.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignVault {
+    pub name: String,
+    pub encrypted_data_directory: String,
+    pub mount_directory: String,
+    /// The backend, or its name if Vaults does not support it
+    pub backend: Result<Backend, String>,
+}
+
+/// Maps foreign vaults onto vault configs for the review list. Unsupported
+/// vaults are kept, so they can be reported.
+pub fn to_entries(
+    vaults: Vec<ForeignVault>,
+    existing: &HashMap<Uuid, VaultConfig>,
+) -> Vec<BundleEntry> {
+    log::trace!("to_entries({:?})", vaults);
+
+    let mut uuids: HashSet<Uuid> = existing.keys().cloned().collect();
+
+    vaults
+        .into_iter()
+        .map(|vault| {
+            let mut uuid = Uuid::new_v4();
+            while uuids.contains(&uuid) {
+                uuid = Uuid::new_v4();
+            }
+            uuids.insert(uuid);
+
+            let config = VaultConfig {
+                name: vault.name,
+                backend: vault.backend.clone().unwrap_or_default(),
+                encrypted_data_directory: vault.encrypted_data_directory,
+                mount_directory: vault.mount_directory,
+                ..Default::default()
+            };
+
+            let issues = match vault.backend {
+                Ok(_) => vault_bundle::check(&uuid, &config, existing),
+                Err(backend) => vec![ImportIssue::UnsupportedBackend(backend)],
+            };
+
+            BundleEntry {
+                uuid,
+                config,
+                issues,
+            }
+        })
+        .collect()
+}
+
+fn backend_from_name(name: &str) -> Result<Backend, String> {
+    match name.to_lowercase().as_str() {
+        "cryfs" => Ok(Backend::Cryfs),
+        "gocryptfs" => Ok(Backend::Gocryptfs),
+        _ => Err(name.to_string()),
+    }
+}
+
+fn name_from_path(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
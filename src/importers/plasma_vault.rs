@@ -0,0 +1,129 @@
+// plasma_vault.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{ForeignVault, backend_from_name, name_from_path};
+use gtk::glib::home_dir;
+use ini::Ini;
+use std::path::{Path, PathBuf};
+
+// Plasma Vault mounts below this directory in the home directory by default
+const DEFAULT_MOUNT_DIRECTORY: &str = "Vaults";
+
+pub fn config_path() -> PathBuf {
+    home_dir().join(".config").join("plasmavaultrc")
+}
+
+/// Parses `plasmavaultrc`. Every vault has a group named after its encrypted
+/// data directory. Vaults without a mount point use the default one.
+pub fn parse(content: &str) -> Result<Vec<ForeignVault>, String> {
+    log::trace!("parse()");
+
+    let ini = Ini::load_from_str(content).map_err(|e| e.to_string())?;
+
+    let mut vaults = vec![];
+    for (section, properties) in ini.iter() {
+        let Some(encrypted_data_directory) = section else {
+            continue;
+        };
+        let Some(backend) = properties.get("backend") else {
+            continue;
+        };
+
+        let mount_point = properties
+            .get("mountPoint")
+            .filter(|mount_point| Path::new(mount_point).is_absolute());
+
+        let name = match (properties.get("name"), mount_point) {
+            (Some(name), _) if !name.is_empty() => name.to_string(),
+            (_, Some(mount_point)) => name_from_path(mount_point),
+            _ => {
+                let name = name_from_path(encrypted_data_directory);
+                name.strip_suffix(".enc").unwrap_or(&name).to_string()
+            }
+        };
+
+        let mount_directory = match mount_point {
+            Some(mount_point) => mount_point.to_string(),
+            None => home_dir()
+                .join(DEFAULT_MOUNT_DIRECTORY)
+                .join(&name)
+                .to_string_lossy()
+                .to_string(),
+        };
+
+        vaults.push(ForeignVault {
+            name,
+            encrypted_data_directory: encrypted_data_directory.to_string(),
+            mount_directory,
+            backend: backend_from_name(backend),
+        });
+    }
+
+    Ok(vaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+
+    #[test]
+    fn parse_plasmavaultrc() {
+        let content = r#"
+[/home/user/.local/share/plasma-vault/Private.enc]
+activities=
+backend=gocryptfs
+mountPoint=/home/user/Vaults/Private
+name=Private
+offlineOnly=false
+
+[/home/user/.local/share/plasma-vault/Old.enc]
+backend=encfs
+name=Old
+
+[/home/user/.local/share/plasma-vault/Work.enc]
+backend=cryfs
+
+[EncryptedDevices]
+/home/user/.local/share/plasma-vault/Private.enc=true
+/home/user/.local/share/plasma-vault/Work.enc=true
+"#;
+
+        let vaults = parse(content).unwrap();
+        assert_eq!(vaults.len(), 3);
+
+        assert_eq!(
+            vaults[0],
+            ForeignVault {
+                name: "Private".to_string(),
+                encrypted_data_directory: "/home/user/.local/share/plasma-vault/Private.enc"
+                    .to_string(),
+                mount_directory: "/home/user/Vaults/Private".to_string(),
+                backend: Ok(Backend::Gocryptfs),
+            }
+        );
+        assert_eq!(vaults[1].backend, Err("encfs".to_string()));
+        assert_eq!(vaults[2].name, "Work");
+        assert_eq!(
+            vaults[2].mount_directory,
+            home_dir().join("Vaults").join("Work").to_string_lossy()
+        );
+        assert_eq!(vaults[2].backend, Ok(Backend::Cryfs));
+    }
+}
@@ -0,0 +1,178 @@
+// sirikali.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{ForeignVault, name_from_path};
+use crate::backend;
+use gtk::glib::home_dir;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// Files identifying backends that Vaults does not support
+const UNSUPPORTED_CONFIG_FILES: &[(&str, &str)] = &[
+    (".encfs6.xml", "EncFS"),
+    (".securefs.json", "securefs"),
+    ("securefs.json", "securefs"),
+];
+
+// Only the fields Vaults can use. Passwords and key files are never read.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Favorite {
+    volume_path: String,
+    #[serde(default)]
+    mount_point_path: String,
+}
+
+/// Newer versions keep one JSON file per favorite in this directory, older
+/// ones a single file with one tab separated favorite per line.
+pub fn favorites_path() -> PathBuf {
+    home_dir()
+        .join(".config")
+        .join("SiriKali")
+        .join("favorites")
+}
+
+pub fn read(path: &Path) -> Result<Vec<ForeignVault>, String> {
+    log::trace!("read({:?})", path);
+
+    if !path.is_dir() {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        return Ok(parse_legacy(&content));
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    files.sort();
+
+    let mut vaults = vec![];
+    for file in files {
+        let content = std::fs::read_to_string(&file).map_err(|e| e.to_string())?;
+        match parse_json(&content) {
+            Ok(vault) => vaults.push(vault),
+            Err(e) => log::warn!("Skipping SiriKali favorite {:?}: {}", file, e),
+        }
+    }
+
+    Ok(vaults)
+}
+
+pub fn parse_json(content: &str) -> Result<ForeignVault, String> {
+    let favorite: Favorite = serde_json::from_str(content).map_err(|e| e.to_string())?;
+
+    Ok(to_foreign_vault(
+        &favorite.volume_path,
+        &favorite.mount_point_path,
+    ))
+}
+
+pub fn parse_legacy(content: &str) -> Vec<ForeignVault> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let volume_path = fields.next().unwrap_or_default();
+            let mount_point_path = fields.next().unwrap_or_default();
+            to_foreign_vault(volume_path, mount_point_path)
+        })
+        .collect()
+}
+
+fn to_foreign_vault(volume_path: &str, mount_point_path: &str) -> ForeignVault {
+    let name = match name_from_path(mount_point_path) {
+        name if name.is_empty() => name_from_path(volume_path),
+        name => name,
+    };
+
+    ForeignVault {
+        name,
+        encrypted_data_directory: volume_path.to_string(),
+        mount_directory: mount_point_path.to_string(),
+        backend: detect_backend(volume_path),
+    }
+}
+
+// SiriKali does not store the backend, it detects it when mounting
+fn detect_backend(volume_path: &str) -> Result<backend::Backend, String> {
+    let path = Path::new(volume_path);
+
+    // Network volumes are given as e.g. "sshfs user@host:/path"
+    if !path.is_absolute() {
+        return Err(volume_path
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string());
+    }
+
+    if let Some(backend) = backend::detect_backend(path) {
+        return Ok(backend);
+    }
+
+    for (file_name, backend) in UNSUPPORTED_CONFIG_FILES {
+        if path.join(file_name).exists() {
+            return Err(backend.to_string());
+        }
+    }
+
+    Err(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+
+    #[test]
+    fn parse_favorites() {
+        let directory =
+            std::env::temp_dir().join(format!("vaults-sirikali-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let private = directory.join("Private");
+        let old = directory.join("Old");
+        std::fs::create_dir_all(&private).unwrap();
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::write(private.join("gocryptfs.conf"), "").unwrap();
+        std::fs::write(old.join(".encfs6.xml"), "").unwrap();
+
+        let vault = parse_json(&format!(
+            r#"{{"volumePath": "{}", "mountPointPath": "/home/user/Vaults/Secret", "password": "hunter2"}}"#,
+            private.to_str().unwrap()
+        ))
+        .unwrap();
+        assert_eq!(vault.name, "Secret");
+        assert_eq!(vault.backend, Ok(Backend::Gocryptfs));
+
+        let vaults = parse_legacy(&format!(
+            "{}\t\tfalse\t\t\t\nsshfs user@host:/data\t/home/user/Remote\tfalse\n",
+            old.to_str().unwrap()
+        ));
+        assert_eq!(vaults.len(), 2);
+        assert_eq!(vaults[0].name, "Old");
+        assert_eq!(vaults[0].backend, Err("EncFS".to_string()));
+        assert_eq!(vaults[1].name, "Remote");
+        assert_eq!(vaults[1].backend, Err("sshfs".to_string()));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -17,71 +17,22 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::{backend::Backend, util, vault::VaultConfig};
 use gtk::glib::user_config_dir;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use toml::de::Error;
-use uuid::Uuid;
 
-#[derive(Debug, Deserialize, Clone, Serialize)]
-pub struct LegacyVaultConfig {
-    pub backend: Backend,
-    pub encrypted_data_directory: String,
-    pub mount_directory: String,
-    pub session_lock: Option<bool>,
-    pub use_custom_binary: Option<bool>,
-    pub custom_binary_path: Option<String>,
-}
-
-pub fn get_user_config_from_legacy_config() -> HashMap<Uuid, VaultConfig> {
-    log::trace!("convert_legacy_user_config_to_new()");
-
-    let mut new_user_config: HashMap<Uuid, VaultConfig> = HashMap::new();
-    let legacy_user_config = read_legacy_user_config();
-    if let Some(legacy_user_config) = legacy_user_config {
-        for (name, vault_config) in legacy_user_config {
-            let new_vault_config = VaultConfig {
-                name,
-                backend: vault_config.backend,
-                encrypted_data_directory: vault_config.encrypted_data_directory,
-                mount_directory: vault_config.mount_directory,
-                session_lock: vault_config.session_lock.unwrap_or(false),
-            };
-            new_user_config.insert(util::generate_uuid(), new_vault_config);
-        }
-    }
-
-    new_user_config
-}
-
-pub fn read_legacy_user_config() -> Option<HashMap<String, LegacyVaultConfig>> {
+/// Reads the user config of Vaults before 0.11, which is migrated as schema
+/// version 1.
+pub fn read_legacy_user_config() -> Option<String> {
     log::trace!("read_legacy_user_config()");
 
-    let legacy_user_config_path = get_legacy_user_config_path();
+    let legacy_user_config_path = get_legacy_user_config_path()?;
 
-    if let Some(legacy_user_config_path) = legacy_user_config_path {
-        let contents = std::fs::read_to_string(&legacy_user_config_path);
-        match contents {
-            Ok(content) => {
-                let res: Result<HashMap<String, LegacyVaultConfig>, Error> =
-                    toml::from_str(&content.clone());
-                match res {
-                    Ok(v) => {
-                        return Some(v);
-                    }
-                    Err(e) => {
-                        log::error!("Failed to parse user data config: {}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                log::warn!("Failed to read user data config: {}", e);
-            }
+    match std::fs::read_to_string(&legacy_user_config_path) {
+        Ok(content) => Some(content),
+        Err(e) => {
+            log::warn!("Failed to read legacy user config: {}", e);
+            None
         }
     }
-
-    None
 }
 
 fn get_legacy_user_config_path() -> Option<String> {
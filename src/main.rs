@@ -20,11 +20,27 @@
 mod application;
 #[rustfmt::skip]
 mod config;
+mod config_file;
 mod global_config_manager;
+mod importers;
 mod legacy;
+mod migrations;
+mod mount_watcher;
+mod notifications;
+mod search_query;
+mod session_monitor;
 mod user_config_manager;
 mod util;
 mod vault;
+mod vault_backup;
+mod vault_bundle;
+mod vault_conversion;
+mod vault_descriptor;
+mod vault_relocation;
+mod vault_scanner;
+mod vault_sort;
+mod vault_tags;
+mod vault_usage;
 
 mod backend;
 mod ui;
@@ -32,7 +48,6 @@ mod ui;
 #[macro_use]
 extern crate quick_error;
 extern crate ini;
-extern crate proc_mounts;
 extern crate serde;
 extern crate toml;
 extern crate uuid;
@@ -59,9 +74,7 @@ fn main() {
 
     GlobalConfigManager::instance().read_config();
 
-    if legacy::global_config::needs_conversion() {
-        legacy::global_config::convert();
-    }
+    legacy::global_config::convert();
 
     UserConfigManager::instance().read_config();
 
@@ -0,0 +1,216 @@
+// migrations.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use toml::{Table, Value};
+use uuid::Uuid;
+
+// Schema versions of the user config:
+// 1: user_config.toml, vaults keyed by name
+// 2: user_config_v2.toml, vaults keyed by UUID at the top level
+// 3: user_config_v2.toml, `schema_version` and vaults keyed by UUID in `vaults`
+pub const LEGACY_SCHEMA_VERSION: i64 = 1;
+pub const CURRENT_SCHEMA_VERSION: i64 = 3;
+
+type Migration = fn(&mut Table) -> Result<(), String>;
+
+// Each step migrates from the version it is listed with to the next one
+const MIGRATIONS: &[(i64, Migration)] = &[(1, migrate_v1_to_v2), (2, migrate_v2_to_v3)];
+
+/// Files without a `schema_version` field predate it and are version 2.
+pub fn schema_version(table: &Table) -> i64 {
+    table
+        .get("schema_version")
+        .and_then(Value::as_integer)
+        .unwrap_or(2)
+}
+
+/// Runs every migration step from `version` up to the current version.
+///
+/// Returns whether anything was migrated.
+pub fn migrate(table: &mut Table, version: i64) -> Result<bool, String> {
+    log::trace!("migrate({})", version);
+
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(format!(
+            "Schema version {} is newer than the supported version {}",
+            version, CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    let mut current_version = version;
+    for (from_version, migration) in MIGRATIONS {
+        if *from_version < current_version {
+            continue;
+        }
+
+        log::info!(
+            "Migrating user config from schema version {} to {}",
+            from_version,
+            from_version + 1
+        );
+        migration(table)?;
+        current_version = from_version + 1;
+    }
+
+    Ok(current_version != version)
+}
+
+// Vaults were keyed by name, missing optional fields are filled by serde
+fn migrate_v1_to_v2(table: &mut Table) -> Result<(), String> {
+    let legacy = std::mem::take(table);
+
+    for (name, vault) in legacy {
+        let Value::Table(mut vault) = vault else {
+            return Err(format!("Vault {} is not a table", name));
+        };
+
+        vault.insert("name".to_string(), Value::String(name));
+
+        let mut uuid = Uuid::new_v4();
+        while table.contains_key(&uuid.to_string()) {
+            uuid = Uuid::new_v4();
+        }
+        table.insert(uuid.to_string(), Value::Table(vault));
+    }
+
+    Ok(())
+}
+
+// Vaults moved below `vaults` to make room for `schema_version`
+fn migrate_v2_to_v3(table: &mut Table) -> Result<(), String> {
+    let vaults = std::mem::take(table);
+
+    table.insert(
+        "schema_version".to_string(),
+        Value::Integer(CURRENT_SCHEMA_VERSION),
+    );
+    table.insert("vaults".to_string(), Value::Table(vaults));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::Backend, vault::VaultConfig};
+    use std::collections::HashMap;
+
+    const V1: &str = r#"
+[Private]
+backend = "Gocryptfs"
+encrypted_data_directory = "/home/user/.Private"
+mount_directory = "/home/user/Private"
+use_custom_binary = true
+custom_binary_path = "/usr/local/bin/gocryptfs"
+
+[Work]
+backend = "Cryfs"
+encrypted_data_directory = "/home/user/.Work"
+mount_directory = "/home/user/Work"
+"#;
+
+    const V2: &str = r#"
+[a1b2c3d4-0000-4000-8000-000000000001]
+name = "Private"
+backend = "Gocryptfs"
+encrypted_data_directory = "/home/user/.Private"
+mount_directory = "/home/user/Private"
+session_lock = true
+"#;
+
+    fn vaults(table: Table) -> HashMap<Uuid, VaultConfig> {
+        table
+            .get("vaults")
+            .cloned()
+            .unwrap()
+            .try_into::<HashMap<Uuid, VaultConfig>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn detect_schema_version() {
+        assert_eq!(schema_version(&V2.parse::<Table>().unwrap()), 2);
+        assert_eq!(
+            schema_version(&"schema_version = 3".parse::<Table>().unwrap()),
+            3
+        );
+    }
+
+    #[test]
+    fn migrate_from_v1() {
+        let mut table: Table = V1.parse().unwrap();
+
+        assert_eq!(migrate(&mut table, LEGACY_SCHEMA_VERSION), Ok(true));
+        assert_eq!(schema_version(&table), CURRENT_SCHEMA_VERSION);
+
+        let vaults = vaults(table);
+        assert_eq!(vaults.len(), 2);
+
+        let private = vaults.values().find(|v| v.name == "Private").unwrap();
+        assert_eq!(private.backend, Backend::Gocryptfs);
+        assert!(!private.session_lock);
+        assert!(private.use_custom_binary);
+        assert_eq!(private.custom_binary_path, "/usr/local/bin/gocryptfs");
+
+        let work = vaults.values().find(|v| v.name == "Work").unwrap();
+        assert_eq!(work.backend, Backend::Cryfs);
+        assert!(!work.use_custom_binary);
+        assert_eq!(work.custom_binary_path, "");
+    }
+
+    #[test]
+    fn migrate_from_v2() {
+        let mut table: Table = V2.parse().unwrap();
+        let version = schema_version(&table);
+
+        assert_eq!(migrate(&mut table, version), Ok(true));
+        assert_eq!(schema_version(&table), CURRENT_SCHEMA_VERSION);
+
+        let vaults = vaults(table);
+        let uuid = Uuid::parse_str("a1b2c3d4-0000-4000-8000-000000000001").unwrap();
+        assert_eq!(vaults[&uuid].name, "Private");
+        assert!(vaults[&uuid].session_lock);
+        // Fields added later are filled with their defaults
+        assert!(!vaults[&uuid].use_custom_binary);
+    }
+
+    #[test]
+    fn current_version_is_unchanged() {
+        let mut table: Table = V2.parse().unwrap();
+        migrate(&mut table, 2).unwrap();
+        let migrated = table.clone();
+
+        assert_eq!(migrate(&mut table, CURRENT_SCHEMA_VERSION), Ok(false));
+        assert_eq!(table, migrated);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut table = Table::new();
+
+        assert!(migrate(&mut table, CURRENT_SCHEMA_VERSION + 1).is_err());
+    }
+
+    #[test]
+    fn invalid_v1_vault_is_rejected() {
+        let mut table: Table = "Private = 1".parse().unwrap();
+
+        assert!(migrate(&mut table, LEGACY_SCHEMA_VERSION).is_err());
+    }
+}
@@ -0,0 +1,398 @@
+// mount_watcher.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{backend::Backend, user_config_manager::UserConfigManager, vault::VaultConfig};
+use gtk::{
+    gio::{self, subclass::prelude::*},
+    glib::{self, prelude::*, subclass::Signal},
+};
+use once_cell::sync::Lazy;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fs::File,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use uuid::Uuid;
+
+static mut MOUNT_WATCHER: Option<MountWatcher> = None;
+
+// Canonical mount directories as configured. Resolving a path stats the mount
+// point, which blocks while its FUSE daemon hangs, so it is done once per
+// configured directory and off the main thread where possible.
+static CANONICAL_PATHS: Lazy<Mutex<HashMap<String, PathBuf>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct MountWatcher {
+        pub mounted: RefCell<HashMap<Uuid, bool>>,
+        // Kept open, the kernel flags it with POLLPRI when the mounts change
+        pub mountinfo: RefCell<Option<File>>,
+        pub source_id: RefCell<Option<glib::SourceId>>,
+        pub is_polling: Cell<bool>,
+        pub is_poll_pending: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MountWatcher {
+        const NAME: &'static str = "MountWatcher";
+        type ParentType = glib::Object;
+        type Type = super::MountWatcher;
+    }
+
+    impl ObjectImpl for MountWatcher {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![
+                    Signal::builder("vault-changed")
+                        .param_types([String::static_type(), bool::static_type()])
+                        .build(),
+                ]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MountWatcher(ObjectSubclass<imp::MountWatcher>);
+}
+
+impl MountWatcher {
+    pub fn connect_vault_changed<F: Fn(Uuid, bool) + 'static>(
+        &self,
+        callback: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_local("vault-changed", false, move |args| {
+            let uuid = args.get(1).unwrap().get::<String>().unwrap();
+            let is_mounted = args.get(2).unwrap().get::<bool>().unwrap();
+            match Uuid::parse_str(&uuid) {
+                Ok(uuid) => callback(uuid, is_mounted),
+                Err(e) => log::error!("Invalid vault UUID {}: {}", uuid, e),
+            }
+            None
+        })
+    }
+
+    pub fn instance() -> Self {
+        unsafe {
+            #[allow(static_mut_refs)]
+            match MOUNT_WATCHER.as_ref() {
+                Some(mount_watcher) => mount_watcher.clone(),
+                None => {
+                    let mount_watcher: MountWatcher = glib::Object::new();
+                    MOUNT_WATCHER = Some(mount_watcher.clone());
+                    mount_watcher
+                }
+            }
+        }
+    }
+
+    pub fn start(&self) {
+        log::trace!("start()");
+
+        if self.imp().source_id.borrow().is_some() {
+            return;
+        }
+
+        self.poll();
+
+        let mountinfo = match File::open(MOUNTINFO_PATH) {
+            Ok(mountinfo) => mountinfo,
+            Err(e) => {
+                log::error!("Could not watch {}: {}", MOUNTINFO_PATH, e);
+                return;
+            }
+        };
+
+        let source_id = glib::unix_fd_add_local(
+            mountinfo.as_raw_fd(),
+            glib::IOCondition::PRI | glib::IOCondition::ERR,
+            glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move |_, _| {
+                    obj.poll();
+                    glib::ControlFlow::Continue
+                }
+            ),
+        );
+        self.imp().mountinfo.replace(Some(mountinfo));
+        self.imp().source_id.replace(Some(source_id));
+    }
+
+    /// Whether the vault is mounted. Mount directories that were not resolved
+    /// yet are resolved on the calling thread.
+    pub fn is_mounted(&self, vault_config: &VaultConfig) -> bool {
+        log::trace!("is_mounted({:?})", vault_config);
+
+        match read_mounts() {
+            Some(mounts) => is_vault_mounted(&mounts, vault_config),
+            None => false,
+        }
+    }
+
+    // Compares the mounts with the vaults in a worker thread. Changes that
+    // arrive meanwhile are checked once it finished.
+    fn poll(&self) {
+        let imp = self.imp();
+        if imp.is_polling.get() {
+            imp.is_poll_pending.set(true);
+            return;
+        }
+        imp.is_polling.set(true);
+
+        let map = UserConfigManager::instance().get_map();
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let result = gio::spawn_blocking(move || {
+                    let mounts = read_mounts()?;
+                    Some(
+                        map.iter()
+                            .map(|(uuid, vault_config)| {
+                                (*uuid, is_vault_mounted(&mounts, vault_config))
+                            })
+                            .collect::<HashMap<Uuid, bool>>(),
+                    )
+                })
+                .await;
+
+                if let Ok(Some(states)) = result {
+                    obj.update(states);
+                }
+
+                obj.imp().is_polling.set(false);
+                if obj.imp().is_poll_pending.replace(false) {
+                    obj.poll();
+                }
+            }
+        ));
+    }
+
+    fn update(&self, states: HashMap<Uuid, bool>) {
+        let mut changed = vec![];
+        {
+            let mut mounted = self.imp().mounted.borrow_mut();
+            mounted.retain(|uuid, _| states.contains_key(uuid));
+
+            for (uuid, is_mounted) in states {
+                let was_mounted = mounted.insert(uuid, is_mounted).unwrap_or(false);
+                if is_mounted != was_mounted {
+                    changed.push((uuid, is_mounted));
+                }
+            }
+        }
+
+        for (uuid, is_mounted) in changed {
+            log::debug!("Vault {} mounted: {}", uuid, is_mounted);
+            self.emit_by_name::<()>("vault-changed", &[&uuid.to_string(), &is_mounted]);
+        }
+    }
+}
+
+fn read_mounts() -> Option<Vec<MountInfo>> {
+    match std::fs::read_to_string(MOUNTINFO_PATH) {
+        Ok(content) => Some(parse_mountinfo(&content)),
+        Err(e) => {
+            log::error!("Could not read {}: {}", MOUNTINFO_PATH, e);
+            None
+        }
+    }
+}
+
+fn is_vault_mounted(mounts: &[MountInfo], vault_config: &VaultConfig) -> bool {
+    let mount_directory = cached_canonical_path(&vault_config.mount_directory);
+    let fs_type = fuse_fs_type(&vault_config.backend);
+
+    mounts
+        .iter()
+        .any(|m| m.mount_point == mount_directory && m.fs_type == fs_type)
+}
+
+pub fn fuse_fs_type(backend: &Backend) -> &'static str {
+    match backend {
+        Backend::Cryfs => "fuse.cryfs",
+        Backend::Gocryptfs => "fuse.gocryptfs",
+    }
+}
+
+fn cached_canonical_path(path: &str) -> PathBuf {
+    if let Some(canonical_path) = CANONICAL_PATHS.lock().unwrap().get(path) {
+        return canonical_path.clone();
+    }
+
+    // Missing directories are resolved again once they exist
+    let Some(canonical_path) = resolve_path(Path::new(path)) else {
+        return PathBuf::from(path);
+    };
+    CANONICAL_PATHS
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), canonical_path.clone());
+
+    canonical_path
+}
+
+/// Resolves the path like the kernel reports it in mountinfo.
+///
+/// A mount point whose backend died can no longer be resolved itself, so only
+/// its parent directory is canonicalized in that case.
+fn resolve_path(path: &Path) -> Option<PathBuf> {
+    if let Ok(canonical_path) = path.canonicalize() {
+        return Some(canonical_path);
+    }
+
+    let parent = path.parent()?.canonicalize().ok()?;
+    Some(parent.join(path.file_name()?))
+}
+
+/// Parses the mount point and file system type of each line in mountinfo.
+///
+/// See proc_pid_mountinfo(5) for the format.
+pub fn parse_mountinfo(content: &str) -> Vec<MountInfo> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (mount_fields, fs_fields) = line.split_once(" - ")?;
+            let mount_point = mount_fields.split(' ').nth(4)?;
+            let fs_type = fs_fields.split(' ').next()?;
+            Some(MountInfo {
+                mount_point: PathBuf::from(unescape(mount_point)),
+                fs_type: unescape(fs_type),
+            })
+        })
+        .collect()
+}
+
+// Spaces, tabs, newlines and backslashes are written as octal escapes
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let is_escape = bytes[i] == b'\\'
+            && i + 3 < bytes.len()
+            && bytes[i + 1..i + 4]
+                .iter()
+                .all(|b| (b'0'..=b'7').contains(b));
+        if is_escape {
+            let code = bytes[i + 1..i + 4]
+                .iter()
+                .fold(0u32, |acc, b| acc * 8 + u32::from(b - b'0'));
+            unescaped.push(code as u8);
+            i += 4;
+            continue;
+        }
+        unescaped.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 0:21 / / rw,relatime shared:1 - btrfs /dev/nvme0n1p3 rw,seclabel
+61 22 0:52 / /home/user/Vaults/Private rw,nosuid,nodev,relatime shared:33 - fuse.gocryptfs /home/user/.Private rw,user_id=1000,group_id=1000
+62 22 0:53 / /home/user/My\\040Vault rw,nosuid,nodev,relatime shared:34 - fuse.cryfs cryfs@/home/user/.cryfs rw,user_id=1000,group_id=1000
+63 22 0:54 / /home/user/Other/Private rw,nosuid,nodev,relatime shared:35 - fuse.sshfs host:/ rw,user_id=1000,group_id=1000
+";
+
+    fn vault_config(backend: Backend, mount_directory: &str) -> VaultConfig {
+        VaultConfig {
+            name: "".to_string(),
+            backend,
+            encrypted_data_directory: "".to_string(),
+            mount_directory: mount_directory.to_string(),
+            session_lock: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_mount_points_and_fs_types() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+
+        assert_eq!(mounts.len(), 4);
+        assert_eq!(
+            mounts[1].mount_point,
+            PathBuf::from("/home/user/Vaults/Private")
+        );
+        assert_eq!(mounts[1].fs_type, "fuse.gocryptfs");
+        assert_eq!(mounts[2].mount_point, PathBuf::from("/home/user/My Vault"));
+        assert_eq!(mounts[2].fs_type, "fuse.cryfs");
+    }
+
+    #[test]
+    fn unescape_octal_sequences() {
+        assert_eq!(unescape("a\\040b"), "a b");
+        assert_eq!(unescape("a\\011b\\012c"), "a\tb\nc");
+        assert_eq!(unescape("a\\134b"), "a\\b");
+        assert_eq!(unescape("a\\b"), "a\\b");
+        assert_eq!(unescape("trailing\\04"), "trailing\\04");
+    }
+
+    #[test]
+    fn match_on_path_and_fs_type() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+
+        assert!(is_vault_mounted(
+            &mounts,
+            &vault_config(Backend::Gocryptfs, "/home/user/Vaults/Private")
+        ));
+        assert!(is_vault_mounted(
+            &mounts,
+            &vault_config(Backend::Cryfs, "/home/user/My Vault")
+        ));
+
+        // Same basename, different directory
+        assert!(!is_vault_mounted(
+            &mounts,
+            &vault_config(Backend::Gocryptfs, "/home/user/Other/Private")
+        ));
+        // Same directory, different backend
+        assert!(!is_vault_mounted(
+            &mounts,
+            &vault_config(Backend::Cryfs, "/home/user/Vaults/Private")
+        ));
+    }
+}
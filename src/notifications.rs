@@ -0,0 +1,145 @@
+// notifications.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::BackendError;
+use crate::vault::Vault;
+use gettextrs::gettext;
+use gtk::{gio, prelude::*};
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+// Application actions taking the vault UUID as string
+pub const ACTION_OPEN_FOLDER: &str = "app.open-folder";
+pub const ACTION_LOCK: &str = "app.lock-vault";
+pub const ACTION_UNLOCK: &str = "app.unlock-vault";
+
+static HAS_ACTIONS: AtomicBool = AtomicBool::new(true);
+
+/// Sends notifications without actions from now on. Vaults is not D-Bus
+/// activatable, so nothing handles them once the process exited.
+pub fn disable_actions() {
+    HAS_ACTIONS.store(false, Ordering::Relaxed);
+}
+
+/// Whether the user is looking at a window of Vaults, so results can be shown
+/// there instead.
+pub fn is_app_focused() -> bool {
+    gio::Application::default()
+        .and_downcast::<gtk::Application>()
+        .and_then(|app| app.active_window())
+        .is_some_and(|window| window.is_active())
+}
+
+pub fn unlocked(vault: &Vault) {
+    log::trace!("unlocked({})", vault.name());
+
+    let notification = gio::Notification::new(&gettext("Vault Unlocked"));
+    notification.set_body(Some(&vault.name()));
+    set_default_action(&notification, ACTION_OPEN_FOLDER, vault);
+    add_button(
+        &notification,
+        &gettext("Open Folder"),
+        ACTION_OPEN_FOLDER,
+        vault,
+    );
+    add_button(&notification, &gettext("Lock"), ACTION_LOCK, vault);
+
+    send(vault, &notification);
+}
+
+/// The vault was locked without the user asking for it, e.g. on screen lock.
+pub fn locked_automatically(vault: &Vault, reason: &str) {
+    log::trace!("locked_automatically({}, {})", vault.name(), reason);
+
+    let notification = gio::Notification::new(&gettext("Vault Locked"));
+    notification.set_body(Some(&format!("{}: {}", vault.name(), reason)));
+
+    send(vault, &notification);
+}
+
+pub fn unlock_failed(vault: &Vault, e: &BackendError) {
+    log::trace!("unlock_failed({}, {})", vault.name(), e);
+
+    let notification = failed(&gettext("Could Not Unlock Vault"), vault, e);
+    add_button(&notification, &gettext("Retry"), ACTION_UNLOCK, vault);
+
+    send(vault, &notification);
+}
+
+pub fn lock_failed(vault: &Vault, e: &BackendError) {
+    log::trace!("lock_failed({}, {})", vault.name(), e);
+
+    let title = match e {
+        BackendError::Busy => gettext("Vault Is Still in Use"),
+        _ => gettext("Could Not Lock Vault"),
+    };
+    let notification = failed(&title, vault, e);
+    add_button(&notification, &gettext("Retry"), ACTION_LOCK, vault);
+
+    send(vault, &notification);
+}
+
+/// Removes the last notification of the vault, as it is outdated.
+pub fn withdraw(uuid: Uuid) {
+    if let Some(app) = gio::Application::default() {
+        app.withdraw_notification(&id(uuid));
+    }
+}
+
+fn failed(title: &str, vault: &Vault, e: &BackendError) -> gio::Notification {
+    let notification = gio::Notification::new(title);
+    notification.set_body(Some(&format!("{}: {}", vault.name(), e)));
+    notification.set_priority(gio::NotificationPriority::High);
+    notification
+}
+
+fn set_default_action(notification: &gio::Notification, action: &str, vault: &Vault) {
+    if !HAS_ACTIONS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    notification.set_default_action_and_target_value(
+        action,
+        Some(&vault.get_uuid().to_string().to_variant()),
+    );
+}
+
+fn add_button(notification: &gio::Notification, label: &str, action: &str, vault: &Vault) {
+    if !HAS_ACTIONS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    notification.add_button_with_target_value(
+        label,
+        action,
+        Some(&vault.get_uuid().to_string().to_variant()),
+    );
+}
+
+// One notification per vault, a newer one replaces the older one
+fn send(vault: &Vault, notification: &gio::Notification) {
+    match gio::Application::default() {
+        Some(app) => app.send_notification(Some(&id(vault.get_uuid())), notification),
+        None => log::error!("Cannot send notification without application"),
+    }
+}
+
+fn id(uuid: Uuid) -> String {
+    format!("vault-{}", uuid)
+}
@@ -0,0 +1,325 @@
+// search_query.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    vault::{VaultConfig, VaultState},
+    vault_tags,
+};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Part of the name or one of the paths
+    Text(String),
+    Tag(String),
+    Backend(String),
+    State(String),
+    Path(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub filter: Filter,
+    pub negated: bool,
+}
+
+/// A search as typed into the search entry, e.g.
+/// `"my vault" backend:gocryptfs -state:open path:~/Nextcloud tag:work`.
+///
+/// All terms must match. A leading `-` negates a term and double quotes keep
+/// spaces in a term. Unknown prefixes are searched for as text.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<Term>,
+}
+
+impl SearchQuery {
+    /// `home` replaces a leading `~` in `path:` terms.
+    pub fn parse(text: &str, home: &Path) -> Self {
+        let mut query = Self::default();
+
+        for token in tokenize(text) {
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, token.as_str()),
+            };
+
+            let filter = match token.split_once(':') {
+                Some((key, value)) => match key.to_lowercase().as_str() {
+                    "tag" => Filter::Tag(value.to_lowercase()),
+                    "backend" => Filter::Backend(value.to_lowercase()),
+                    "state" => Filter::State(value.to_lowercase()),
+                    "path" => Filter::Path(expand_home(value, home)),
+                    _ => Filter::Text(token.to_lowercase()),
+                },
+                None => Filter::Text(token.to_lowercase()),
+            };
+
+            // Still being typed
+            let is_empty = match &filter {
+                Filter::Text(value)
+                | Filter::Tag(value)
+                | Filter::Backend(value)
+                | Filter::State(value)
+                | Filter::Path(value) => value.is_empty(),
+            };
+            if !is_empty {
+                query.terms.push(Term { filter, negated });
+            }
+        }
+
+        query
+    }
+
+    pub fn matches(&self, config: &VaultConfig, state: VaultState) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.negated != matches_filter(&term.filter, config, state))
+    }
+
+    /// The text terms to highlight in matching rows.
+    pub fn highlight_words(&self) -> Vec<String> {
+        self.terms
+            .iter()
+            .filter(|term| !term.negated)
+            .filter_map(|term| match &term.filter {
+                Filter::Text(text) => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn matches_filter(filter: &Filter, config: &VaultConfig, state: VaultState) -> bool {
+    match filter {
+        Filter::Text(text) => [
+            &config.name,
+            &config.encrypted_data_directory,
+            &config.mount_directory,
+        ]
+        .iter()
+        .any(|value| value.to_lowercase().contains(text)),
+        Filter::Tag(tag) => vault_tags::has_tag(config, tag),
+        Filter::Backend(backend) => config.backend.to_string().to_lowercase() == *backend,
+        Filter::State(name) => matches_state(name, state),
+        Filter::Path(path) => [&config.encrypted_data_directory, &config.mount_directory]
+            .iter()
+            .any(|value| {
+                // Absolute paths match everything below them
+                if path.starts_with('/') {
+                    value.starts_with(path.as_str())
+                } else {
+                    value.to_lowercase().contains(&path.to_lowercase())
+                }
+            }),
+    }
+}
+
+fn matches_state(name: &str, state: VaultState) -> bool {
+    match name {
+        "open" | "unlocked" => state == VaultState::Unlocked,
+        "closed" | "locked" => state == VaultState::Locked,
+        "busy" => matches!(
+            state,
+            VaultState::Unlocking | VaultState::Locking | VaultState::Busy
+        ),
+        "disconnected" => state == VaultState::Disconnected,
+        "error" => matches!(state, VaultState::Error | VaultState::BackendMissing),
+        _ => false,
+    }
+}
+
+fn expand_home(path: &str, home: &Path) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home.to_string_lossy(), rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut in_quotes = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Escapes `text` for Pango markup and makes every occurrence of `words`
+/// bold, ignoring case.
+pub fn highlight(text: &str, words: &[String]) -> String {
+    let mut markup = String::new();
+    let mut end = 0;
+
+    for (range_start, range_end) in match_ranges(text, words) {
+        markup.push_str(&escape(&text[end..range_start]));
+        markup.push_str("<b>");
+        markup.push_str(&escape(&text[range_start..range_end]));
+        markup.push_str("</b>");
+        end = range_end;
+    }
+    markup.push_str(&escape(&text[end..]));
+
+    markup
+}
+
+pub fn contains_any(text: &str, words: &[String]) -> bool {
+    !match_ranges(text, words).is_empty()
+}
+
+// Sorted, non-overlapping byte ranges of `text` matching any of the
+// lowercase `words`
+fn match_ranges(text: &str, words: &[String]) -> Vec<(usize, usize)> {
+    // Lowercasing can change the length of a character, so remember where
+    // each lowercase byte came from
+    let mut lowercase = String::new();
+    let mut offsets = vec![];
+    for (index, c) in text.char_indices() {
+        let start = lowercase.len();
+        lowercase.extend(c.to_lowercase());
+        offsets.resize(offsets.len() + lowercase.len() - start, index);
+    }
+    offsets.push(text.len());
+
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for word in words.iter().filter(|w| !w.is_empty()) {
+        for (start, _) in lowercase.match_indices(word.as_str()) {
+            let range = (offsets[start], offsets[start + word.len()]);
+            if range.0 < range.1 {
+                ranges.push(range);
+            }
+        }
+    }
+    ranges.sort();
+
+    let mut merged: Vec<(usize, usize)> = vec![];
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+
+    fn config(name: &str, tags: &[&str]) -> VaultConfig {
+        VaultConfig {
+            name: name.to_string(),
+            backend: Backend::Gocryptfs,
+            encrypted_data_directory: "/home/alice/Nextcloud/Private".to_string(),
+            mount_directory: "/home/alice/Vaults/Private".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn parse(text: &str) -> SearchQuery {
+        SearchQuery::parse(text, Path::new("/home/alice"))
+    }
+
+    #[test]
+    fn parse_query() {
+        let query = parse(r#"Tag:work  "my vault" -path:~/Nextcloud tag: foo:bar"#);
+        assert_eq!(
+            query.terms,
+            vec![
+                Term {
+                    filter: Filter::Tag("work".to_string()),
+                    negated: false
+                },
+                Term {
+                    filter: Filter::Text("my vault".to_string()),
+                    negated: false
+                },
+                Term {
+                    filter: Filter::Path("/home/alice/Nextcloud".to_string()),
+                    negated: true
+                },
+                Term {
+                    filter: Filter::Text("foo:bar".to_string()),
+                    negated: false
+                },
+            ]
+        );
+        assert_eq!(query.highlight_words(), vec!["my vault", "foo:bar"]);
+        assert_eq!(parse("  - "), SearchQuery::default());
+    }
+
+    #[test]
+    fn match_filters() {
+        let vault = config("My Vault", &["Work", "Team A"]);
+        let locked = VaultState::Locked;
+
+        assert!(parse("").matches(&vault, locked));
+        assert!(parse("vault my").matches(&vault, locked));
+        assert!(parse("nextcloud").matches(&vault, locked));
+        assert!(parse("tag:work tag:\"team a\"").matches(&vault, locked));
+        assert!(!parse("tag:wor").matches(&vault, locked));
+        assert!(parse("backend:gocryptfs").matches(&vault, locked));
+        assert!(!parse("backend:cryfs").matches(&vault, locked));
+        assert!(parse("path:~/Nextcloud").matches(&vault, locked));
+        assert!(!parse("path:/home/alice/Documents").matches(&vault, locked));
+        assert!(parse("state:locked -state:open").matches(&vault, locked));
+        assert!(parse("state:open").matches(&vault, VaultState::Unlocked));
+        assert!(!parse("-tag:work").matches(&vault, locked));
+        assert!(!parse("tag:work other").matches(&vault, locked));
+    }
+
+    #[test]
+    fn highlight_matches() {
+        let words = vec!["va".to_string(), "ult".to_string(), "<".to_string()];
+        assert_eq!(
+            highlight("My Vault <3", &words),
+            "My <b>Vault</b> <b>&lt;</b>3"
+        );
+        assert_eq!(highlight("Ärger", &["ä".to_string()]), "<b>Ä</b>rger");
+        assert_eq!(highlight("a & b", &[]), "a &amp; b");
+        assert!(!contains_any("Private", &["x".to_string()]));
+    }
+}
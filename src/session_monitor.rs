@@ -0,0 +1,341 @@
+// session_monitor.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    config::APP_ID,
+    notifications,
+    user_config_manager::UserConfigManager,
+    vault::{self, Vault},
+};
+use gettextrs::gettext;
+use gtk::{
+    gio::{self, prelude::*, subclass::prelude::*},
+    glib,
+};
+use std::cell::{Cell, RefCell};
+
+static mut SESSION_MONITOR: Option<SessionMonitor> = None;
+
+const LOGIN_NAME: &str = "org.freedesktop.login1";
+const LOGIN_PATH: &str = "/org/freedesktop/login1";
+const LOGIN_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+// Only available on GNOME
+const IDLE_MONITOR_NAME: &str = "org.gnome.Mutter.IdleMonitor";
+const IDLE_MONITOR_PATH: &str = "/org/gnome/Mutter/IdleMonitor/Core";
+const IDLE_MONITOR_INTERFACE: &str = "org.gnome.Mutter.IdleMonitor";
+
+const IDLE_SETTING: &str = "lock-after-idle";
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct SessionMonitor {
+        pub login: RefCell<Option<gio::DBusProxy>>,
+        // Delays suspending until the vaults are locked, released by dropping
+        pub sleep_inhibitor: RefCell<Option<gio::UnixFDList>>,
+        pub idle_monitor: RefCell<Option<gio::DBusProxy>>,
+        pub idle_watch: Cell<Option<u32>>,
+        pub settings: gio::Settings,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SessionMonitor {
+        const NAME: &'static str = "SessionMonitor";
+        type ParentType = glib::Object;
+        type Type = super::SessionMonitor;
+
+        fn new() -> Self {
+            Self {
+                login: RefCell::new(None),
+                sleep_inhibitor: RefCell::new(None),
+                idle_monitor: RefCell::new(None),
+                idle_watch: Cell::new(None),
+                settings: gio::Settings::new(APP_ID),
+            }
+        }
+    }
+
+    impl ObjectImpl for SessionMonitor {}
+}
+
+glib::wrapper! {
+    pub struct SessionMonitor(ObjectSubclass<imp::SessionMonitor>);
+}
+
+impl SessionMonitor {
+    pub fn instance() -> Self {
+        unsafe {
+            #[allow(static_mut_refs)]
+            match SESSION_MONITOR.as_ref() {
+                Some(session_monitor) => session_monitor.clone(),
+                None => {
+                    let session_monitor: SessionMonitor = glib::Object::new();
+                    SESSION_MONITOR = Some(session_monitor.clone());
+                    session_monitor
+                }
+            }
+        }
+    }
+
+    /// Locks the vaults with session lock before the computer suspends and
+    /// after the user was idle for the configured time.
+    pub fn start(&self) {
+        log::trace!("start()");
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                obj.watch_sleep().await;
+            }
+        ));
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                obj.watch_idle().await;
+            }
+        ));
+
+        self.imp().settings.connect_changed(
+            Some(IDLE_SETTING),
+            glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    glib::spawn_future_local(glib::clone!(
+                        #[weak]
+                        obj,
+                        async move {
+                            obj.add_idle_watch().await;
+                        }
+                    ));
+                }
+            ),
+        );
+    }
+
+    async fn watch_sleep(&self) {
+        let login = match gio::DBusProxy::for_bus_future(
+            gio::BusType::System,
+            gio::DBusProxyFlags::DO_NOT_LOAD_PROPERTIES,
+            None,
+            LOGIN_NAME,
+            LOGIN_PATH,
+            LOGIN_INTERFACE,
+        )
+        .await
+        {
+            Ok(login) => login,
+            Err(e) => {
+                log::warn!("Cannot lock vaults on suspend: {}", e);
+                return;
+            }
+        };
+
+        login.connect_g_signal(
+            Some("PrepareForSleep"),
+            glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _, _, parameters| {
+                    let Some((is_sleeping,)) = parameters.get::<(bool,)>() else {
+                        return;
+                    };
+                    obj.prepare_for_sleep(is_sleeping);
+                }
+            ),
+        );
+        self.imp().login.replace(Some(login));
+
+        self.inhibit_sleep().await;
+    }
+
+    async fn inhibit_sleep(&self) {
+        let Some(login) = self.imp().login.borrow().clone() else {
+            return;
+        };
+
+        let result = login
+            .call_with_unix_fd_list_future(
+                "Inhibit",
+                Some(
+                    &(
+                        "sleep",
+                        "Vaults",
+                        gettext("Locking vaults").as_str(),
+                        "delay",
+                    )
+                        .to_variant(),
+                ),
+                gio::DBusCallFlags::NONE,
+                -1,
+                None::<&gio::UnixFDList>,
+            )
+            .await;
+
+        match result {
+            Ok((_, fd_list)) => {
+                self.imp().sleep_inhibitor.replace(fd_list);
+            }
+            Err(e) => {
+                log::warn!("Could not delay suspend: {}", e);
+            }
+        }
+    }
+
+    fn prepare_for_sleep(&self, is_sleeping: bool) {
+        log::trace!("prepare_for_sleep({})", is_sleeping);
+
+        if !is_sleeping {
+            glib::spawn_future_local(glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                async move {
+                    obj.inhibit_sleep().await;
+                }
+            ));
+            return;
+        }
+
+        self.lock_vaults(
+            gettext("The computer was suspended"),
+            glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move || {
+                    obj.imp().sleep_inhibitor.replace(None);
+                }
+            ),
+        );
+    }
+
+    async fn watch_idle(&self) {
+        let idle_monitor = match gio::DBusProxy::for_bus_future(
+            gio::BusType::Session,
+            gio::DBusProxyFlags::DO_NOT_LOAD_PROPERTIES,
+            None,
+            IDLE_MONITOR_NAME,
+            IDLE_MONITOR_PATH,
+            IDLE_MONITOR_INTERFACE,
+        )
+        .await
+        {
+            Ok(idle_monitor) => idle_monitor,
+            Err(e) => {
+                log::warn!("Cannot lock vaults when idle: {}", e);
+                return;
+            }
+        };
+
+        idle_monitor.connect_g_signal(
+            Some("WatchFired"),
+            glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _, _, parameters| {
+                    let Some((id,)) = parameters.get::<(u32,)>() else {
+                        return;
+                    };
+                    if obj.imp().idle_watch.get() == Some(id) {
+                        obj.lock_vaults(gettext("You were away"), || {});
+                    }
+                }
+            ),
+        );
+        self.imp().idle_monitor.replace(Some(idle_monitor));
+
+        self.add_idle_watch().await;
+    }
+
+    // Replaces the watch with one for the current setting
+    async fn add_idle_watch(&self) {
+        let Some(idle_monitor) = self.imp().idle_monitor.borrow().clone() else {
+            return;
+        };
+
+        if let Some(id) = self.imp().idle_watch.take() {
+            if let Err(e) = idle_monitor
+                .call_future(
+                    "RemoveWatch",
+                    Some(&(id,).to_variant()),
+                    gio::DBusCallFlags::NONE,
+                    -1,
+                )
+                .await
+            {
+                log::warn!("Could not remove idle watch: {}", e);
+            }
+        }
+
+        let minutes = self.imp().settings.int(IDLE_SETTING);
+        if minutes <= 0 {
+            return;
+        }
+
+        let interval = minutes as u64 * 60 * 1000;
+        match idle_monitor
+            .call_future(
+                "AddIdleWatch",
+                Some(&(interval,).to_variant()),
+                gio::DBusCallFlags::NONE,
+                -1,
+            )
+            .await
+        {
+            Ok(result) => {
+                self.imp()
+                    .idle_watch
+                    .set(result.get::<(u32,)>().map(|(id,)| id));
+            }
+            Err(e) => {
+                log::warn!("Could not add idle watch: {}", e);
+            }
+        }
+    }
+
+    // Locks the unlocked vaults with session lock and notifies the user
+    fn lock_vaults<F: FnOnce() + 'static>(&self, reason: String, callback: F) {
+        log::trace!("lock_vaults({})", reason);
+
+        let vaults: Vec<Vault> = UserConfigManager::instance()
+            .get_unlocked_vaults()
+            .into_iter()
+            .filter(|vault| vault.session_lock())
+            .collect();
+
+        for vault in &vaults {
+            notifications::withdraw(vault.get_uuid());
+        }
+
+        vault::lock_all(vaults.clone(), move |failures| {
+            for vault in vaults {
+                match failures.iter().find(|(failed, _)| *failed == vault) {
+                    Some((_, e)) => notifications::lock_failed(&vault, e),
+                    None => notifications::locked_automatically(&vault, &reason),
+                }
+            }
+
+            callback();
+        });
+    }
+}
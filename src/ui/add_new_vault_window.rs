@@ -75,6 +75,8 @@ mod imp {
         pub encrypted_data_directory_error_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub mount_directory_error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub descriptor_switch_row: TemplateChild<adw::SwitchRow>,
 
         pub current_page: RefCell<u32>,
 
@@ -106,6 +108,7 @@ mod imp {
                 mount_directory_button: TemplateChild::default(),
                 encrypted_data_directory_error_label: TemplateChild::default(),
                 mount_directory_error_label: TemplateChild::default(),
+                descriptor_switch_row: TemplateChild::default(),
 
                 current_page: RefCell::new(0),
 
@@ -677,6 +680,10 @@ impl AddNewVaultWindow {
         String::from(self.imp().password_entry_row.text().as_str())
     }
 
+    pub fn get_write_descriptor(&self) -> bool {
+        self.imp().descriptor_switch_row.is_active()
+    }
+
     pub fn get_vault(&self) -> Vault {
         let backend = backend::get_backend_from_ui_string(
             &self
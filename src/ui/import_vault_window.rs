@@ -21,12 +21,13 @@ use adw::prelude::{AdwDialogExt, ComboRowExt};
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gtk::{self, CompositeTemplate, gio, gio::File, glib, glib::GString, glib::clone, prelude::*};
-use std::cell::RefCell;
+use std::{cell::RefCell, path::Path};
 use strum::IntoEnumIterator;
 
 use crate::application::VApplication;
+use crate::config::APP_ID;
 use crate::util;
-use crate::{backend, vault::*};
+use crate::{backend, vault::*, vault_descriptor};
 
 mod imp {
     use gtk::glib::subclass::Signal;
@@ -71,6 +72,8 @@ mod imp {
         pub mount_directory_error_label: TemplateChild<gtk::Label>,
 
         pub current_page: RefCell<u32>,
+        pub descriptor_directory: RefCell<Option<String>>,
+        pub session_lock: RefCell<bool>,
     }
 
     #[glib::object_subclass]
@@ -99,6 +102,8 @@ mod imp {
                 mount_directory_error_label: TemplateChild::default(),
 
                 current_page: RefCell::new(0),
+                descriptor_directory: RefCell::new(None),
+                session_lock: RefCell::new(false),
             }
         }
 
@@ -424,10 +429,13 @@ impl ImportVaultDialog {
             .set_visible(false);
 
         let encrypted_data_directory = self.imp().encrypted_data_directory_entry_row.text();
-        let mount_directory = self.imp().mount_directory_entry_row.text();
 
         let is_edd_valid = self.is_encrypted_data_directory_valid(&encrypted_data_directory);
+        if is_edd_valid {
+            self.load_descriptor(&encrypted_data_directory);
+        }
 
+        let mount_directory = self.imp().mount_directory_entry_row.text();
         let is_md_valid = self.is_mount_directory_valid(&mount_directory);
 
         if !is_edd_valid || !is_md_valid {
@@ -598,10 +606,45 @@ impl ImportVaultDialog {
                     .as_str(),
             ),
             String::from(self.imp().mount_directory_entry_row.text().as_str()),
-            false,
+            *self.imp().session_lock.borrow(),
         )
     }
 
+    // Fills in the fields from the vault's descriptor, once per directory so
+    // the user can still change them
+    fn load_descriptor(&self, encrypted_data_directory: &str) {
+        if self.imp().descriptor_directory.borrow().as_deref() == Some(encrypted_data_directory) {
+            return;
+        }
+        self.imp()
+            .descriptor_directory
+            .replace(Some(encrypted_data_directory.to_string()));
+
+        let descriptor = match vault_descriptor::read(Path::new(encrypted_data_directory)) {
+            Some(Ok(descriptor)) => descriptor,
+            Some(Err(e)) => {
+                log::warn!("Ignoring invalid vault descriptor: {}", e);
+                return;
+            }
+            None => return,
+        };
+        log::info!("Found vault descriptor: {:?}", descriptor);
+
+        let mut mount_directory = gio::Settings::new(APP_ID)
+            .string("mount-directory")
+            .to_string();
+        if !mount_directory.ends_with('/') {
+            mount_directory.push('/');
+        }
+        mount_directory.push_str(&descriptor.mount_directory_name);
+
+        self.imp().name_entry_row.set_text(&descriptor.name);
+        self.imp()
+            .mount_directory_entry_row
+            .set_text(&mount_directory);
+        self.imp().session_lock.replace(descriptor.session_lock);
+    }
+
     fn fill_combo_box_text(&self) {
         let list = gtk::StringList::new(&[]);
 
@@ -621,70 +664,30 @@ impl ImportVaultDialog {
     }
 
     fn is_valid_backend(&self, path: &String) -> bool {
-        match std::fs::read_dir(path) {
-            Ok(dir) => {
-                for file in dir.into_iter() {
-                    match file {
-                        Ok(f) => {
-                            let file_name = f.file_name();
-
-                            if file_name == "gocryptfs.conf" {
-                                self.imp()
-                                    .encrypted_data_directory_info_label
-                                    .set_text(&gettext("Found gocryptfs configuration file."));
-
-                                self.imp()
-                                    .encrypted_data_directory_info_label
-                                    .set_visible(true);
-
-                                let model = self.imp().combo_row_backend.model().unwrap();
-                                for (position, item) in model.iter::<glib::Object>().enumerate() {
-                                    if let Ok(object) = item {
-                                        let string_object =
-                                            object.downcast::<gtk::StringObject>().unwrap();
-                                        if string_object.string().eq("gocryptfs") {
-                                            self.imp()
-                                                .combo_row_backend
-                                                .set_selected(position as u32);
-                                        }
-                                    }
-                                }
-                                return true;
-                            }
+        if let Some(backend) = backend::detect_backend(Path::new(path)) {
+            let info = match backend {
+                backend::Backend::Cryfs => gettext("Found CryFS configuration file."),
+                backend::Backend::Gocryptfs => gettext("Found gocryptfs configuration file."),
+            };
+            self.imp()
+                .encrypted_data_directory_info_label
+                .set_text(&info);
 
-                            if file_name == "cryfs.config" {
-                                self.imp()
-                                    .encrypted_data_directory_info_label
-                                    .set_text(&gettext("Found CryFS configuration file."));
-
-                                self.imp()
-                                    .encrypted_data_directory_info_label
-                                    .set_visible(true);
-
-                                let model = self.imp().combo_row_backend.model().unwrap();
-                                for (position, item) in model.iter::<glib::Object>().enumerate() {
-                                    if let Ok(object) = item {
-                                        let string_object =
-                                            object.downcast::<gtk::StringObject>().unwrap();
-                                        if string_object.string().eq("CryFS") {
-                                            self.imp()
-                                                .combo_row_backend
-                                                .set_selected(position as u32);
-                                        }
-                                    }
-                                }
-                                return true;
-                            }
-                        }
-                        Err(e) => {
-                            log::debug!("Invalid file: {}", e);
-                        }
+            self.imp()
+                .encrypted_data_directory_info_label
+                .set_visible(true);
+
+            let ui_string = backend::get_ui_string_from_backend(&backend);
+            let model = self.imp().combo_row_backend.model().unwrap();
+            for (position, item) in model.iter::<glib::Object>().enumerate() {
+                if let Ok(object) = item {
+                    let string_object = object.downcast::<gtk::StringObject>().unwrap();
+                    if string_object.string().eq(&ui_string) {
+                        self.imp().combo_row_backend.set_selected(position as u32);
                     }
                 }
             }
-            Err(e) => {
-                log::debug!("Could not read path {}: {}", path, e);
-            }
+            return true;
         }
 
         self.imp()
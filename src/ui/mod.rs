@@ -3,9 +3,13 @@ pub mod pages;
 mod add_new_vault_window;
 mod import_vault_window;
 mod preferences;
+mod vault_backup_dialog;
+mod vault_bundle_dialog;
 mod window;
 
 pub use add_new_vault_window::AddNewVaultWindow;
 pub use import_vault_window::ImportVaultDialog;
 pub use preferences::VaultsSettingsWindow;
+pub use vault_backup_dialog::VaultBackupDialog;
+pub use vault_bundle_dialog::VaultBundleDialog;
 pub use window::ApplicationWindow;
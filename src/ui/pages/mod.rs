@@ -1,7 +1,13 @@
 mod vaults_page_row;
+mod vaults_page_row_convert_dialog;
+mod vaults_page_row_details_window;
+mod vaults_page_row_move_dialog;
 mod vaults_page_row_password_prompt_window;
 mod vaults_page_row_settings_window;
 
 pub use vaults_page_row::VaultsPageRow;
+pub use vaults_page_row_convert_dialog::VaultsPageRowConvertDialog;
+pub use vaults_page_row_details_window::VaultsPageRowDetailsWindow;
+pub use vaults_page_row_move_dialog::VaultsPageRowMoveDialog;
 pub use vaults_page_row_password_prompt_window::VaultsPageRowPasswordPromptWindow;
 pub use vaults_page_row_settings_window::VaultsPageRowSettingsWindow;
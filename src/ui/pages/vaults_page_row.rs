@@ -23,28 +23,28 @@ use gettextrs::gettext;
 use glib::{clone, subclass};
 use gtk::CompositeTemplate;
 use gtk::gio;
-use gtk::gio::Mount;
-use gtk::gio::VolumeMonitor;
 use gtk::glib::subclass::Signal;
-use gtk::glib::{self, closure_local};
+use gtk::glib::{self, Properties, closure_local};
 use gtk::prelude::*;
 use once_cell::sync::Lazy;
-use std::cell::RefCell;
-use std::process::Command;
-use uuid::Uuid;
+use std::cell::{Cell, RefCell};
 
-use super::{VaultsPageRowPasswordPromptWindow, VaultsPageRowSettingsWindow};
+use super::{
+    VaultsPageRowDetailsWindow, VaultsPageRowPasswordPromptWindow, VaultsPageRowSettingsWindow,
+};
 use crate::{
     VApplication,
     backend::{Backend, BackendError},
     config::APP_ID,
+    notifications, search_query,
     vault::*,
 };
 
 mod imp {
     use super::*;
 
-    #[derive(Debug, CompositeTemplate)]
+    #[derive(Debug, CompositeTemplate, Properties)]
+    #[properties(wrapper_type = super::VaultsPageRow)]
     #[template(resource = "/io/github/mpobaschnig/Vaults/vaults_page_row.ui")]
     pub struct VaultsPageRow {
         #[template_child]
@@ -52,20 +52,29 @@ mod imp {
         #[template_child]
         pub select_vault_button: TemplateChild<gtk::CheckButton>,
         #[template_child]
+        pub favorite_image: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub cleanup_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub open_folder_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub locker_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub details_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub settings_button: TemplateChild<gtk::Button>,
 
         pub spinner: RefCell<gtk::Spinner>,
 
-        pub uuid: RefCell<Option<Uuid>>,
-        pub config: RefCell<Option<VaultConfig>>,
+        pub vault: RefCell<Option<Vault>>,
 
-        pub volume_monitor: RefCell<VolumeMonitor>,
+        #[property(get, set, builder(VaultState::Locked))]
+        pub state: Cell<VaultState>,
 
         pub settings: gio::Settings,
+
+        // Search terms to highlight in the title and subtitle
+        pub highlight: RefCell<Vec<String>>,
     }
 
     #[glib::object_subclass]
@@ -78,14 +87,17 @@ mod imp {
             Self {
                 vaults_page_row: TemplateChild::default(),
                 select_vault_button: TemplateChild::default(),
+                favorite_image: TemplateChild::default(),
+                cleanup_button: TemplateChild::default(),
                 open_folder_button: TemplateChild::default(),
                 locker_button: TemplateChild::default(),
+                details_button: TemplateChild::default(),
                 settings_button: TemplateChild::default(),
-                uuid: RefCell::new(None),
-                config: RefCell::new(None),
+                vault: RefCell::new(None),
+                state: Cell::new(VaultState::Locked),
                 spinner: RefCell::new(gtk::Spinner::new()),
-                volume_monitor: RefCell::new(VolumeMonitor::get()),
                 settings: gio::Settings::new(APP_ID),
+                highlight: RefCell::new(vec![]),
             }
         }
 
@@ -98,6 +110,7 @@ mod imp {
         }
     }
 
+    #[glib::derived_properties]
     impl ObjectImpl for VaultsPageRow {
         fn constructed(&self) {
             let obj = self.obj();
@@ -148,22 +161,40 @@ impl VaultsPageRow {
     pub fn new(vault: Vault) -> Self {
         let object: Self = glib::Object::new();
 
-        object.imp().vaults_page_row.set_title(&vault.name());
-        object.imp().uuid.replace(Some(vault.get_uuid()));
-        object.imp().config.replace(Some(vault.config()));
+        object.imp().vault.replace(Some(vault.clone()));
 
-        if vault.is_mounted() {
-            object.set_vault_row_state_opened();
-        }
+        vault.connect_config_notify(clone!(
+            #[weak]
+            object,
+            move |_| {
+                object.update_title();
+            }
+        ));
+        object.update_title();
 
-        if !vault.is_backend_available() {
-            object.set_vault_row_state_backend_unavailable();
-        }
+        vault
+            .bind_property("state", &object, "state")
+            .sync_create()
+            .build();
+
+        object.update_state();
 
         object
     }
 
     pub fn setup_connect_handlers(&self) {
+        self.connect_state_notify(|obj| {
+            obj.update_state();
+        });
+
+        self.imp().cleanup_button.connect_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.cleanup_button_clicked();
+            }
+        ));
+
         self.imp().open_folder_button.connect_clicked(clone!(
             #[weak(rename_to = obj)]
             self,
@@ -180,6 +211,14 @@ impl VaultsPageRow {
             }
         ));
 
+        self.imp().details_button.connect_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.details_button_clicked();
+            }
+        ));
+
         self.imp().settings_button.connect_clicked(clone!(
             #[weak(rename_to = obj)]
             self,
@@ -188,28 +227,6 @@ impl VaultsPageRow {
             }
         ));
 
-        self.imp()
-            .volume_monitor
-            .borrow()
-            .connect_mount_added(clone!(
-                #[weak(rename_to = obj)]
-                self,
-                move |_, m| {
-                    obj.mount_added_triggered(m);
-                }
-            ));
-
-        self.imp()
-            .volume_monitor
-            .borrow()
-            .connect_mount_removed(clone!(
-                #[weak(rename_to = obj)]
-                self,
-                move |_, m| {
-                    obj.mount_removed_triggered(m);
-                }
-            ));
-
         let window = gtk::gio::Application::default()
             .unwrap()
             .downcast_ref::<VApplication>()
@@ -219,19 +236,12 @@ impl VaultsPageRow {
             #[weak(rename_to = obj)]
             self,
             move |_| {
-                let config = obj.imp().config.borrow().clone().unwrap();
-                if config.session_lock {
-                    let vault = obj.get_vault();
-
-                    if !vault.is_backend_available() {
-                        obj.set_vault_row_state_backend_unavailable();
-                        return;
-                    } else {
-                        obj.set_vault_row_state_backend_available();
-                    }
+                let vault = obj.get_vault();
+                if vault.session_lock() {
+                    vault.refresh_state();
 
-                    if obj.is_mounted() {
-                        obj.locker_button_clicked_is_mounted(vault);
+                    if vault.state() == VaultState::Unlocked {
+                        obj.lock(vault, Some(gettext("The screen was locked")));
                     }
                 }
             }
@@ -241,32 +251,21 @@ impl VaultsPageRow {
     fn open_folder_button_clicked(&self) {
         log::trace!("open_folder_button_clicked");
 
-        let output_res = Command::new("xdg-open")
-            .arg(&self.imp().config.borrow().as_ref().unwrap().mount_directory)
-            .output();
-
-        if let Err(e) = output_res {
-            log::error!("Failed to open folder: {}", e);
-        }
+        self.get_vault().open_folder();
     }
 
-    fn locker_button_clicked_is_mounted(&self, vault: Vault) {
-        log::trace!("locker_button_clicked_is_mounted");
-
-        if !self.imp().open_folder_button.is_visible() {
-            self.set_vault_row_state_opened();
-            return;
-        }
+    fn cleanup_button_clicked(&self) {
+        log::trace!("cleanup_button_clicked");
 
         if self.imp().spinner.borrow().is_spinning() {
             return;
         }
 
-        self.imp().open_folder_button.set_sensitive(false);
+        self.imp().cleanup_button.set_sensitive(false);
 
         *self.imp().spinner.borrow_mut() = gtk::Spinner::new();
         let spinner = self.imp().spinner.borrow().clone();
-        self.imp().locker_button.set_child(Some(&spinner));
+        self.imp().cleanup_button.set_child(Some(&spinner));
 
         spinner.start();
 
@@ -276,55 +275,34 @@ impl VaultsPageRow {
         }
 
         let (sender, receiver) = async_channel::unbounded();
-        let vault_config = vault.config().clone();
-        let locker_button = self.imp().locker_button.clone();
-        let open_folder_button = self.imp().open_folder_button.clone();
-        let settings_button = self.imp().settings_button.clone();
-        let vaults_page_row = self.imp().vaults_page_row.clone();
-        glib::spawn_future_local(clone!(async move {
-            while let Ok(message) = receiver.recv().await {
-                match message {
-                    Message::Finished => {
-                        locker_button.set_icon_name("changes-prevent-symbolic");
-                        locker_button.set_tooltip_text(Some(&gettext("Open Vault")));
-                        open_folder_button.set_visible(false);
-                        open_folder_button.set_sensitive(false);
-                        settings_button.set_sensitive(true);
-                    }
-                    Message::Error(e) => {
-                        log::error!("Error closing vault: {}", &e);
-
-                        locker_button.set_icon_name("changes-allow-symbolic");
-                        locker_button.set_tooltip_text(Some(&gettext("Close Vault")));
-                        open_folder_button.set_visible(true);
-                        open_folder_button.set_sensitive(true);
-                        settings_button.set_sensitive(false);
-
-                        let vault_name = vaults_page_row.title().to_string();
-                        gtk::glib::MainContext::default().spawn_local(async move {
-                            let window = gtk::gio::Application::default()
-                                .unwrap()
-                                .downcast_ref::<VApplication>()
-                                .unwrap()
-                                .active_window()
-                                .unwrap()
-                                .clone();
-                            let info_dialog = gtk::AlertDialog::builder()
-                                .message(&vault_name)
-                                .detail(format!("{}", e))
-                                .modal(true)
-                                .build();
-
-                            info_dialog.show(Some(&window));
-                        });
+        let vault = self.get_vault();
+        let vault_config = vault.config();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                while let Ok(message) = receiver.recv().await {
+                    obj.imp()
+                        .cleanup_button
+                        .set_icon_name("edit-clear-symbolic");
+                    obj.imp().cleanup_button.set_sensitive(true);
+
+                    match message {
+                        Message::Finished => {
+                            vault.refresh_state();
+                        }
+                        Message::Error(e) => {
+                            log::error!("Error cleaning up vault: {}", &e);
+                            show_error_dialog(vault.name(), &e);
+                        }
                     }
-                }
 
-                spinner.stop();
+                    spinner.stop();
+                }
             }
-        }));
+        ));
 
-        std::thread::spawn(move || match Backend::close(&vault_config) {
+        std::thread::spawn(move || match Backend::cleanup(&vault_config) {
             Ok(_) => gio::spawn_blocking(move || {
                 sender
                     .send_blocking(Message::Finished)
@@ -338,15 +316,35 @@ impl VaultsPageRow {
         });
     }
 
-    fn locker_button_clicked_is_not_mounted(&self, vault: Vault) {
-        log::trace!("locker_button_clicked_is_not_mounted");
+    /// Locks the vault. With a reason, the lock was not requested by the user,
+    /// who is notified about it.
+    fn lock(&self, vault: Vault, reason: Option<String>) {
+        log::trace!("lock({:?})", reason);
 
-        if self.imp().open_folder_button.is_visible() {
-            self.set_vault_row_state_closed();
+        if vault.is_busy() {
             return;
         }
 
-        if self.imp().spinner.borrow().is_spinning() {
+        notifications::withdraw(vault.get_uuid());
+        vault.lock_in_background(move |vault, result| match result {
+            Ok(_) => {
+                if let Some(reason) = reason {
+                    notifications::locked_automatically(vault, &reason);
+                }
+            }
+            Err(e) => {
+                log::error!("Error closing vault: {}", &e);
+                report_error(vault, &e, notifications::lock_failed);
+            }
+        });
+    }
+
+    /// Asks for the password and unlocks the vault.
+    pub fn unlock(&self) {
+        log::trace!("unlock");
+
+        let vault = self.get_vault();
+        if vault.is_busy() {
             return;
         }
 
@@ -356,87 +354,24 @@ impl VaultsPageRow {
             "unlock",
             false,
             closure_local!(
-                #[strong(rename_to = obj)]
-                self,
+                #[strong]
+                vault,
                 move |dialog: VaultsPageRowPasswordPromptWindow| {
-                    let password = dialog.get_password();
-
-                    obj.imp().settings_button.set_sensitive(false);
-                    obj.imp().open_folder_button.set_sensitive(false);
-
-                    *obj.imp().spinner.borrow_mut() = gtk::Spinner::new();
-                    let spinner = obj.imp().spinner.borrow().clone();
-                    obj.imp().locker_button.set_child(Some(&spinner));
-
-                    spinner.start();
-
-                    enum Message {
-                        Finished,
-                        Error(BackendError),
-                    }
-
-                    let (sender, receiver) = async_channel::unbounded();
-                    let vault_config = vault.config().clone();
-                    let locker_button = obj.imp().locker_button.clone();
-                    let open_folder_button = obj.imp().open_folder_button.clone();
-                    let settings_button = obj.imp().settings_button.clone();
-                    let vaults_page_row = obj.imp().vaults_page_row.clone();
-                    glib::spawn_future_local(clone!(async move {
-                        while let Ok(message) = receiver.recv().await {
-                            match message {
-                                Message::Finished => {
-                                    locker_button.set_icon_name("changes-allow-symbolic");
-                                    locker_button.set_tooltip_text(Some(&gettext("Close Vault")));
-                                    open_folder_button.set_visible(true);
-                                    open_folder_button.set_sensitive(true);
-                                    settings_button.set_sensitive(false);
-                                }
-                                Message::Error(e) => {
-                                    log::error!("Error opening vault: {}", &e);
-
-                                    locker_button.set_icon_name("changes-prevent-symbolic");
-                                    locker_button.set_tooltip_text(Some(&gettext("Open Vault")));
-                                    open_folder_button.set_visible(false);
-                                    open_folder_button.set_sensitive(false);
-                                    settings_button.set_sensitive(true);
-
-                                    let vault_name = vaults_page_row.title().to_string();
-                                    gtk::glib::MainContext::default().spawn_local(async move {
-                                        let window = gtk::gio::Application::default()
-                                            .unwrap()
-                                            .downcast_ref::<VApplication>()
-                                            .unwrap()
-                                            .active_window()
-                                            .unwrap()
-                                            .clone();
-
-                                        let info_dialog = gtk::AlertDialog::builder()
-                                            .modal(true)
-                                            .message(&vault_name)
-                                            .detail(format!("{}", e))
-                                            .build();
-
-                                        info_dialog.show(Some(&window));
-                                    });
+                    notifications::withdraw(vault.get_uuid());
+                    vault.unlock_in_background(
+                        dialog.get_password(),
+                        |vault, result| match result {
+                            Ok(_) => {
+                                if !notifications::is_app_focused() {
+                                    notifications::unlocked(vault);
                                 }
                             }
-
-                            spinner.stop();
-                        }
-                    }));
-
-                    std::thread::spawn(move || match Backend::open(&vault_config, password) {
-                        Ok(_) => gio::spawn_blocking(move || {
-                            sender
-                                .send_blocking(Message::Finished)
-                                .expect("Channel needs to be open");
-                        }),
-                        Err(e) => gio::spawn_blocking(move || {
-                            sender
-                                .send_blocking(Message::Error(e))
-                                .expect("Channel needs to be open");
-                        }),
-                    });
+                            Err(e) => {
+                                log::error!("Error opening vault: {}", &e);
+                                report_error(vault, &e, notifications::unlock_failed);
+                            }
+                        },
+                    );
                 }
             ),
         );
@@ -445,30 +380,37 @@ impl VaultsPageRow {
             .unwrap()
             .downcast_ref::<VApplication>()
             .unwrap()
-            .active_window()
-            .unwrap()
-            .clone();
+            .active_window();
 
-        AdwDialogExt::present(&dialog, Some(&window));
+        AdwDialogExt::present(&dialog, window.as_ref());
     }
 
     fn locker_button_clicked(&self) {
         log::trace!("Locker button clicked");
 
         let vault = self.get_vault();
+        vault.refresh_state();
 
-        if !vault.is_backend_available() {
-            self.set_vault_row_state_backend_unavailable();
-            return;
-        } else {
-            self.set_vault_row_state_backend_available();
+        match vault.state() {
+            VaultState::Unlocked => self.lock(vault, None),
+            VaultState::Locked => self.unlock(),
+            state => log::debug!("Ignoring locker button in state {:?}", state),
         }
+    }
 
-        if self.is_mounted() {
-            self.locker_button_clicked_is_mounted(vault);
-        } else {
-            self.locker_button_clicked_is_not_mounted(vault);
-        }
+    fn details_button_clicked(&self) {
+        log::trace!("details_button_clicked");
+
+        let dialog = VaultsPageRowDetailsWindow::new(self.get_vault());
+
+        let window = gtk::gio::Application::default()
+            .unwrap()
+            .downcast_ref::<VApplication>()
+            .unwrap()
+            .active_window()
+            .unwrap()
+            .clone();
+        AdwDialogExt::present(&dialog, Some(&window));
     }
 
     fn settings_button_clicked(&self) {
@@ -482,17 +424,10 @@ impl VaultsPageRow {
             closure_local!(
                 #[strong(rename_to = obj)]
                 self,
-                move |dialog: VaultsPageRowSettingsWindow| {
+                move |_: VaultsPageRowSettingsWindow| {
                     obj.emit_by_name::<()>("save", &[]);
 
-                    let vault = &dialog.create_vault_from_settings();
-                    if !vault.is_backend_available() {
-                        obj.set_vault_row_state_backend_unavailable();
-                    } else {
-                        obj.set_vault_row_state_backend_available();
-                    }
-
-                    obj.set_vault(vault.clone());
+                    obj.get_vault().refresh_state();
                 }
             ),
         );
@@ -523,163 +458,147 @@ impl VaultsPageRow {
     pub fn get_vault(&self) -> Vault {
         log::trace!("get_vault");
 
-        let uuid = self.imp().uuid.borrow().unwrap();
-        let config = self.imp().config.borrow().clone();
-        match config {
-            Some(config) => Vault::new(
-                uuid,
-                config.name,
-                config.backend,
-                config.encrypted_data_directory,
-                config.mount_directory,
-                config.session_lock,
-            ),
-            _ => {
+        match self.imp().vault.borrow().as_ref() {
+            Some(vault) => vault.clone(),
+            None => {
                 log::error!("Vault not initialized!");
                 Vault::new_none()
             }
         }
     }
 
-    pub fn set_vault(&self, vault: Vault) {
-        log::trace!("set_vault");
-
-        let uuid = vault.get_uuid();
-        let name = vault.name();
-        let config = vault.config();
-        self.imp().uuid.replace(Some(uuid));
-        self.imp().vaults_page_row.set_title(&name);
-        self.imp().config.replace(Some(config));
-    }
-
     pub fn get_name(&self) -> String {
-        self.imp().vaults_page_row.title().to_string()
+        self.get_vault().name()
     }
 
-    fn is_mounted(&self) -> bool {
-        log::trace!("is_mounted");
-        self.get_vault().is_mounted()
-    }
+    pub fn set_highlight(&self, words: Vec<String>) {
+        self.imp().highlight.replace(words);
+        self.update_title();
 
-    fn set_vault_row_state_opened(&self) {
-        log::trace!("set_vault_row_state_opened");
-
-        self.imp()
-            .locker_button
-            .set_icon_name("changes-allow-symbolic");
-        self.imp()
-            .locker_button
-            .set_tooltip_text(Some(&gettext("Close Vault")));
-        self.imp().open_folder_button.set_visible(true);
-        self.imp().open_folder_button.set_sensitive(true);
-        self.imp().settings_button.set_sensitive(false);
+        if matches!(self.state(), VaultState::Locked | VaultState::Unlocked) {
+            self.update_subtitle();
+        }
     }
 
-    fn set_vault_row_state_closed(&self) {
-        log::trace!("set_vault_row_state_closed");
-
-        self.imp()
-            .locker_button
-            .set_icon_name("changes-prevent-symbolic");
+    fn update_title(&self) {
+        let title = search_query::highlight(&self.get_name(), &self.imp().highlight.borrow());
+        self.imp().vaults_page_row.set_title(&title);
         self.imp()
-            .locker_button
-            .set_tooltip_text(Some(&gettext("Open Vault")));
-        self.imp().open_folder_button.set_visible(false);
-        self.imp().open_folder_button.set_sensitive(true);
-        self.imp().settings_button.set_sensitive(true);
+            .favorite_image
+            .set_visible(self.get_vault().config().favorite);
     }
 
-    fn set_vault_row_state_backend_unavailable(&self) {
-        log::trace!("set_vault_row_state_backend_unavailable");
-        self.imp()
-            .vaults_page_row
-            .set_subtitle(&gettext("Backend is not installed."));
-        self.imp().locker_button.set_sensitive(false);
+    // Shows the path a search matched, if any
+    fn update_subtitle(&self) {
+        let words = self.imp().highlight.borrow();
+        let config = self.get_vault().config();
+
+        let subtitle = [&config.encrypted_data_directory, &config.mount_directory]
+            .into_iter()
+            .find(|path| search_query::contains_any(path, &words))
+            .map(|path| search_query::highlight(path, &words))
+            .unwrap_or_default();
+        self.imp().vaults_page_row.set_subtitle(&subtitle);
     }
 
-    fn set_vault_row_state_backend_available(&self) {
-        log::trace!("set_vault_row_state_backend_available");
-        self.imp().vaults_page_row.set_subtitle("");
-        self.imp().locker_button.set_sensitive(true);
-    }
+    fn update_state(&self) {
+        log::trace!("update_state({:?})", self.state());
 
-    fn mount_added_triggered(&self, mount: &Mount) {
-        log::trace!("mount_added_triggered({:?})", mount.name());
-
-        let config_mount_directory = self.imp().config.borrow().clone().unwrap().mount_directory;
-
-        let config_mount_directory_path = std::path::Path::new(&config_mount_directory)
-            .canonicalize()
-            .unwrap();
-
-        let config_mount_directory_file_name = config_mount_directory_path.file_name();
-
-        match config_mount_directory_file_name {
-            Some(config_mount_directory_file_name) => {
-                match config_mount_directory_file_name.to_str() {
-                    Some(file_name) => {
-                        let eq_name = mount.name() == file_name;
-                        let eq_path =
-                            mount.default_location().path().unwrap() == config_mount_directory_path;
-                        if eq_name && eq_path {
-                            log::debug!(
-                                "Setting row state opened for mount with name: \"{}\", and path \"{}\"",
-                                file_name,
-                                config_mount_directory_path.display()
-                            );
-                            self.set_vault_row_state_opened();
-                        }
-                    }
-                    None => {
-                        log::error!("Could not get mount directory path");
-                    }
-                }
+        let imp = self.imp();
+        let state = self.state();
+
+        imp.cleanup_button
+            .set_visible(state == VaultState::Disconnected);
+
+        match state {
+            VaultState::Unlocking | VaultState::Locking => {
+                *imp.spinner.borrow_mut() = gtk::Spinner::new();
+                let spinner = imp.spinner.borrow().clone();
+                imp.locker_button.set_child(Some(&spinner));
+                spinner.start();
+
+                imp.locker_button.set_sensitive(true);
+                imp.open_folder_button.set_sensitive(false);
+                imp.settings_button.set_sensitive(false);
+                return;
             }
-            None => {
-                log::error!("Could not get config mount directory file name");
+            VaultState::BackendMissing => {
+                imp.vaults_page_row
+                    .set_subtitle(&gettext("Backend is not installed."));
+                imp.locker_button.set_sensitive(false);
             }
-        }
-    }
-
-    fn mount_removed_triggered(&self, mount: &Mount) {
-        log::trace!("mount_removed_triggered({:?})", mount.name());
-
-        let config_mount_directory = self.imp().config.borrow().clone().unwrap().mount_directory;
-
-        let config_mount_directory_path = std::path::Path::new(&config_mount_directory)
-            .canonicalize()
-            .unwrap();
-
-        let config_mount_directory_file_name = config_mount_directory_path.file_name();
-
-        match config_mount_directory_file_name {
-            Some(config_mount_directory_file_name) => {
-                match config_mount_directory_file_name.to_str() {
-                    Some(file_name) => {
-                        let eq_name = mount.name() == file_name;
-                        let eq_path =
-                            mount.default_location().path().unwrap() == config_mount_directory_path;
-                        if eq_name && eq_path {
-                            log::debug!(
-                                "Setting row state closed for mount with name: \"{}\", and path \"{}\"",
-                                file_name,
-                                config_mount_directory_path.display()
-                            );
-                            self.set_vault_row_state_closed();
-                        }
-                    }
-                    None => {
-                        log::error!("Could not get mount directory path");
-                    }
-                }
+            VaultState::Disconnected => {
+                imp.vaults_page_row.set_subtitle(&gettext("Disconnected"));
+                imp.locker_button.set_sensitive(false);
             }
-            None => {
-                log::error!("Could not get config mount directory file name");
+            VaultState::Busy => {
+                imp.vaults_page_row.set_subtitle(&gettext("Busy"));
+                imp.locker_button.set_sensitive(false);
+            }
+            VaultState::Error => {
+                imp.vaults_page_row
+                    .set_subtitle(&self.get_vault().error_message());
+                imp.locker_button.set_sensitive(true);
+            }
+            VaultState::Locked | VaultState::Unlocked => {
+                self.update_subtitle();
+                imp.locker_button.set_sensitive(true);
             }
         }
+
+        imp.spinner.borrow().stop();
+
+        let is_unlocked = match state {
+            VaultState::Unlocked => true,
+            VaultState::Error => self.get_vault().is_mounted(),
+            _ => false,
+        };
+
+        if is_unlocked {
+            imp.locker_button.set_icon_name("changes-allow-symbolic");
+            imp.locker_button
+                .set_tooltip_text(Some(&gettext("Close Vault")));
+        } else {
+            imp.locker_button.set_icon_name("changes-prevent-symbolic");
+            imp.locker_button
+                .set_tooltip_text(Some(&gettext("Open Vault")));
+        }
+
+        imp.open_folder_button.set_visible(is_unlocked);
+        imp.open_folder_button.set_sensitive(is_unlocked);
+        imp.settings_button
+            .set_sensitive(!is_unlocked && state != VaultState::Disconnected);
     }
 
     pub fn selected(&self) -> bool {
         self.imp().select_vault_button.is_active()
     }
 }
+
+// Shows the error in the window the user is looking at, or as notification
+fn report_error(vault: &Vault, e: &BackendError, notify: fn(&Vault, &BackendError)) {
+    if notifications::is_app_focused() {
+        show_error_dialog(vault.name(), e);
+    } else {
+        notify(vault, e);
+    }
+}
+
+fn show_error_dialog(vault_name: String, e: &BackendError) {
+    let detail = format!("{}", e);
+    gtk::glib::MainContext::default().spawn_local(async move {
+        let window = gtk::gio::Application::default()
+            .unwrap()
+            .downcast_ref::<VApplication>()
+            .unwrap()
+            .active_window();
+
+        let info_dialog = gtk::AlertDialog::builder()
+            .modal(true)
+            .message(&vault_name)
+            .detail(detail)
+            .build();
+
+        info_dialog.show(window.as_ref());
+    });
+}
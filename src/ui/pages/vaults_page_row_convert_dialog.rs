@@ -0,0 +1,621 @@
+// vaults_page_row_convert_dialog.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::{self, Backend, BackendError};
+use crate::user_config_manager::UserConfigManager;
+use crate::vault::{Vault, VaultConfig};
+use crate::vault_conversion::{self, PendingConversion};
+use crate::vault_descriptor;
+use crate::vault_relocation::{self, Progress, RelocationError};
+use adw::{
+    prelude::{AdwDialogExt, ComboRowExt},
+    subclass::{dialog::AdwDialogImpl, prelude::*},
+};
+use gettextrs::gettext;
+use gtk::{
+    self, CompositeTemplate,
+    glib::{self, Properties, clone, subclass::Signal},
+    prelude::*,
+};
+use once_cell::sync::Lazy;
+use std::{
+    cell::RefCell,
+    fs,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use strum::IntoEnumIterator;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, CompositeTemplate, Properties, Default)]
+    #[properties(wrapper_type = super::VaultsPageRowConvertDialog)]
+    #[template(resource = "/io/github/mpobaschnig/Vaults/vaults_page_row_convert_dialog.ui")]
+    pub struct VaultsPageRowConvertDialog {
+        #[template_child]
+        pub settings_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub backend_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub destination_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub destination_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub password_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub password_entry_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub same_password_switch_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub new_password_entry_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub confirm_password_entry_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub cancel_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub convert_button: TemplateChild<gtk::Button>,
+        #[property(get, set, name = "vault", construct_only)]
+        pub vault: RefCell<Option<Vault>>,
+        // Backends the vault can be converted to, in the order of the combo row
+        pub backends: RefCell<Vec<Backend>>,
+        // Set while converting, cancels the worker thread
+        pub cancelled: RefCell<Option<Arc<AtomicBool>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VaultsPageRowConvertDialog {
+        const NAME: &'static str = "VaultsPageRowConvertDialog";
+        type ParentType = adw::Dialog;
+        type Type = super::VaultsPageRowConvertDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for VaultsPageRowConvertDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let vault = obj.vault().unwrap();
+
+            let backends: Vec<Backend> = Backend::iter()
+                .filter(|backend| *backend != vault.backend())
+                .collect();
+            let list = gtk::StringList::new(&[]);
+            for backend in &backends {
+                list.append(&backend::get_ui_string_from_backend(backend));
+            }
+            self.backend_combo_row.set_model(Some(&list));
+            self.convert_button.set_sensitive(!backends.is_empty());
+            self.backends.replace(backends);
+            obj.update_destination();
+
+            self.backend_combo_row.connect_selected_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.update_destination();
+                }
+            ));
+
+            self.same_password_switch_row
+                .bind_property("active", &self.new_password_entry_row.get(), "visible")
+                .invert_boolean()
+                .sync_create()
+                .build();
+            self.same_password_switch_row
+                .bind_property("active", &self.confirm_password_entry_row.get(), "visible")
+                .invert_boolean()
+                .sync_create()
+                .build();
+
+            self.destination_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.destination_button_clicked();
+                }
+            ));
+
+            self.convert_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.start();
+                }
+            ));
+
+            self.cancel_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.cancel();
+                }
+            ));
+
+            obj.connect_close_attempt(|obj| {
+                obj.cancel();
+            });
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> =
+                Lazy::new(|| vec![Signal::builder("converted").build()]);
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for VaultsPageRowConvertDialog {}
+    impl AdwDialogImpl for VaultsPageRowConvertDialog {}
+}
+
+glib::wrapper! {
+    pub struct VaultsPageRowConvertDialog(ObjectSubclass<imp::VaultsPageRowConvertDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+enum Message {
+    Step(String),
+    Progress(Progress),
+    // With a warning if the old vault could not be locked again
+    Finished(Option<String>),
+    Cancelled,
+    Error(String),
+}
+
+impl VaultsPageRowConvertDialog {
+    pub fn new(vault: Vault) -> Self {
+        glib::Object::builder().property("vault", vault).build()
+    }
+
+    /// Emitted once the vault uses the new backend.
+    pub fn connect_converted<F: Fn() + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_local("converted", false, move |_| {
+            callback();
+            None
+        })
+    }
+
+    fn selected_backend(&self) -> Option<Backend> {
+        let imp = self.imp();
+        let position = imp.backend_combo_row.selected() as usize;
+        imp.backends.borrow().get(position).copied()
+    }
+
+    fn update_destination(&self) {
+        let Some(backend) = self.selected_backend() else {
+            return;
+        };
+
+        let encrypted_data_directory = self.vault().unwrap().encrypted_data_directory();
+        let destination =
+            vault_conversion::default_destination(Path::new(&encrypted_data_directory), backend);
+        self.imp()
+            .destination_entry_row
+            .set_text(&destination.to_string_lossy());
+    }
+
+    fn destination_button_clicked(&self) {
+        log::trace!("destination_button_clicked()");
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Choose New Encrypted Data Directory"))
+                    .modal(true)
+                    .accept_label(gettext("Select"))
+                    .build();
+
+                let root = obj.root().and_downcast::<gtk::Window>();
+                let Ok(folder) = dialog.select_folder_future(root.as_ref()).await else {
+                    return;
+                };
+                let Some(folder) = folder.path() else {
+                    return;
+                };
+
+                obj.imp()
+                    .destination_entry_row
+                    .set_text(&folder.to_string_lossy());
+            }
+        ));
+    }
+
+    fn start(&self) {
+        log::trace!("start()");
+
+        let imp = self.imp();
+        let vault = self.vault().unwrap();
+        let Some(backend) = self.selected_backend() else {
+            return;
+        };
+        let source = vault.config();
+        let destination = imp.destination_entry_row.text().to_string();
+
+        // Only one old encrypted data directory is kept track of per vault
+        if vault_conversion::pending(&vault.get_uuid()).is_some() {
+            self.show_status(&gettext(
+                "Delete the old encrypted data of the last conversion in the vault settings before converting the vault again.",
+            ));
+            return;
+        }
+
+        let password = imp.password_entry_row.text().to_string();
+        if password.is_empty() {
+            self.show_status(&gettext("Enter the current password."));
+            return;
+        }
+        let new_password = if imp.same_password_switch_row.is_active() {
+            password.clone()
+        } else {
+            let new_password = imp.new_password_entry_row.text().to_string();
+            if new_password.is_empty() {
+                self.show_status(&gettext("Enter a new password."));
+                return;
+            }
+            if new_password != imp.confirm_password_entry_row.text() {
+                self.show_status(&gettext("The new passwords do not match."));
+                return;
+            }
+            new_password
+        };
+
+        if let Err(e) = vault_relocation::check_destination(
+            Path::new(&source.encrypted_data_directory),
+            Path::new(&destination),
+            false,
+        ) {
+            self.show_status(&e);
+            return;
+        }
+
+        let mut target = vault_conversion::converted_config(&source, backend, &destination);
+        if !matches!(backend.is_available(&target), Ok(true)) {
+            self.show_status(&format!(
+                "{}: {}",
+                gettext("Backend is not installed"),
+                backend::get_ui_string_from_backend(&backend)
+            ));
+            return;
+        }
+
+        // The old vault is not unlocked at its mount directory, so it is not
+        // seen as unlocked while it is converted
+        let mount_directory = Path::new(&source.mount_directory);
+        let source_mount_directory = vault_conversion::source_mount_directory(mount_directory);
+        let temporary_mount_directory =
+            vault_conversion::temporary_mount_directory(mount_directory);
+        for directory in [&source_mount_directory, &temporary_mount_directory] {
+            if fs::read_dir(directory).is_ok_and(|mut dir| dir.next().is_some()) {
+                self.show_status(&format!(
+                    "{}: {}",
+                    gettext("The folder is not empty"),
+                    directory.display()
+                ));
+                return;
+            }
+        }
+        let mut mounted_source = source.clone();
+        mounted_source.mount_directory = source_mount_directory.to_string_lossy().to_string();
+        target.mount_directory = temporary_mount_directory.to_string_lossy().to_string();
+
+        // The vault cannot be opened until converting finished or failed
+        if !vault.mark_busy() {
+            self.show_status(&gettext("Lock the vault before converting it."));
+            return;
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        imp.cancelled.replace(Some(cancelled.clone()));
+        self.set_running(true);
+
+        let (sender, receiver) = async_channel::unbounded();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            source,
+            #[strong]
+            target,
+            async move {
+                while let Ok(message) = receiver.recv().await {
+                    match message {
+                        Message::Step(step) => obj.show_status(&step),
+                        Message::Progress(progress) => obj.show_progress(&progress),
+                        Message::Finished(warning) => {
+                            obj.finish(&source, &target, warning.as_deref())
+                        }
+                        Message::Cancelled => obj.fail(&gettext("Converting was cancelled.")),
+                        Message::Error(e) => obj.fail(&e),
+                    }
+                }
+            }
+        ));
+
+        std::thread::spawn(move || {
+            let send = |message: Message| {
+                sender
+                    .send_blocking(message)
+                    .expect("Channel needs to be open");
+            };
+
+            send(convert(
+                &mounted_source,
+                &target,
+                password,
+                new_password,
+                &cancelled,
+                &send,
+            ));
+        });
+    }
+
+    fn cancel(&self) {
+        log::trace!("cancel()");
+
+        match self.imp().cancelled.borrow().as_ref() {
+            Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+            None => {
+                self.close();
+            }
+        }
+    }
+
+    // Both trees match, so the vault can use the new data. The old data is
+    // only deleted once the user confirms it in the settings.
+    fn finish(&self, source: &VaultConfig, target: &VaultConfig, warning: Option<&str>) {
+        log::trace!("finish({:?})", warning);
+
+        let vault = self.vault().unwrap();
+        let mut config = target.clone();
+        config.mount_directory = source.mount_directory.clone();
+
+        if vault_descriptor::exists(Path::new(&source.encrypted_data_directory)) {
+            if let Err(e) = vault_descriptor::write(&config) {
+                log::error!("Could not write vault descriptor: {}", e);
+            }
+        }
+
+        // Nothing is recorded as pending before the vault uses the new data,
+        // so the old data cannot be deleted while it is still needed
+        if let Err(e) = UserConfigManager::instance().change_vault(vault.get_uuid(), config) {
+            log::error!("Could not save converted vault: {}", e);
+            let mut status = format!(
+                "{}: {}\n\n{}: {}",
+                gettext("The vault was converted, but it could not be saved"),
+                e,
+                gettext("New Encrypted Data"),
+                target.encrypted_data_directory
+            );
+            if let Some(warning) = warning {
+                status = format!("{}\n\n{}", status, warning);
+            }
+            self.fail(&status);
+            return;
+        }
+        let pending = PendingConversion {
+            encrypted_data_directory: source.encrypted_data_directory.clone(),
+            backend: source.backend,
+        };
+        if let Err(e) = vault_conversion::set_pending(vault.get_uuid(), pending) {
+            log::error!("Could not record conversion: {}", e);
+        }
+        vault.clear_busy();
+
+        let imp = self.imp();
+        imp.cancelled.replace(None);
+        self.set_running(false);
+        imp.settings_group.set_sensitive(false);
+        imp.password_group.set_sensitive(false);
+        imp.convert_button.set_visible(false);
+        imp.cancel_button.set_label(&gettext("_Close"));
+        self.emit_by_name::<()>("converted", &[]);
+
+        let mut status = format!(
+            "{}\n\n{}: {}",
+            gettext(
+                "The vault was converted. Its old encrypted data is kept until you delete it in the vault settings."
+            ),
+            gettext("Old Encrypted Data"),
+            source.encrypted_data_directory
+        );
+        if let Some(warning) = warning {
+            status = format!("{}\n\n{}", status, warning);
+        }
+        self.show_status(&status);
+    }
+
+    fn fail(&self, message: &str) {
+        log::trace!("fail({})", message);
+
+        self.vault().unwrap().clear_busy();
+        self.imp().cancelled.replace(None);
+        self.set_running(false);
+        self.show_status(message);
+    }
+
+    fn set_running(&self, running: bool) {
+        let imp = self.imp();
+
+        imp.settings_group.set_sensitive(!running);
+        imp.password_group.set_sensitive(!running);
+        imp.convert_button.set_sensitive(!running);
+        imp.progress_bar.set_visible(running);
+        self.set_can_close(!running);
+    }
+
+    fn show_progress(&self, progress: &Progress) {
+        let imp = self.imp();
+
+        imp.progress_bar.set_fraction(progress.fraction());
+        imp.progress_bar.set_text(Some(&format!(
+            "{} / {}",
+            glib::format_size(progress.done_bytes),
+            glib::format_size(progress.total_bytes)
+        )));
+        self.show_status(&format!(
+            "{}: {} / {}",
+            progress.phase.label(),
+            progress.done_files,
+            progress.total_files
+        ));
+    }
+
+    fn show_status(&self, status: &str) {
+        let status_label = &self.imp().status_label;
+        status_label.set_text(status);
+        status_label.set_visible(true);
+    }
+}
+
+// Creates the new vault, unlocks both, copies the files from the old vault
+// into the new one and compares them. The new vault is deleted again unless
+// this succeeds.
+fn convert(
+    source: &VaultConfig,
+    target: &VaultConfig,
+    password: String,
+    new_password: String,
+    cancelled: &AtomicBool,
+    send: &dyn Fn(Message),
+) -> Message {
+    log::trace!("convert({:?}, {:?})", source, target);
+
+    send(Message::Step(gettext("Creating the new vault…")));
+    if let Err(e) = Backend::init(target, new_password.clone()) {
+        remove_new_vault(target);
+        return error(&gettext("Could not create the new vault"), e);
+    }
+
+    send(Message::Step(gettext("Unlocking both vaults…")));
+    if let Err(e) = fs::create_dir_all(&source.mount_directory) {
+        remove_new_vault(target);
+        log::error!("Could not create mount directory: {}", e);
+        return Message::Error(format!(
+            "{}: {}",
+            gettext("Could not create the mount directory"),
+            e
+        ));
+    }
+    if let Err(e) = Backend::open(source, password) {
+        let _ = fs::remove_dir(&source.mount_directory);
+        remove_new_vault(target);
+        return error(&gettext("Could not unlock the vault"), e);
+    }
+    if let Err(e) = Backend::open(target, new_password) {
+        close(source);
+        remove_new_vault(target);
+        return error(&gettext("Could not unlock the new vault"), e);
+    }
+
+    let from = Path::new(&source.mount_directory);
+    let to = Path::new(&target.mount_directory);
+    let mut progress = |progress: Progress| send(Message::Progress(progress));
+    let result = vault_relocation::copy(from, to, cancelled, &mut progress)
+        .and_then(|_| vault_relocation::verify(from, to, cancelled, &mut progress));
+
+    send(Message::Step(gettext("Locking both vaults…")));
+    if let Err(e) = Backend::close(target) {
+        // Still in use, so it is kept
+        close(source);
+        return error(&gettext("Could not lock the new vault"), e);
+    }
+    if let Err(e) = fs::remove_dir(to) {
+        log::warn!("Could not remove temporary mount directory: {}", e);
+    }
+
+    // The verified copy is still good if only the old vault stays unlocked
+    let still_unlocked = match Backend::close(source) {
+        Ok(_) => {
+            if let Err(e) = fs::remove_dir(from) {
+                log::warn!("Could not remove temporary mount directory: {}", e);
+            }
+            None
+        }
+        Err(e) => {
+            log::error!("Could not lock the vault: {}", e);
+            Some(format!(
+                "{}: {}\n{}",
+                gettext("The old vault could not be locked and is still unlocked"),
+                from.display(),
+                e
+            ))
+        }
+    };
+
+    match result {
+        Ok(_) => Message::Finished(still_unlocked),
+        Err(e) => {
+            remove_new_vault(target);
+            let message = match e {
+                RelocationError::Cancelled if still_unlocked.is_none() => {
+                    return Message::Cancelled;
+                }
+                RelocationError::Cancelled => gettext("Converting was cancelled."),
+                e => format!("{}: {}", gettext("Could not copy the files"), e),
+            };
+            match still_unlocked {
+                Some(still_unlocked) => {
+                    Message::Error(format!("{}\n\n{}", message, still_unlocked))
+                }
+                None => Message::Error(message),
+            }
+        }
+    }
+}
+
+// Locks the old vault and removes its temporary mount directory
+fn close(config: &VaultConfig) {
+    match Backend::close(config) {
+        Ok(_) => {
+            let _ = fs::remove_dir(&config.mount_directory);
+        }
+        Err(e) => log::error!("Could not lock {}: {}", config.name, e),
+    }
+}
+
+fn remove_new_vault(target: &VaultConfig) {
+    if let Err(e) = fs::remove_dir_all(&target.encrypted_data_directory) {
+        log::error!("Could not remove new encrypted data: {}", e);
+    }
+    let _ = fs::remove_dir(&target.mount_directory);
+}
+
+fn error(message: &str, e: BackendError) -> Message {
+    log::error!("{}: {}", message, e);
+    Message::Error(format!("{}: {}", message, e))
+}
@@ -0,0 +1,319 @@
+// vaults_page_row_details_window.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::{self, info, options};
+use crate::vault::{Vault, VaultState};
+use crate::vault_usage;
+use adw::{
+    prelude::{ActionRowExt, AdwDialogExt, PreferencesGroupExt},
+    subclass::{dialog::AdwDialogImpl, prelude::*},
+};
+use gettextrs::gettext;
+use gtk::{
+    self, CompositeTemplate, gio,
+    glib::{self, Properties, clone},
+    prelude::*,
+};
+use std::{cell::RefCell, future::Future, path::PathBuf};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, CompositeTemplate, Properties, Default)]
+    #[properties(wrapper_type = super::VaultsPageRowDetailsWindow)]
+    #[template(resource = "/io/github/mpobaschnig/Vaults/vaults_page_row_details_window.ui")]
+    pub struct VaultsPageRowDetailsWindow {
+        #[template_child]
+        pub backend_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub backend_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub version_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub encrypted_size_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub encrypted_files_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub encrypted_free_space_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub mounted_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub mounted_size_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub mounted_files_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub mounted_free_space_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub created_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub last_unlock_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub last_lock_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub unlock_count_row: TemplateChild<adw::ActionRow>,
+        #[property(get, set, name = "vault", construct_only)]
+        pub vault: RefCell<Option<Vault>>,
+        pub state_handler: RefCell<Option<glib::SignalHandlerId>>,
+        // Running computations, stopped when the window is closed
+        pub tasks: RefCell<Vec<glib::JoinHandle<()>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VaultsPageRowDetailsWindow {
+        const NAME: &'static str = "VaultsPageRowDetailsWindow";
+        type ParentType = adw::Dialog;
+        type Type = super::VaultsPageRowDetailsWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for VaultsPageRowDetailsWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let vault = obj.vault().unwrap();
+            obj.set_title(&vault.name());
+
+            // Opening or closing the vault while the window is shown
+            let state_handler = vault.connect_state_notify(clone!(
+                #[weak]
+                obj,
+                move |vault| {
+                    obj.update_usage();
+                    if matches!(vault.state(), VaultState::Locked | VaultState::Unlocked) {
+                        obj.update_mounted_statistics();
+                    }
+                }
+            ));
+            self.state_handler.replace(Some(state_handler));
+
+            obj.update_backend();
+            obj.update_encrypted_statistics();
+            obj.update_mounted_statistics();
+            obj.update_usage();
+        }
+    }
+
+    impl WidgetImpl for VaultsPageRowDetailsWindow {}
+    impl AdwDialogImpl for VaultsPageRowDetailsWindow {
+        fn closed(&self) {
+            if let Some(state_handler) = self.state_handler.take() {
+                self.obj().vault().unwrap().disconnect(state_handler);
+            }
+
+            for task in self.tasks.take() {
+                task.abort();
+            }
+
+            self.parent_closed();
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct VaultsPageRowDetailsWindow(ObjectSubclass<imp::VaultsPageRowDetailsWindow>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl VaultsPageRowDetailsWindow {
+    pub fn new(vault: Vault) -> Self {
+        glib::Object::builder().property("vault", vault).build()
+    }
+
+    fn spawn(&self, future: impl Future<Output = ()> + 'static) {
+        let task = glib::spawn_future_local(future);
+        self.imp().tasks.borrow_mut().push(task);
+    }
+
+    fn update_backend(&self) {
+        log::trace!("update_backend()");
+
+        let config = self.vault().unwrap().config();
+        self.imp()
+            .backend_row
+            .set_subtitle(&backend::get_ui_string_from_backend(&config.backend));
+
+        // Runs the binary, unless it was probed before
+        let binary_path = config.backend.get_binary_path(&config);
+        self.spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let version =
+                    gio::spawn_blocking(move || binary_path.and_then(|p| info::probe(&p).version))
+                        .await
+                        .ok()
+                        .flatten();
+
+                obj.imp().version_row.set_subtitle(
+                    &version
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| gettext("Unknown")),
+                );
+            }
+        ));
+
+        self.spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let encrypted_data_directory = PathBuf::from(&config.encrypted_data_directory);
+                let result = gio::spawn_blocking(move || {
+                    options::creation_options(&config.backend, &encrypted_data_directory)
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(creation_options)) => {
+                        for option in creation_options {
+                            let row = adw::ActionRow::builder()
+                                .title(option.name)
+                                .subtitle(option.value)
+                                .subtitle_selectable(true)
+                                .use_markup(false)
+                                .css_classes(["property"])
+                                .build();
+                            obj.imp().backend_group.add(&row);
+                        }
+                    }
+                    Ok(Err(e)) => log::warn!("Could not read creation options: {}", e),
+                    Err(_) => log::error!("Reading creation options panicked"),
+                }
+            }
+        ));
+    }
+
+    fn update_encrypted_statistics(&self) {
+        let imp = self.imp();
+        let file = gio::File::for_path(self.vault().unwrap().encrypted_data_directory());
+
+        self.measure(
+            file.clone(),
+            imp.encrypted_size_row.get(),
+            imp.encrypted_files_row.get(),
+        );
+        self.query_free_space(file, imp.encrypted_free_space_row.get());
+    }
+
+    fn update_mounted_statistics(&self) {
+        let imp = self.imp();
+        let vault = self.vault().unwrap();
+
+        let is_unlocked = vault.state() == VaultState::Unlocked;
+        imp.mounted_group.set_visible(is_unlocked);
+        if !is_unlocked {
+            return;
+        }
+
+        let calculating = gettext("Calculating…");
+        for row in [
+            &imp.mounted_size_row,
+            &imp.mounted_files_row,
+            &imp.mounted_free_space_row,
+        ] {
+            row.set_subtitle(&calculating);
+        }
+
+        let file = gio::File::for_path(vault.mount_directory());
+        self.measure(
+            file.clone(),
+            imp.mounted_size_row.get(),
+            imp.mounted_files_row.get(),
+        );
+        self.query_free_space(file, imp.mounted_free_space_row.get());
+    }
+
+    fn measure(&self, file: gio::File, size_row: adw::ActionRow, files_row: adw::ActionRow) {
+        log::trace!("measure({:?})", file.path());
+
+        self.spawn(async move {
+            let (measurement, _progress) = file.measure_disk_usage_future(
+                gio::FileMeasureFlags::APPARENT_SIZE | gio::FileMeasureFlags::NO_XDEV,
+                glib::Priority::LOW,
+            );
+
+            match measurement.await {
+                Ok((size, _directories, files)) => {
+                    size_row.set_subtitle(&glib::format_size(size));
+                    files_row.set_subtitle(&files.to_string());
+                }
+                Err(e) => {
+                    log::warn!("Could not measure {:?}: {}", file.path(), e);
+                    size_row.set_subtitle(&gettext("Not available"));
+                    files_row.set_subtitle(&gettext("Not available"));
+                }
+            }
+        });
+    }
+
+    fn query_free_space(&self, file: gio::File, row: adw::ActionRow) {
+        log::trace!("query_free_space({:?})", file.path());
+
+        self.spawn(async move {
+            let info = file
+                .query_filesystem_info_future(
+                    gio::FILE_ATTRIBUTE_FILESYSTEM_FREE,
+                    glib::Priority::DEFAULT,
+                )
+                .await;
+
+            match info {
+                Ok(info) => row.set_subtitle(&glib::format_size(
+                    info.attribute_uint64(gio::FILE_ATTRIBUTE_FILESYSTEM_FREE),
+                )),
+                Err(e) => {
+                    log::warn!("Could not query free space of {:?}: {}", file.path(), e);
+                    row.set_subtitle(&gettext("Not available"));
+                }
+            }
+        });
+    }
+
+    fn update_usage(&self) {
+        log::trace!("update_usage()");
+
+        let usage = vault_usage::read(&self.vault().unwrap().get_uuid());
+        let imp = self.imp();
+
+        imp.created_row
+            .set_subtitle(&format_time(usage.created, gettext("Unknown")));
+        imp.last_unlock_row
+            .set_subtitle(&format_time(usage.last_unlock, gettext("Never")));
+        imp.last_lock_row
+            .set_subtitle(&format_time(usage.last_lock, gettext("Never")));
+        imp.unlock_count_row
+            .set_subtitle(&usage.unlock_count.to_string());
+    }
+}
+
+fn format_time(time: Option<i64>, fallback: String) -> String {
+    time.and_then(|time| glib::DateTime::from_unix_local(time).ok())
+        .and_then(|date_time| date_time.format("%c").ok())
+        .map(|date_time| date_time.to_string())
+        .unwrap_or(fallback)
+}
@@ -0,0 +1,413 @@
+// vaults_page_row_move_dialog.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::user_config_manager::UserConfigManager;
+use crate::vault::Vault;
+use crate::vault_relocation::{self, PendingRelocation, Progress, RelocationError};
+use adw::{
+    prelude::AdwDialogExt,
+    subclass::{dialog::AdwDialogImpl, prelude::*},
+};
+use gettextrs::gettext;
+use gtk::{
+    self, CompositeTemplate, gio,
+    glib::{self, Properties, clone, subclass::Signal},
+    prelude::*,
+};
+use once_cell::sync::Lazy;
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, CompositeTemplate, Properties, Default)]
+    #[properties(wrapper_type = super::VaultsPageRowMoveDialog)]
+    #[template(resource = "/io/github/mpobaschnig/Vaults/vaults_page_row_move_dialog.ui")]
+    pub struct VaultsPageRowMoveDialog {
+        #[template_child]
+        pub destination_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub destination_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub cancel_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub move_button: TemplateChild<gtk::Button>,
+        #[property(get, set, name = "vault", construct_only)]
+        pub vault: RefCell<Option<Vault>>,
+        // Set while moving, cancels the worker thread
+        pub cancelled: RefCell<Option<Arc<AtomicBool>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VaultsPageRowMoveDialog {
+        const NAME: &'static str = "VaultsPageRowMoveDialog";
+        type ParentType = adw::Dialog;
+        type Type = super::VaultsPageRowMoveDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for VaultsPageRowMoveDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let vault = obj.vault().unwrap();
+
+            // Continue where a cancelled or interrupted move stopped
+            if let Some(pending) =
+                vault_relocation::pending(&vault.get_uuid(), &vault.encrypted_data_directory())
+            {
+                self.destination_entry_row.set_text(&pending.destination);
+                obj.show_status(&gettext(
+                    "An earlier move was not finished. Moving again continues where it stopped.",
+                ));
+            }
+
+            self.destination_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.destination_button_clicked();
+                }
+            ));
+
+            self.move_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.start();
+                }
+            ));
+
+            self.cancel_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.cancel();
+                }
+            ));
+
+            obj.connect_close_attempt(|obj| {
+                obj.cancel();
+            });
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> =
+                Lazy::new(|| vec![Signal::builder("moved").build()]);
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for VaultsPageRowMoveDialog {}
+    impl AdwDialogImpl for VaultsPageRowMoveDialog {}
+}
+
+glib::wrapper! {
+    pub struct VaultsPageRowMoveDialog(ObjectSubclass<imp::VaultsPageRowMoveDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+enum Message {
+    Progress(Progress),
+    Finished,
+    Error(RelocationError),
+}
+
+impl VaultsPageRowMoveDialog {
+    pub fn new(vault: Vault) -> Self {
+        glib::Object::builder().property("vault", vault).build()
+    }
+
+    /// Emitted once the data was moved and the vault points to it.
+    pub fn connect_moved<F: Fn() + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_local("moved", false, move |_| {
+            callback();
+            None
+        })
+    }
+
+    fn destination_button_clicked(&self) {
+        log::trace!("destination_button_clicked()");
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Choose New Location"))
+                    .modal(true)
+                    .accept_label(gettext("Select"))
+                    .build();
+
+                let root = obj.root().and_downcast::<gtk::Window>();
+                let Ok(folder) = dialog.select_folder_future(root.as_ref()).await else {
+                    return;
+                };
+                let Some(folder) = folder.path() else {
+                    return;
+                };
+
+                // The data keeps its folder name
+                let encrypted_data_directory = obj.vault().unwrap().encrypted_data_directory();
+                let destination = match Path::new(&encrypted_data_directory).file_name() {
+                    Some(name) => folder.join(name),
+                    None => folder,
+                };
+                obj.imp()
+                    .destination_entry_row
+                    .set_text(&destination.to_string_lossy());
+            }
+        ));
+    }
+
+    fn start(&self) {
+        log::trace!("start()");
+
+        let imp = self.imp();
+        let vault = self.vault().unwrap();
+        let uuid = vault.get_uuid();
+        let source = vault.encrypted_data_directory();
+        let destination = imp.destination_entry_row.text().to_string();
+
+        let resume = vault_relocation::pending(&uuid, &source)
+            .is_some_and(|pending| pending.destination == destination);
+        if let Err(e) =
+            vault_relocation::check_destination(Path::new(&source), Path::new(&destination), resume)
+        {
+            self.show_status(&e);
+            return;
+        }
+
+        // The vault cannot be opened until the move finished or failed
+        if !vault.mark_busy() {
+            self.show_status(&gettext("Lock the vault before moving its encrypted data."));
+            return;
+        }
+
+        let pending = PendingRelocation {
+            source: source.clone(),
+            destination: destination.clone(),
+        };
+        if let Err(e) = vault_relocation::set_pending(uuid, pending) {
+            log::error!("Could not record move: {}", e);
+            vault.clear_busy();
+            self.show_status(&format!("{}: {}", gettext("Could not start moving"), e));
+            return;
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        imp.cancelled.replace(Some(cancelled.clone()));
+        self.set_running(true);
+
+        let (sender, receiver) = async_channel::unbounded();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            source,
+            #[strong]
+            destination,
+            async move {
+                while let Ok(message) = receiver.recv().await {
+                    match message {
+                        Message::Progress(progress) => obj.show_progress(&progress),
+                        Message::Finished => obj.finish(&source, &destination).await,
+                        Message::Error(e) => obj.fail(e),
+                    }
+                }
+            }
+        ));
+
+        std::thread::spawn(move || {
+            let source = PathBuf::from(source);
+            let destination = PathBuf::from(destination);
+            let mut progress = |progress: Progress| {
+                sender
+                    .send_blocking(Message::Progress(progress))
+                    .expect("Channel needs to be open");
+            };
+
+            let result = vault_relocation::copy(&source, &destination, &cancelled, &mut progress)
+                .and_then(|_| {
+                    vault_relocation::verify(&source, &destination, &cancelled, &mut progress)
+                });
+
+            sender
+                .send_blocking(match result {
+                    Ok(_) => Message::Finished,
+                    Err(e) => Message::Error(e),
+                })
+                .expect("Channel needs to be open");
+        });
+    }
+
+    fn cancel(&self) {
+        log::trace!("cancel()");
+
+        match self.imp().cancelled.borrow().as_ref() {
+            Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+            None => {
+                self.close();
+            }
+        }
+    }
+
+    // The copy is verified, so the vault can use it
+    async fn finish(&self, source: &str, destination: &str) {
+        log::trace!("finish({}, {})", source, destination);
+
+        let vault = self.vault().unwrap();
+        let mut vault_config = vault.config();
+        vault_config.encrypted_data_directory = destination.to_string();
+
+        // The old location is the only one the vault can be found with, until
+        // the new one is saved. The copy is kept, moving again resumes it.
+        if let Err(e) = UserConfigManager::instance().change_vault(vault.get_uuid(), vault_config) {
+            log::error!("Could not save new location: {}", e);
+            vault.clear_busy();
+            self.imp().cancelled.replace(None);
+            self.set_running(false);
+            self.show_status(&format!(
+                "{}: {}",
+                gettext("The files were copied, but the new location could not be saved"),
+                e
+            ));
+            return;
+        }
+        vault_relocation::clear_pending(&vault.get_uuid());
+
+        // The vault could still have been opened outside of this window, e.g.
+        // from the command line
+        if vault.is_mounted() {
+            vault.clear_busy();
+            self.imp().cancelled.replace(None);
+            self.set_running(false);
+            self.emit_by_name::<()>("moved", &[]);
+            self.imp().move_button.set_visible(false);
+            self.imp().cancel_button.set_label(&gettext("_Close"));
+            self.show_status(
+                &gettext("The vault was moved, but it is open, so its old location was kept: {}")
+                    .replace("{}", source),
+            );
+            return;
+        }
+
+        self.show_status(&gettext("Deleting the old location…"));
+        let old_location = source.to_string();
+        let result = gio::spawn_blocking(move || std::fs::remove_dir_all(old_location)).await;
+
+        vault.clear_busy();
+        self.imp().cancelled.replace(None);
+        self.set_running(false);
+        self.emit_by_name::<()>("moved", &[]);
+
+        match result {
+            Ok(Ok(_)) => {
+                self.close();
+            }
+            Ok(Err(e)) => {
+                log::error!("Could not delete old location: {}", e);
+                self.imp().move_button.set_visible(false);
+                self.imp().cancel_button.set_label(&gettext("_Close"));
+                self.show_status(&format!(
+                    "{}: {}",
+                    gettext("The vault was moved, but its old location could not be deleted"),
+                    e
+                ));
+            }
+            Err(_) => log::error!("Deleting old location panicked"),
+        }
+    }
+
+    fn fail(&self, e: RelocationError) {
+        log::trace!("fail({})", e);
+
+        self.vault().unwrap().clear_busy();
+        self.imp().cancelled.replace(None);
+        self.set_running(false);
+
+        match e {
+            RelocationError::Cancelled => self.show_status(&gettext(
+                "Moving was cancelled. Moving again continues where it stopped.",
+            )),
+            e => {
+                log::error!("Could not move encrypted data: {}", e);
+                self.show_status(&format!(
+                    "{}: {}",
+                    gettext("Could not move encrypted data"),
+                    e
+                ));
+            }
+        }
+    }
+
+    fn set_running(&self, running: bool) {
+        let imp = self.imp();
+
+        imp.destination_entry_row.set_sensitive(!running);
+        imp.move_button.set_sensitive(!running);
+        imp.progress_bar.set_visible(running);
+        self.set_can_close(!running);
+    }
+
+    fn show_progress(&self, progress: &Progress) {
+        let imp = self.imp();
+
+        imp.progress_bar.set_fraction(progress.fraction());
+        imp.progress_bar.set_text(Some(&format!(
+            "{} / {}",
+            glib::format_size(progress.done_bytes),
+            glib::format_size(progress.total_bytes)
+        )));
+
+        self.show_status(&format!(
+            "{}: {} / {}",
+            progress.phase.label(),
+            progress.done_files,
+            progress.total_files
+        ));
+    }
+
+    fn show_status(&self, status: &str) {
+        let status_label = &self.imp().status_label;
+        status_label.set_text(status);
+        status_label.set_visible(true);
+    }
+}
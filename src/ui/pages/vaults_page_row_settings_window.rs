@@ -18,11 +18,17 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::application::VApplication;
-use crate::ui::pages::vaults_page_row_settings_window;
-use crate::vault::Vault;
-use crate::{backend, backend::Backend, user_config_manager::UserConfigManager};
+use crate::ui::VaultBackupDialog;
+use crate::ui::pages::{
+    VaultsPageRowConvertDialog, VaultsPageRowMoveDialog, vaults_page_row_settings_window,
+};
+use crate::vault::{Vault, VaultConfig};
+use crate::{
+    backend, backend::Backend, user_config_manager::UserConfigManager, vault_backup,
+    vault_conversion, vault_descriptor, vault_tags,
+};
 use adw::{
-    prelude::{ComboRowExt, EntryRowExt},
+    prelude::{AdwDialogExt, AlertDialogExt, ComboRowExt, EntryRowExt},
     subclass::{dialog::AdwDialogImpl, prelude::*},
 };
 use gettextrs::gettext;
@@ -32,8 +38,9 @@ use gtk::{
     prelude::*,
 };
 use once_cell::sync::Lazy;
-use std::cell::RefCell;
+use std::{cell::RefCell, path::Path};
 use strum::IntoEnumIterator;
+use uuid::Uuid;
 
 mod imp {
     use super::*;
@@ -55,7 +62,33 @@ mod imp {
         #[template_child]
         pub mount_directory_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub tags_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub favorite_switch_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
         pub lock_screen_switch_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub custom_binary_expander_row: TemplateChild<adw::ExpanderRow>,
+        #[template_child]
+        pub custom_binary_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub custom_binary_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub descriptor_switch_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub move_data_button_row: TemplateChild<adw::ButtonRow>,
+        #[template_child]
+        pub backup_button_row: TemplateChild<adw::ButtonRow>,
+        #[template_child]
+        pub convert_button_row: TemplateChild<adw::ButtonRow>,
+        #[template_child]
+        pub remove_button_row: TemplateChild<adw::ButtonRow>,
+        #[template_child]
+        pub delete_data_button_row: TemplateChild<adw::ButtonRow>,
+        #[template_child]
+        pub delete_previous_data_button_row: TemplateChild<adw::ButtonRow>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[property(get, set, name = "vault", construct)]
         pub vault: RefCell<Option<Vault>>,
     }
@@ -105,6 +138,26 @@ mod imp {
             self.lock_screen_switch_row
                 .set_active(self.obj().vault().unwrap().session_lock());
 
+            let vault_config = self.obj().vault().unwrap().config();
+            self.tags_entry_row
+                .set_text(&vault_tags::to_text(&vault_config.tags));
+            self.favorite_switch_row.set_active(vault_config.favorite);
+            self.custom_binary_expander_row
+                .set_enable_expansion(vault_config.use_custom_binary);
+            self.custom_binary_expander_row
+                .set_expanded(vault_config.use_custom_binary);
+            self.custom_binary_entry_row
+                .set_text(&vault_config.custom_binary_path);
+
+            self.descriptor_switch_row
+                .set_active(vault_descriptor::exists(Path::new(
+                    &vault_config.encrypted_data_directory,
+                )));
+
+            self.delete_previous_data_button_row.set_visible(
+                vault_conversion::pending(&self.obj().vault().unwrap().get_uuid()).is_some(),
+            );
+
             self.obj().connect_vault_notify(clone!(move |obj| {
                 obj.emit_by_name::<()>("save", &[]);
             }));
@@ -150,6 +203,22 @@ mod imp {
                 }
             ));
 
+            self.tags_entry_row.connect_apply(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().apply_changes();
+                }
+            ));
+
+            self.favorite_switch_row.connect_active_notify(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().apply_changes();
+                }
+            ));
+
             self.lock_screen_switch_row.connect_active_notify(clone!(
                 #[weak(rename_to = s)]
                 self,
@@ -157,6 +226,88 @@ mod imp {
                     s.obj().apply_changes();
                 }
             ));
+
+            self.custom_binary_expander_row
+                .connect_enable_expansion_notify(clone!(
+                    #[weak(rename_to = s)]
+                    self,
+                    move |_| {
+                        s.obj().apply_changes();
+                    }
+                ));
+
+            self.custom_binary_entry_row.connect_apply(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().apply_changes();
+                }
+            ));
+
+            self.custom_binary_button.connect_clicked(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().custom_binary_button_clicked();
+                }
+            ));
+
+            self.descriptor_switch_row.connect_active_notify(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().update_descriptor();
+                }
+            ));
+
+            self.move_data_button_row.connect_activated(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().move_data_button_row_activated();
+                }
+            ));
+
+            self.backup_button_row.connect_activated(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().backup_button_row_activated();
+                }
+            ));
+
+            self.convert_button_row.connect_activated(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().convert_button_row_activated();
+                }
+            ));
+
+            self.remove_button_row.connect_activated(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().emit_by_name::<()>("remove", &[]);
+                }
+            ));
+
+            self.delete_data_button_row.connect_activated(clone!(
+                #[weak(rename_to = s)]
+                self,
+                move |_| {
+                    s.obj().delete_data_button_row_activated();
+                }
+            ));
+
+            self.delete_previous_data_button_row
+                .connect_activated(clone!(
+                    #[weak(rename_to = s)]
+                    self,
+                    move |_| {
+                        s.obj().delete_previous_data_button_row_activated();
+                    }
+                ));
         }
 
         fn signals() -> &'static [Signal] {
@@ -187,35 +338,308 @@ impl VaultsPageRowSettingsWindow {
     }
 
     fn apply_changes(&self) {
-        let new_vault = Vault::new(
-            self.vault().unwrap().get_uuid(),
-            String::from(self.imp().name_entry_row.text().as_str()),
-            backend::get_backend_from_ui_string(
-                &self
-                    .imp()
-                    .combo_row_backend
-                    .selected_item()
-                    .unwrap()
-                    .downcast::<gtk::StringObject>()
-                    .unwrap()
-                    .string()
-                    .to_string(),
-            )
-            .unwrap(),
-            String::from(
-                self.imp()
-                    .encrypted_data_directory_entry_row
-                    .text()
-                    .as_str(),
+        let new_vault = self.create_vault_from_settings();
+
+        if let Err(e) = UserConfigManager::instance()
+            .change_vault(self.vault().unwrap().get_uuid(), new_vault.config().clone())
+        {
+            log::error!("Could not save vault settings: {}", e);
+            self.show_toast(format!("{}: {}", gettext("Could not save the settings"), e));
+            return;
+        }
+        self.set_vault(new_vault);
+        self.notify_vault();
+
+        if self.imp().descriptor_switch_row.is_active() {
+            self.update_descriptor();
+        }
+    }
+
+    fn update_descriptor(&self) {
+        let vault_config = self.vault().unwrap().config();
+        let encrypted_data_directory = Path::new(&vault_config.encrypted_data_directory);
+
+        let result = if self.imp().descriptor_switch_row.is_active() {
+            vault_descriptor::write(&vault_config)
+        } else {
+            vault_descriptor::remove(encrypted_data_directory)
+        };
+
+        if let Err(e) = result {
+            log::error!("Could not update vault descriptor: {}", e);
+            self.imp().toast_overlay.add_toast(adw::Toast::new(&format!(
+                "{}: {}",
+                gettext("Could not update vault description"),
+                e
+            )));
+        }
+    }
+
+    fn move_data_button_row_activated(&self) {
+        log::trace!("move_data_button_row_activated()");
+
+        let dialog = VaultsPageRowMoveDialog::new(self.vault().unwrap());
+        dialog.connect_moved(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                let vault = obj.vault().unwrap();
+                if let Some(vault) = UserConfigManager::instance().get_vault(vault.get_uuid()) {
+                    obj.imp()
+                        .encrypted_data_directory_entry_row
+                        .set_text(&vault.encrypted_data_directory());
+                    obj.set_vault(vault);
+                }
+            }
+        ));
+
+        AdwDialogExt::present(&dialog, Some(self));
+    }
+
+    fn backup_button_row_activated(&self) {
+        log::trace!("backup_button_row_activated()");
+
+        let vault = self.vault().unwrap();
+        vault.refresh_state();
+        if vault.is_busy() || vault.is_mounted() {
+            self.show_toast(gettext("Lock the vault before backing it up"));
+            return;
+        }
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let filter = gtk::FileFilter::new();
+                filter.set_name(Some(&gettext("Vault Backups")));
+                filter.add_suffix(vault_backup::FILE_EXTENSION);
+                let filters = gio::ListStore::new::<gtk::FileFilter>();
+                filters.append(&filter);
+
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Back Up Vault"))
+                    .modal(true)
+                    .accept_label(gettext("Save"))
+                    .initial_name(format!("{}.{}", vault.name(), vault_backup::FILE_EXTENSION))
+                    .filters(&filters)
+                    .build();
+
+                let root = obj.root().and_downcast::<gtk::Window>();
+                let Ok(file) = dialog.save_future(root.as_ref()).await else {
+                    return;
+                };
+                let Some(path) = file.path() else {
+                    return;
+                };
+
+                if let Err(e) = vault_backup::check_archive_path(
+                    Path::new(&vault.encrypted_data_directory()),
+                    &path,
+                ) {
+                    obj.show_toast(e);
+                    return;
+                }
+
+                // The vault may have been opened while the file was chosen
+                if !vault.mark_busy() {
+                    obj.show_toast(gettext("Lock the vault before backing it up"));
+                    return;
+                }
+
+                let backup_dialog = VaultBackupDialog::new_backup(&vault, path);
+                AdwDialogExt::present(&backup_dialog, Some(&obj));
+            }
+        ));
+    }
+
+    fn convert_button_row_activated(&self) {
+        log::trace!("convert_button_row_activated()");
+
+        let dialog = VaultsPageRowConvertDialog::new(self.vault().unwrap());
+        dialog.connect_converted(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                let vault = obj.vault().unwrap();
+                let Some(vault) = UserConfigManager::instance().get_vault(vault.get_uuid()) else {
+                    return;
+                };
+
+                let imp = obj.imp();
+                if let Some(position) = Backend::iter().position(|b| b == vault.backend()) {
+                    imp.combo_row_backend.set_selected(position as u32);
+                }
+                imp.encrypted_data_directory_entry_row
+                    .set_text(&vault.encrypted_data_directory());
+                imp.custom_binary_entry_row.set_text("");
+                imp.custom_binary_expander_row.set_enable_expansion(false);
+                imp.delete_previous_data_button_row.set_visible(true);
+                obj.set_vault(vault);
+            }
+        ));
+
+        AdwDialogExt::present(&dialog, Some(self));
+    }
+
+    fn delete_previous_data_button_row_activated(&self) {
+        log::trace!("delete_previous_data_button_row_activated()");
+
+        let uuid = self.vault().unwrap().get_uuid();
+        let Some(pending) = vault_conversion::pending(&uuid) else {
+            self.imp()
+                .delete_previous_data_button_row
+                .set_visible(false);
+            return;
+        };
+
+        let dialog = adw::AlertDialog::new(
+            Some(&gettext("Delete Encrypted Data Before Conversion?")),
+            Some(&format!(
+                "{}\n\n{}: {}",
+                gettext(
+                    "The encrypted data the vault used before it was converted is moved to the trash. Make sure the converted vault works first."
+                ),
+                backend::get_ui_string_from_backend(&pending.backend),
+                pending.encrypted_data_directory
+            )),
+        );
+        dialog.add_responses(&[
+            ("cancel", &gettext("_Keep")),
+            ("delete", &gettext("_Delete")),
+        ]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        dialog.connect_response(
+            Some("delete"),
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.delete_previous_data(uuid, pending.encrypted_data_directory.clone());
+                }
             ),
-            String::from(self.imp().mount_directory_entry_row.text().as_str()),
-            self.imp().lock_screen_switch_row.is_active(),
         );
 
-        UserConfigManager::instance()
-            .change_vault(self.vault().unwrap().get_uuid(), new_vault.config().clone());
-        self.set_vault(new_vault);
-        self.notify_vault();
+        AdwDialogExt::present(&dialog, Some(self));
+    }
+
+    fn delete_previous_data(&self, uuid: Uuid, encrypted_data_directory: String) {
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let result = gio::File::for_path(&encrypted_data_directory)
+                    .trash_future(glib::Priority::DEFAULT)
+                    .await;
+
+                match result {
+                    Ok(_) => {
+                        vault_conversion::clear_pending(&uuid);
+                        obj.imp().delete_previous_data_button_row.set_visible(false);
+                        obj.show_toast(gettext("Encrypted data moved to the trash"));
+                    }
+                    Err(e) => {
+                        log::error!("Could not delete encrypted data: {}", e);
+                        obj.show_toast(format!(
+                            "{}: {}",
+                            gettext("Could not delete encrypted data"),
+                            e
+                        ));
+                    }
+                }
+            }
+        ));
+    }
+
+    fn delete_data_button_row_activated(&self) {
+        log::trace!("delete_data_button_row_activated()");
+
+        let vault = self.vault().unwrap();
+        vault.refresh_state();
+        if vault.is_busy() || vault.is_mounted() {
+            self.show_toast(gettext("Lock the vault before deleting its encrypted data"));
+            return;
+        }
+
+        let name = vault.name();
+        let dialog = adw::AlertDialog::new(
+            Some(&gettext("Delete Encrypted Data?")),
+            Some(&format!(
+                "{}\n\n{}: {}",
+                gettext(
+                    "The encrypted data directory is moved to the trash and the vault is removed. Type the name of the vault to confirm."
+                ),
+                gettext("Encrypted Data Directory"),
+                vault.encrypted_data_directory()
+            )),
+        );
+        dialog.add_responses(&[
+            ("cancel", &gettext("_Cancel")),
+            ("delete", &gettext("_Delete")),
+        ]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        dialog.set_response_enabled("delete", false);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        let name_entry = gtk::Entry::builder().placeholder_text(&name).build();
+        name_entry.connect_changed(clone!(
+            #[weak]
+            dialog,
+            #[strong]
+            name,
+            move |entry| {
+                dialog.set_response_enabled("delete", entry.text() == name);
+            }
+        ));
+        dialog.set_extra_child(Some(&name_entry));
+
+        dialog.connect_response(
+            Some("delete"),
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[weak]
+                name_entry,
+                move |_, _| {
+                    if name_entry.text() == name {
+                        obj.delete_encrypted_data();
+                    }
+                }
+            ),
+        );
+
+        AdwDialogExt::present(&dialog, Some(self));
+    }
+
+    fn delete_encrypted_data(&self) {
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let vault = obj.vault().unwrap();
+                vault.refresh_state();
+
+                match vault.delete_encrypted_data().await {
+                    Ok(_) => obj.emit_by_name::<()>("remove", &[]),
+                    Err(e) => {
+                        log::error!("Could not delete encrypted data: {}", e);
+                        obj.show_toast(format!(
+                            "{}: {}",
+                            gettext("Could not delete encrypted data"),
+                            e
+                        ));
+                    }
+                }
+            }
+        ));
+    }
+
+    fn show_toast(&self, title: String) {
+        let toast = adw::Toast::builder().title(title).use_markup(false).build();
+        self.imp().toast_overlay.add_toast(toast);
     }
 
     fn encrypted_data_directory_button_clicked(&self) {
@@ -302,30 +726,80 @@ impl VaultsPageRowSettingsWindow {
         ));
     }
 
+    fn custom_binary_button_clicked(&self) {
+        let window = gtk::gio::Application::default()
+            .unwrap()
+            .downcast_ref::<VApplication>()
+            .unwrap()
+            .active_window()
+            .unwrap()
+            .clone();
+
+        glib::spawn_future_local(clone!(
+            #[strong]
+            window,
+            #[strong(rename_to = obj)]
+            self,
+            async move {
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Choose Custom Binary"))
+                    .modal(true)
+                    .accept_label(gettext("Select"))
+                    .build();
+
+                dialog.open(
+                    Some(&window),
+                    gio::Cancellable::NONE,
+                    clone!(
+                        #[strong]
+                        obj,
+                        move |file| {
+                            if let Ok(file) = file {
+                                let path = String::from(
+                                    file.path().unwrap().as_os_str().to_str().unwrap(),
+                                );
+                                obj.imp().custom_binary_entry_row.set_text(&path);
+                                obj.apply_changes();
+                            }
+                        }
+                    ),
+                );
+            }
+        ));
+    }
+
     pub fn create_vault_from_settings(&self) -> Vault {
-        Vault::new(
+        Vault::from_config(
             self.vault().unwrap().get_uuid(),
-            String::from(self.imp().name_entry_row.text().as_str()),
-            backend::get_backend_from_ui_string(
-                &self
-                    .imp()
-                    .combo_row_backend
-                    .selected_item()
-                    .unwrap()
-                    .downcast::<gtk::StringObject>()
-                    .unwrap()
-                    .string()
-                    .to_string(),
-            )
-            .unwrap(),
-            String::from(
-                self.imp()
-                    .encrypted_data_directory_entry_row
-                    .text()
-                    .as_str(),
-            ),
-            String::from(self.imp().mount_directory_entry_row.text().as_str()),
-            self.imp().lock_screen_switch_row.is_active(),
+            VaultConfig {
+                name: String::from(self.imp().name_entry_row.text().as_str()),
+                backend: backend::get_backend_from_ui_string(
+                    &self
+                        .imp()
+                        .combo_row_backend
+                        .selected_item()
+                        .unwrap()
+                        .downcast::<gtk::StringObject>()
+                        .unwrap()
+                        .string()
+                        .to_string(),
+                )
+                .unwrap(),
+                encrypted_data_directory: String::from(
+                    self.imp()
+                        .encrypted_data_directory_entry_row
+                        .text()
+                        .as_str(),
+                ),
+                mount_directory: String::from(self.imp().mount_directory_entry_row.text().as_str()),
+                session_lock: self.imp().lock_screen_switch_row.is_active(),
+                use_custom_binary: self.imp().custom_binary_expander_row.enables_expansion(),
+                custom_binary_path: String::from(
+                    self.imp().custom_binary_entry_row.text().as_str(),
+                ),
+                tags: vault_tags::parse(self.imp().tags_entry_row.text().as_str()),
+                favorite: self.imp().favorite_switch_row.is_active(),
+            },
         )
     }
 }
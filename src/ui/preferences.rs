@@ -29,7 +29,25 @@ use gtk::{CompositeTemplate, glib};
 use once_cell::sync::Lazy;
 
 use crate::application::VApplication;
+use crate::backend::{
+    Backend, get_ui_string_from_backend,
+    info::{self, BackendStatus, Probe},
+};
 use crate::config::APP_ID;
+use crate::vault::VaultConfig;
+use std::cell::RefCell;
+use strum::IntoEnumIterator;
+
+// Values of the quit-action setting, in the order of the combo row
+const QUIT_ACTIONS: [&str; 3] = ["ask", "lock", "keep"];
+
+#[derive(Debug)]
+pub struct BackendRow {
+    backend: Backend,
+    expander_row: adw::ExpanderRow,
+    test_button: gtk::Button,
+    rows: Vec<adw::ActionRow>,
+}
 
 mod imp {
     use super::*;
@@ -47,6 +65,10 @@ mod imp {
         pub mount_directory_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub lock_after_idle_spin_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub quit_action_combo_row: TemplateChild<adw::ComboRow>,
         // cryfs
         #[template_child]
         pub cryfs_custom_binary_expander_row: TemplateChild<adw::ExpanderRow>,
@@ -61,6 +83,10 @@ mod imp {
         pub gocryptfs_custom_binary_entry_row: TemplateChild<adw::EntryRow>,
         #[template_child]
         pub gocryptfs_custom_binary_button: TemplateChild<gtk::Button>,
+        // backends
+        #[template_child]
+        pub backends_group: TemplateChild<adw::PreferencesGroup>,
+        pub backend_rows: RefCell<Vec<BackendRow>>,
 
         pub settings: Settings,
     }
@@ -78,12 +104,16 @@ mod imp {
                 mount_directory_entry_row: TemplateChild::default(),
                 mount_directory_button: TemplateChild::default(),
                 toast_overlay: TemplateChild::default(),
+                lock_after_idle_spin_row: TemplateChild::default(),
+                quit_action_combo_row: TemplateChild::default(),
                 cryfs_custom_binary_expander_row: TemplateChild::default(),
                 cryfs_custom_binary_entry_row: TemplateChild::default(),
                 cryfs_custom_binary_button: TemplateChild::default(),
                 gocryptfs_custom_binary_expander_row: TemplateChild::default(),
                 gocryptfs_custom_binary_entry_row: TemplateChild::default(),
                 gocryptfs_custom_binary_button: TemplateChild::default(),
+                backends_group: TemplateChild::default(),
+                backend_rows: RefCell::new(vec![]),
 
                 settings: Settings::new(APP_ID),
             }
@@ -131,6 +161,7 @@ impl VaultsSettingsWindow {
     pub fn new() -> Self {
         let o: Self = glib::Object::builder().build();
         o.setup_signals();
+        o.setup_backend_rows();
         o
     }
 
@@ -171,6 +202,35 @@ impl VaultsSettingsWindow {
             )
             .build();
 
+        self.imp()
+            .settings
+            .bind(
+                "lock-after-idle",
+                &self.imp().lock_after_idle_spin_row.get(),
+                "value",
+            )
+            .build();
+
+        self.imp()
+            .settings
+            .bind(
+                "quit-action",
+                &self.imp().quit_action_combo_row.get(),
+                "selected",
+            )
+            .mapping(|variant, _| {
+                let position = QUIT_ACTIONS
+                    .iter()
+                    .position(|action| Some(*action) == variant.str())
+                    .unwrap_or_default();
+                Some((position as u32).to_value())
+            })
+            .set_mapping(|value, _| {
+                let position = value.get::<u32>().ok()? as usize;
+                QUIT_ACTIONS.get(position).map(|action| action.to_variant())
+            })
+            .build();
+
         self.imp()
             .settings
             .bind(
@@ -308,4 +368,198 @@ impl VaultsSettingsWindow {
             }
         ));
     }
+
+    fn setup_backend_rows(&self) {
+        log::trace!("setup_backend_rows()");
+
+        for backend in Backend::iter() {
+            // The subtitle shows the output of the binary
+            let expander_row = adw::ExpanderRow::builder()
+                .title(get_ui_string_from_backend(&backend))
+                .use_markup(false)
+                .build();
+
+            let test_button = gtk::Button::builder()
+                .label(gettext("Test"))
+                .valign(gtk::Align::Center)
+                .tooltip_text(gettext("Run the Binary Again"))
+                .build();
+            test_button.connect_clicked(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_| {
+                    obj.update_backend_row(backend, true);
+                }
+            ));
+            expander_row.add_suffix(&test_button);
+
+            self.imp().backends_group.add(&expander_row);
+            self.imp().backend_rows.borrow_mut().push(BackendRow {
+                backend,
+                expander_row,
+                test_button,
+                rows: vec![],
+            });
+
+            self.update_backend_row(backend, false);
+        }
+
+        self.imp().settings.connect_changed(
+            None,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, key| {
+                    match key {
+                        "use-custom-cryfs-binary" | "custom-cryfs-binary-path" => {
+                            obj.update_backend_row(Backend::Cryfs, false);
+                        }
+                        "use-custom-gocryptfs-binary" | "custom-gocryptfs-binary-path" => {
+                            obj.update_backend_row(Backend::Gocryptfs, false);
+                        }
+                        _ => {}
+                    }
+                }
+            ),
+        );
+    }
+
+    fn update_backend_row(&self, backend: Backend, force: bool) {
+        log::trace!("update_backend_row({:?}, {})", backend, force);
+
+        let vault_config = VaultConfig {
+            backend,
+            ..Default::default()
+        };
+        let binary_path = backend.get_binary_path(&vault_config);
+
+        let Some(binary_path) = binary_path else {
+            self.show_backend_info(backend, None, None);
+            return;
+        };
+
+        if !force {
+            if let Some(probe) = info::cached_probe(&binary_path) {
+                self.show_backend_info(backend, Some(binary_path), Some(probe));
+                return;
+            }
+        }
+
+        if let Some(row) = self
+            .imp()
+            .backend_rows
+            .borrow()
+            .iter()
+            .find(|r| r.backend == backend)
+        {
+            row.expander_row.set_subtitle(&gettext("Testing…"));
+            row.test_button.set_sensitive(false);
+        }
+
+        let (sender, receiver) = async_channel::unbounded();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            binary_path,
+            async move {
+                if let Ok(probe) = receiver.recv().await {
+                    obj.show_backend_info(backend, Some(binary_path), Some(probe));
+                }
+            }
+        ));
+
+        std::thread::spawn(move || {
+            sender
+                .send_blocking(info::test(&binary_path))
+                .expect("Channel needs to be open");
+        });
+    }
+
+    fn show_backend_info(
+        &self,
+        backend: Backend,
+        binary_path: Option<String>,
+        probe: Option<Probe>,
+    ) {
+        log::trace!(
+            "show_backend_info({:?}, {:?}, {:?})",
+            backend,
+            binary_path,
+            probe
+        );
+
+        let mut backend_rows = self.imp().backend_rows.borrow_mut();
+        let Some(row) = backend_rows.iter_mut().find(|r| r.backend == backend) else {
+            return;
+        };
+
+        for child in row.rows.drain(..) {
+            row.expander_row.remove(&child);
+        }
+        row.test_button.set_sensitive(binary_path.is_some());
+
+        let status = match probe.as_ref().map(|p| &p.status) {
+            None => gettext("No binary path set"),
+            Some(BackendStatus::Available) => gettext("Installed"),
+            Some(BackendStatus::NotInstalled) => gettext("Not installed"),
+            Some(BackendStatus::Failed(e)) if e.is_empty() => gettext("Not working"),
+            Some(BackendStatus::Failed(e)) => gettext("Not working: {}").replace("{}", e),
+        };
+        row.expander_row.set_subtitle(&status);
+
+        let vault_config = VaultConfig {
+            backend,
+            ..Default::default()
+        };
+        let binary_origin = if backend.is_custom_binary(&vault_config) {
+            gettext("Custom Binary")
+        } else {
+            gettext("Bundled Binary")
+        };
+        row.rows.push(
+            adw::ActionRow::builder()
+                .title(binary_origin)
+                .use_markup(false)
+                .subtitle(binary_path.unwrap_or_default())
+                .subtitle_selectable(true)
+                .build(),
+        );
+
+        let version = probe.as_ref().and_then(|p| p.version);
+        row.rows.push(
+            adw::ActionRow::builder()
+                .title(gettext("Version"))
+                .subtitle(match version {
+                    Some(version) => version.to_string(),
+                    None => gettext("Unknown"),
+                })
+                .build(),
+        );
+
+        for feature in info::features(&backend) {
+            let is_supported = version.is_some_and(|v| v >= feature.since);
+            let icon = gtk::Image::from_icon_name(if is_supported {
+                "object-select-symbolic"
+            } else {
+                "action-unavailable-symbolic"
+            });
+            icon.set_tooltip_text(Some(&if is_supported {
+                gettext("Supported")
+            } else {
+                gettext("Not supported")
+            }));
+
+            let feature_row = adw::ActionRow::builder()
+                .title(feature.name)
+                .subtitle(gettext("Requires version {}").replace("{}", &feature.since.to_string()))
+                .build();
+            feature_row.add_suffix(&icon);
+            row.rows.push(feature_row);
+        }
+
+        for child in row.rows.iter() {
+            row.expander_row.add_row(child);
+        }
+    }
 }
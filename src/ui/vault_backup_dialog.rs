@@ -0,0 +1,444 @@
+// vault_backup_dialog.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{CompositeTemplate, gio, glib, glib::clone};
+use std::{
+    cell::RefCell,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use uuid::Uuid;
+
+use crate::backend;
+use crate::config::APP_ID;
+use crate::user_config_manager::UserConfigManager;
+use crate::vault::{Vault, VaultConfig};
+use crate::vault_backup::{self, BackupError, Manifest};
+use crate::vault_relocation::{Phase, Progress};
+
+mod imp {
+    use gtk::glib::subclass::Signal;
+    use once_cell::sync::Lazy;
+
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/mpobaschnig/Vaults/vault_backup_dialog.ui")]
+    pub struct VaultBackupDialog {
+        #[template_child]
+        pub window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
+        pub description_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub cancel_button: TemplateChild<gtk::Button>,
+
+        // Set while running, cancels the worker thread
+        pub cancelled: RefCell<Option<Arc<AtomicBool>>>,
+        // Set once the data is unpacked and checked, removed if the vault
+        // cannot be added
+        pub restored: RefCell<Option<PathBuf>>,
+        // The vault being backed up, kept busy while running
+        pub busy_vault: RefCell<Option<Vault>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VaultBackupDialog {
+        const NAME: &'static str = "VaultBackupDialog";
+        type ParentType = adw::Dialog;
+        type Type = super::VaultBackupDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VaultBackupDialog {
+        fn constructed(&self) {
+            let obj = self.obj();
+            self.parent_constructed();
+
+            self.cancel_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.cancel();
+                }
+            ));
+
+            obj.connect_close_attempt(|obj| {
+                obj.cancel();
+            });
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> =
+                Lazy::new(|| vec![Signal::builder("restore").build()]);
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for VaultBackupDialog {}
+    impl AdwDialogImpl for VaultBackupDialog {}
+}
+
+glib::wrapper! {
+    pub struct VaultBackupDialog(ObjectSubclass<imp::VaultBackupDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+enum Message {
+    Progress(Progress),
+    Finished(Manifest),
+    Error(BackupError),
+}
+
+impl VaultBackupDialog {
+    /// Writes the encrypted data of the locked vault into `archive`. The vault
+    /// must be marked busy, it is cleared once the backup stopped.
+    pub fn new_backup(vault: &Vault, archive: PathBuf) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        dialog.imp().busy_vault.replace(Some(vault.clone()));
+
+        dialog
+            .imp()
+            .window_title
+            .set_title(&gettext("Back Up Vault"));
+        dialog.imp().window_title.set_subtitle(&vault.name());
+        dialog.imp().description_group.set_description(Some(&gettext(
+            "The encrypted data is saved as compressed archive. Keep the vault locked until the backup is done.",
+        )));
+
+        let source = PathBuf::from(vault.encrypted_data_directory());
+        let config = vault.config();
+        dialog.run(
+            move |cancelled, progress| {
+                write_archive(&source, &config, &archive, cancelled, progress)
+            },
+            |dialog, _| {
+                dialog.show_result(&gettext("The backup was saved and can be closed."));
+            },
+        );
+
+        dialog
+    }
+
+    /// Unpacks `archive` into `destination` and adds the vault once all files
+    /// match the manifest.
+    pub fn new_restore(archive: PathBuf, destination: PathBuf) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+
+        dialog
+            .imp()
+            .window_title
+            .set_title(&gettext("Restore from Backup"));
+        dialog
+            .imp()
+            .window_title
+            .set_subtitle(&destination.to_string_lossy());
+        dialog
+            .imp()
+            .description_group
+            .set_description(Some(&gettext(
+                "The encrypted data is unpacked and checked before the vault is added.",
+            )));
+
+        let restored = destination.clone();
+        dialog.run(
+            move |cancelled, progress| read_archive(&archive, &destination, cancelled, progress),
+            move |dialog, manifest| {
+                dialog.register(manifest, &restored);
+            },
+        );
+
+        dialog
+    }
+
+    pub fn connect_restore<F: Fn() + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_local("restore", false, move |_| {
+            callback();
+            None
+        })
+    }
+
+    fn run<W, F>(&self, work: W, finished: F)
+    where
+        W: FnOnce(&AtomicBool, &mut dyn FnMut(Progress)) -> Result<Manifest, BackupError>
+            + Send
+            + 'static,
+        F: FnOnce(&Self, Manifest) + 'static,
+    {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.imp().cancelled.replace(Some(cancelled.clone()));
+        self.set_can_close(false);
+
+        let (sender, receiver) = async_channel::unbounded();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let mut finished = Some(finished);
+                while let Ok(message) = receiver.recv().await {
+                    match message {
+                        Message::Progress(progress) => obj.show_progress(&progress),
+                        Message::Finished(manifest) => {
+                            obj.stop();
+                            if let Some(finished) = finished.take() {
+                                finished(&obj, manifest);
+                            }
+                        }
+                        Message::Error(e) => obj.fail(e),
+                    }
+                }
+            }
+        ));
+
+        std::thread::spawn(move || {
+            let mut progress = |progress: Progress| {
+                sender
+                    .send_blocking(Message::Progress(progress))
+                    .expect("Channel needs to be open");
+            };
+
+            let result = work(&cancelled, &mut progress);
+
+            sender
+                .send_blocking(match result {
+                    Ok(manifest) => Message::Finished(manifest),
+                    Err(e) => Message::Error(e),
+                })
+                .expect("Channel needs to be open");
+        });
+    }
+
+    fn cancel(&self) {
+        log::trace!("cancel()");
+
+        match self.imp().cancelled.borrow().as_ref() {
+            Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+            None => {
+                self.close();
+            }
+        }
+    }
+
+    // Adds the restored vault under a name and mount directory that are not
+    // taken yet
+    fn register(&self, manifest: Manifest, destination: &Path) {
+        log::trace!("register({:?})", destination);
+
+        self.imp().restored.replace(Some(destination.to_path_buf()));
+
+        let descriptor = manifest.vault;
+        if backend::detect_backend(destination) != Some(descriptor.backend) {
+            self.fail(BackupError::Invalid(gettext(
+                "The backup does not contain the configuration file of its backend.",
+            )));
+            return;
+        }
+
+        let mut mount_root = gio::Settings::new(APP_ID)
+            .string("mount-directory")
+            .to_string();
+        if !mount_root.ends_with('/') {
+            mount_root.push('/');
+        }
+
+        let map = UserConfigManager::instance().get_map();
+        let is_taken = |name: &str, mount_directory: &str| {
+            map.values().any(|config| {
+                config.name == name
+                    || config.mount_directory.trim_end_matches('/') == mount_directory
+            }) || Path::new(mount_directory).exists()
+        };
+
+        let mut attempt = 0;
+        let (name, mount_directory) = loop {
+            let suffix = match attempt {
+                0 => String::new(),
+                1 => format!(" ({})", gettext("Restored")),
+                n => format!(" ({} {})", gettext("Restored"), n),
+            };
+            let name = format!("{}{}", descriptor.name, suffix);
+            let mount_directory = format!(
+                "{}{}{}",
+                mount_root, descriptor.mount_directory_name, suffix
+            );
+            if !is_taken(&name, &mount_directory) {
+                break (name, mount_directory);
+            }
+            attempt += 1;
+        };
+
+        let config = VaultConfig {
+            name,
+            backend: descriptor.backend,
+            encrypted_data_directory: destination.to_string_lossy().to_string(),
+            mount_directory,
+            session_lock: descriptor.session_lock,
+            ..Default::default()
+        };
+        UserConfigManager::instance().add_vault(Vault::from_config(Uuid::new_v4(), config));
+        self.imp().restored.replace(None);
+        self.emit_by_name::<()>("restore", &[]);
+
+        self.show_result(&gettext("The vault was restored and added."));
+    }
+
+    fn fail(&self, e: BackupError) {
+        log::trace!("fail({})", e);
+
+        self.stop();
+
+        if e.is_cancelled() {
+            self.close();
+            return;
+        }
+
+        log::error!("Backup failed: {}", e);
+        self.imp().progress_bar.set_visible(false);
+        self.show_result(&format!("{}: {}", gettext("Failed"), e));
+
+        // Failures while unpacking are cleaned up by the restore itself
+        if let Some(restored) = self.imp().restored.take() {
+            glib::spawn_future_local(async move {
+                let path = restored.clone();
+                match gio::spawn_blocking(move || fs::remove_dir_all(path)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => log::error!("Could not remove {:?}: {}", restored, e),
+                    Err(_) => log::error!("Removing {:?} panicked", restored),
+                }
+            });
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(vault) = self.imp().busy_vault.take() {
+            vault.clear_busy();
+        }
+        self.imp().cancelled.replace(None);
+        self.set_can_close(true);
+        self.imp().cancel_button.set_label(&gettext("_Close"));
+    }
+
+    fn show_progress(&self, progress: &Progress) {
+        let progress_bar = &self.imp().progress_bar;
+
+        // The size of an archive is only known once it was read
+        if progress.phase == Phase::Extracting {
+            progress_bar.pulse();
+            progress_bar.set_text(Some(&glib::format_size(progress.done_bytes)));
+            self.imp().status_label.set_text(&format!(
+                "{}: {}",
+                progress.phase.label(),
+                progress.done_files
+            ));
+            return;
+        }
+
+        progress_bar.set_fraction(progress.fraction());
+        progress_bar.set_text(Some(&format!(
+            "{} / {}",
+            glib::format_size(progress.done_bytes),
+            glib::format_size(progress.total_bytes)
+        )));
+        self.imp().status_label.set_text(&format!(
+            "{}: {} / {}",
+            progress.phase.label(),
+            progress.done_files,
+            progress.total_files
+        ));
+    }
+
+    fn show_result(&self, result: &str) {
+        self.imp().status_label.set_text(result);
+    }
+}
+
+// Written next to the archive first, so a failed backup does not replace an
+// older one
+fn write_archive(
+    source: &Path,
+    config: &VaultConfig,
+    archive: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Manifest, BackupError> {
+    let mut partial_name = archive.file_name().unwrap_or_default().to_owned();
+    partial_name.push(".vaults-partial");
+    let partial = archive.with_file_name(partial_name);
+
+    let result = (|| -> Result<Manifest, BackupError> {
+        let output = gio::File::for_path(&partial)
+            .replace(
+                None,
+                false,
+                gio::FileCreateFlags::REPLACE_DESTINATION,
+                gio::Cancellable::NONE,
+            )
+            .map_err(io::Error::other)?;
+        let compressor = gio::ZlibCompressor::new(gio::ZlibCompressorFormat::Gzip, -1);
+        let stream = gio::ConverterOutputStream::new(&output, &compressor);
+
+        let mut writer = stream.clone().into_write();
+        let manifest = vault_backup::create(source, config, &mut writer, cancelled, progress)?;
+        stream
+            .close(gio::Cancellable::NONE)
+            .map_err(io::Error::other)?;
+        fs::rename(&partial, archive)?;
+
+        Ok(manifest)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn read_archive(
+    archive: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Manifest, BackupError> {
+    let input = gio::File::for_path(archive)
+        .read(gio::Cancellable::NONE)
+        .map_err(io::Error::other)?;
+    let decompressor = gio::ZlibDecompressor::new(gio::ZlibCompressorFormat::Gzip);
+    let stream = gio::ConverterInputStream::new(&input, &decompressor);
+
+    let mut reader = stream.into_read();
+    vault_backup::restore(&mut reader, destination, cancelled, progress)
+}
@@ -0,0 +1,391 @@
+// vault_bundle_dialog.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{CompositeTemplate, gio, glib, glib::clone};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use uuid::Uuid;
+
+use crate::config::APP_ID;
+use crate::config_file;
+use crate::user_config_manager::UserConfigManager;
+use crate::vault::Vault;
+use crate::vault_bundle::{self, BundleEntry};
+use crate::vault_scanner;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BundleMode {
+    #[default]
+    Export,
+    Import,
+    Scan,
+}
+
+mod imp {
+    use gtk::glib::subclass::Signal;
+    use once_cell::sync::Lazy;
+
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/mpobaschnig/Vaults/vault_bundle_dialog.ui")]
+    pub struct VaultBundleDialog {
+        #[template_child]
+        pub window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
+        pub cancel_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub confirm_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub vaults_group: TemplateChild<adw::PreferencesGroup>,
+
+        pub mode: RefCell<BundleMode>,
+        pub is_cancelled: Arc<AtomicBool>,
+        pub entries: RefCell<Vec<(BundleEntry, gtk::CheckButton)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VaultBundleDialog {
+        const NAME: &'static str = "VaultBundleDialog";
+        type ParentType = adw::Dialog;
+        type Type = super::VaultBundleDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VaultBundleDialog {
+        fn constructed(&self) {
+            let obj = self.obj();
+            self.parent_constructed();
+
+            obj.setup_signals();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> =
+                Lazy::new(|| vec![Signal::builder("import").build()]);
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for VaultBundleDialog {}
+    impl AdwDialogImpl for VaultBundleDialog {}
+}
+
+glib::wrapper! {
+    pub struct VaultBundleDialog(ObjectSubclass<imp::VaultBundleDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl VaultBundleDialog {
+    /// Lets the user pick the vaults to export. The given vaults are checked,
+    /// or all vaults if none are given.
+    pub fn new_export(selected: &[Uuid]) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        dialog.imp().mode.replace(BundleMode::Export);
+
+        dialog
+            .imp()
+            .window_title
+            .set_title(&gettext("Export Vaults"));
+        dialog.imp().confirm_button.set_label(&gettext("_Export…"));
+
+        let mut entries: Vec<BundleEntry> = UserConfigManager::instance()
+            .get_map()
+            .into_iter()
+            .map(|(uuid, config)| BundleEntry {
+                uuid,
+                config,
+                issues: vec![],
+            })
+            .collect();
+        entries.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+
+        for entry in entries {
+            let is_checked = selected.is_empty() || selected.contains(&entry.uuid);
+            dialog.add_row(entry, is_checked);
+        }
+        dialog.update_confirm_button();
+
+        dialog
+    }
+
+    /// Lets the user review vaults from a bundle or another application.
+    /// Vaults with issues cannot be imported.
+    pub fn new_import(title: &str, mut entries: Vec<BundleEntry>) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        dialog.imp().mode.replace(BundleMode::Import);
+
+        dialog.imp().window_title.set_title(title);
+        dialog.imp().confirm_button.set_label(&gettext("_Import"));
+        dialog.imp().vaults_group.set_description(Some(&gettext(
+            "Vaults that conflict with existing ones, whose encrypted data cannot be found or that use unsupported backends are skipped.",
+        )));
+
+        entries.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+        for entry in entries {
+            let is_checked = entry.issues.is_empty();
+            dialog.add_row(entry, is_checked);
+        }
+        dialog.update_confirm_button();
+
+        dialog
+    }
+
+    /// Scans `root` in the background and lets the user pick the vaults to
+    /// import. Vaults that already exist are not listed.
+    pub fn new_scan(root: PathBuf) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        dialog.imp().mode.replace(BundleMode::Scan);
+
+        dialog
+            .imp()
+            .window_title
+            .set_title(&gettext("Scan for Vaults"));
+        dialog
+            .imp()
+            .window_title
+            .set_subtitle(&root.to_string_lossy());
+        dialog.imp().confirm_button.set_label(&gettext("_Import"));
+        dialog.imp().stack.set_visible_child_name("scanning");
+
+        let (sender, receiver) = async_channel::unbounded();
+        glib::spawn_future_local(clone!(
+            #[weak]
+            dialog,
+            async move {
+                if let Ok(found) = receiver.recv().await {
+                    dialog.show_scan_results(found);
+                }
+            }
+        ));
+
+        let is_cancelled = dialog.imp().is_cancelled.clone();
+        std::thread::spawn(move || {
+            let found = vault_scanner::scan(&root, &is_cancelled);
+            // The dialog may be gone already
+            let _ = sender.send_blocking(found);
+        });
+
+        dialog
+    }
+
+    fn show_scan_results(&self, found: Vec<vault_scanner::FoundVault>) {
+        let settings = gio::Settings::new(APP_ID);
+        let mount_directory = match settings.string("mount-directory").as_str() {
+            "" => glib::home_dir().join("Vaults"),
+            path => PathBuf::from(path),
+        };
+
+        let existing = UserConfigManager::instance().get_map();
+        let mut entries: Vec<BundleEntry> =
+            vault_scanner::suggest(&found, &mount_directory, &existing)
+                .into_iter()
+                .map(|(uuid, config)| BundleEntry {
+                    issues: vault_bundle::check(&uuid, &config, &existing),
+                    uuid,
+                    config,
+                })
+                .collect();
+
+        if entries.is_empty() {
+            self.imp().stack.set_visible_child_name("empty");
+            return;
+        }
+
+        entries.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+        for entry in entries {
+            let is_checked = entry.issues.is_empty();
+            self.add_row(entry, is_checked);
+        }
+        self.update_confirm_button();
+        self.imp().stack.set_visible_child_name("vaults");
+    }
+
+    fn setup_signals(&self) {
+        self.connect_closed(|obj| {
+            obj.imp().is_cancelled.store(true, Ordering::Relaxed);
+        });
+
+        self.imp().cancel_button.connect_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.close();
+            }
+        ));
+
+        self.imp().confirm_button.connect_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                let mode = *obj.imp().mode.borrow();
+                match mode {
+                    BundleMode::Export => obj.export_clicked(),
+                    BundleMode::Import | BundleMode::Scan => obj.import_clicked(),
+                }
+            }
+        ));
+    }
+
+    fn add_row(&self, entry: BundleEntry, is_checked: bool) {
+        let subtitle = if entry.issues.is_empty() {
+            format!(
+                "{} → {}",
+                entry.config.encrypted_data_directory, entry.config.mount_directory
+            )
+        } else {
+            entry
+                .issues
+                .iter()
+                .map(|issue| issue.description())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+
+        let check_button = gtk::CheckButton::builder()
+            .active(is_checked)
+            .sensitive(entry.issues.is_empty())
+            .valign(gtk::Align::Center)
+            .build();
+        check_button.connect_toggled(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.update_confirm_button();
+            }
+        ));
+
+        let row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&entry.config.name))
+            .subtitle(glib::markup_escape_text(&subtitle))
+            .activatable_widget(&check_button)
+            .build();
+        row.add_prefix(&check_button);
+        if !entry.issues.is_empty() {
+            row.add_css_class("error");
+        }
+
+        self.imp().vaults_group.add(&row);
+        self.imp().entries.borrow_mut().push((entry, check_button));
+    }
+
+    fn update_confirm_button(&self) {
+        let has_checked = !self.checked_entries().is_empty();
+        self.imp().confirm_button.set_sensitive(has_checked);
+    }
+
+    fn checked_entries(&self) -> Vec<BundleEntry> {
+        self.imp()
+            .entries
+            .borrow()
+            .iter()
+            .filter(|(_, check_button)| check_button.is_active())
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+
+    fn export_clicked(&self) {
+        let vaults: HashMap<_, _> = self
+            .checked_entries()
+            .into_iter()
+            .map(|entry| (entry.uuid, entry.config))
+            .collect();
+
+        glib::spawn_future_local(clone!(
+            #[strong(rename_to = obj)]
+            self,
+            async move {
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Export Vaults"))
+                    .modal(true)
+                    .initial_name("vaults.toml")
+                    .accept_label(gettext("Export"))
+                    .build();
+
+                let Ok(file) = dialog.save_future(Some(&obj)).await else {
+                    return;
+                };
+                let Some(path) = file.path() else {
+                    return;
+                };
+
+                let result = vault_bundle::export(&vaults, &glib::home_dir())
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| {
+                        config_file::write_atomic(&path, &contents).map_err(|e| e.to_string())
+                    });
+
+                match result {
+                    Ok(_) => {
+                        log::info!("Exported {} vaults to {:?}", vaults.len(), path);
+                        obj.close();
+                    }
+                    Err(e) => {
+                        log::error!("Could not export vaults: {}", e);
+                        let info_dialog = gtk::AlertDialog::builder()
+                            .modal(true)
+                            .message(gettext("Could not export vaults"))
+                            .detail(e)
+                            .build();
+                        info_dialog.show(obj.root().and_downcast_ref::<gtk::Window>());
+                    }
+                }
+            }
+        ));
+    }
+
+    fn import_clicked(&self) {
+        let user_config_manager = UserConfigManager::instance();
+
+        for entry in self.checked_entries() {
+            // The config may have changed while the dialog was open
+            let issues =
+                vault_bundle::check(&entry.uuid, &entry.config, &user_config_manager.get_map());
+            if !issues.is_empty() {
+                log::warn!("Skipping vault {}: {:?}", entry.config.name, issues);
+                continue;
+            }
+
+            user_config_manager.add_vault(Vault::from_config(entry.uuid, entry.config));
+        }
+
+        self.emit_by_name::<()>("import", &[]);
+        self.close();
+    }
+}
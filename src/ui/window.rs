@@ -20,12 +20,22 @@
 use crate::config::APP_ID;
 use crate::ui::pages::*;
 use crate::ui::window::glib::GString;
-use crate::ui::{AddNewVaultWindow, ImportVaultDialog};
+use crate::ui::{AddNewVaultWindow, ImportVaultDialog, VaultBackupDialog, VaultBundleDialog};
 use crate::{
-    application::VApplication, backend::Backend, user_config_manager::UserConfigManager, vault::*,
+    application::VApplication,
+    backend::{Backend, BackendError},
+    importers::{self, ImportSource},
+    search_query::SearchQuery,
+    user_config_manager::UserConfigManager,
+    vault::{self, Vault, VaultConfig, VaultState},
+    vault_backup,
+    vault_bundle::{self, BundleEntry},
+    vault_descriptor,
+    vault_sort::{self, SortEntry, SortOrder},
+    vault_tags, vault_usage,
 };
 
-use adw::prelude::AdwDialogExt;
+use adw::prelude::{AdwDialogExt, ExpanderRowExt};
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use glib::clone;
@@ -37,9 +47,13 @@ use gtk::{CompositeTemplate, gio, glib};
 use gtk_macros::action;
 
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+use uuid::Uuid;
 
 #[derive(PartialEq, Debug)]
 pub enum View {
+    ConfigError,
     Search,
     Start,
     Vaults,
@@ -59,6 +73,8 @@ mod imp {
         #[template_child]
         pub vaults_list_box: TemplateChild<gtk::ListBox>,
         #[template_child]
+        pub vaults_groups_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
         pub search_vaults_list_box: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub headerbar: TemplateChild<adw::HeaderBar>,
@@ -76,11 +92,28 @@ mod imp {
         pub select_toggle_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
         pub remove_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub config_error_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub config_error_open_file_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub config_error_restore_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub config_error_reset_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
 
         pub list_store: ListStore,
         pub search_list_store: ListStore,
 
         pub search_results: RefCell<u32>,
+        pub search_rows: RefCell<HashMap<Uuid, VaultsPageRow>>,
+
+        pub settings: gio::Settings,
+        // Lowercase names of the tag groups the user collapsed
+        pub collapsed_groups: RefCell<HashSet<String>>,
+        pub regroup_queued: RefCell<bool>,
+        pub sort_queued: RefCell<bool>,
 
         #[property(name = "is-selected", default = false, get = |_| { *self.is_selected.borrow() }, set)]
         pub is_selected: RefCell<bool>,
@@ -97,6 +130,7 @@ mod imp {
                 window_stack: TemplateChild::default(),
                 start_page_status_page: TemplateChild::default(),
                 vaults_list_box: TemplateChild::default(),
+                vaults_groups_list_box: TemplateChild::default(),
                 search_vaults_list_box: TemplateChild::default(),
                 headerbar: TemplateChild::default(),
                 search_bar: TemplateChild::default(),
@@ -106,9 +140,19 @@ mod imp {
                 list_store: ListStore::new::<gtk::Widget>(),
                 search_list_store: ListStore::new::<gtk::Widget>(),
                 search_results: RefCell::new(0),
+                search_rows: RefCell::new(HashMap::new()),
                 add_menu_button: TemplateChild::default(),
                 select_toggle_button: TemplateChild::default(),
                 remove_button: TemplateChild::default(),
+                config_error_status_page: TemplateChild::default(),
+                config_error_open_file_button: TemplateChild::default(),
+                config_error_restore_button: TemplateChild::default(),
+                config_error_reset_button: TemplateChild::default(),
+                toast_overlay: TemplateChild::default(),
+                settings: gio::Settings::new(APP_ID),
+                collapsed_groups: RefCell::new(HashSet::new()),
+                regroup_queued: RefCell::new(false),
+                sort_queued: RefCell::new(false),
                 is_selected: RefCell::new(false),
             }
         }
@@ -134,7 +178,19 @@ mod imp {
     }
 
     impl WidgetImpl for ApplicationWindow {}
-    impl WindowImpl for ApplicationWindow {}
+    impl WindowImpl for ApplicationWindow {
+        // Closing the last window quits, which may have to lock vaults first
+        fn close_request(&self) -> glib::Propagation {
+            let app = self.obj().application().and_downcast::<VApplication>();
+            match app {
+                Some(app) if app.needs_quit_confirmation() => {
+                    app.request_quit();
+                    glib::Propagation::Stop
+                }
+                _ => self.parent_close_request(),
+            }
+        }
+    }
 
     impl ApplicationWindowImpl for ApplicationWindow {}
     impl AdwApplicationWindowImpl for ApplicationWindow {}
@@ -153,16 +209,15 @@ impl ApplicationWindow {
 
         gtk::Window::set_default_icon_name(APP_ID);
 
-        if !UserConfigManager::instance().get_map().is_empty() {
-            object.set_view(View::Vaults);
-        } else {
-            object.set_view(View::Start);
-        }
-
         object.setup_window();
         object.setup_search_page();
         object.setup_start_page();
         object.setup_vaults_page();
+        object.setup_grouping();
+        object.setup_sorting();
+        object.setup_config_error_page();
+
+        object.refresh_new();
 
         let builder = gtk::Builder::from_resource("/io/github/mpobaschnig/Vaults/shortcuts.ui");
         gtk_macros::get_widget!(builder, gtk::ShortcutsWindow, shortcuts);
@@ -171,6 +226,128 @@ impl ApplicationWindow {
         object
     }
 
+    fn setup_config_error_page(&self) {
+        self.imp()
+            .config_error_open_file_button
+            .connect_clicked(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_| {
+                    obj.config_error_open_file_clicked();
+                }
+            ));
+
+        self.imp()
+            .config_error_restore_button
+            .connect_clicked(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_| {
+                    obj.config_error_restore_clicked();
+                }
+            ));
+
+        self.imp().config_error_reset_button.connect_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.config_error_reset_clicked();
+            }
+        ));
+    }
+
+    fn update_config_error_page(&self) {
+        let Some(error) = UserConfigManager::instance().parse_error() else {
+            return;
+        };
+
+        let location = match (error.line, error.column) {
+            (Some(line), Some(column)) => {
+                format!("{}:{}:{}", error.path, line, column)
+            }
+            _ => error.path.clone(),
+        };
+        self.imp()
+            .config_error_status_page
+            .set_description(Some(&format!(
+                "{}\n{}\n\n{}",
+                glib::markup_escape_text(&location),
+                glib::markup_escape_text(&error.message),
+                gettext("The file is left untouched until you choose what to do.")
+            )));
+
+        self.imp().config_error_restore_button.set_sensitive(
+            UserConfigManager::instance()
+                .newest_valid_backup()
+                .is_some(),
+        );
+    }
+
+    fn config_error_open_file_clicked(&self) {
+        let Some(error) = UserConfigManager::instance().parse_error() else {
+            return;
+        };
+
+        let output_res = Command::new("xdg-open").arg(&error.path).output();
+        if let Err(e) = output_res {
+            log::error!("Failed to open {}: {}", error.path, e);
+        }
+    }
+
+    fn config_error_restore_clicked(&self) {
+        let Some(backup) = UserConfigManager::instance().newest_valid_backup() else {
+            return;
+        };
+
+        if let Err(e) = UserConfigManager::instance().restore_backup(&backup) {
+            log::error!("Could not restore backup {:?}: {}", backup, e);
+            self.show_config_error_dialog(gettext("Could not restore backup"), e);
+            return;
+        }
+
+        self.refresh_model();
+    }
+
+    fn config_error_reset_clicked(&self) {
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let cancel = gettext("Cancel");
+                let reset = gettext("Reset");
+                let reset_dialog = gtk::AlertDialog::builder()
+                    .modal(true)
+                    .message(gettext("Reset List of Vaults?"))
+                    .detail(gettext(
+                        "Vaults starts with an empty list. The unreadable file is kept next to it with the extension .broken.",
+                    ))
+                    .buttons([cancel.as_str(), reset.as_str()])
+                    .cancel_button(0)
+                    .default_button(0)
+                    .build();
+
+                if let Ok(1) = reset_dialog.choose_future(Some(&obj)).await {
+                    if let Err(e) = UserConfigManager::instance().reset_config() {
+                        log::error!("Could not reset user config: {}", e);
+                        obj.show_config_error_dialog(gettext("Could not reset"), e);
+                        return;
+                    }
+
+                    obj.refresh_model();
+                }
+            }
+        ));
+    }
+
+    fn show_config_error_dialog(&self, message: String, e: std::io::Error) {
+        let info_dialog = gtk::AlertDialog::builder()
+            .modal(true)
+            .message(message)
+            .detail(e.to_string())
+            .build();
+        info_dialog.show(Some(self));
+    }
+
     fn setup_window(&self) {
         self.imp().search_toggle_button.connect_toggled(clone!(
             #[weak(rename_to = obj)]
@@ -206,53 +383,55 @@ impl ApplicationWindow {
     }
 
     fn search(&self) {
-        let text = self.imp().search_entry.text();
-
-        *self.imp().search_results.borrow_mut() = 0;
-        let mut found = false;
+        let query = SearchQuery::parse(&self.imp().search_entry.text(), &glib::home_dir());
+        let words = query.highlight_words();
         let map = UserConfigManager::instance().get_map();
-        for (k, v) in &map {
-            let k_search = &v.name.to_lowercase();
-
-            if k_search.contains(&text.to_string().to_lowercase()) {
-                if !found {
-                    self.imp().search_list_store.remove_all();
-                    found = true;
-                }
-
-                let vault = Vault::new(
-                    *k,
-                    v.name.to_owned(),
-                    v.backend,
-                    v.encrypted_data_directory.to_owned(),
-                    v.mount_directory.to_owned(),
-                    v.session_lock,
-                );
 
-                let row = VaultsPageRow::new(vault);
-                self.search_row_connect_signals(&row);
+        // Rows are created once per vault and reused on every keystroke
+        let mut results = vec![];
+        {
+            let mut search_rows = self.imp().search_rows.borrow_mut();
+            search_rows.retain(|uuid, row| {
+                UserConfigManager::instance()
+                    .get_vault(*uuid)
+                    .is_some_and(|vault| vault == row.get_vault())
+            });
 
-                self.imp().search_list_store.insert_sorted(&row, |v1, v2| {
-                    let row1 = v1.downcast_ref::<VaultsPageRow>().unwrap();
-                    let name1 = row1.get_name();
-                    let row2 = v2.downcast_ref::<VaultsPageRow>().unwrap();
-                    let name2 = row2.get_name();
-                    name1.cmp(&name2)
-                });
+            for (k, v) in &map {
+                let row = match search_rows.get(k) {
+                    Some(row) => row.clone(),
+                    None => {
+                        let Some(vault) = UserConfigManager::instance().get_vault(*k) else {
+                            continue;
+                        };
+                        let row = VaultsPageRow::new(vault);
+                        self.search_row_connect_signals(&row);
+                        search_rows.insert(*k, row.clone());
+                        row
+                    }
+                };
 
-                *self.imp().search_results.borrow_mut() += 1;
+                if query.matches(v, row.state()) {
+                    row.set_highlight(words.clone());
+                    results.push(row);
+                }
             }
         }
+        let results = self.sort_rows(results);
+
+        self.imp().search_list_store.remove_all();
+        self.imp().search_list_store.extend_from_slice(&results);
+        *self.imp().search_results.borrow_mut() = results.len() as u32;
 
         if map.is_empty() {
             self.imp().search_stack.set_visible_child_name("start");
             return;
         }
 
-        if found {
-            self.imp().search_stack.set_visible_child_name("results");
-        } else {
+        if results.is_empty() {
             self.imp().search_stack.set_visible_child_name("no-results");
+        } else {
+            self.imp().search_stack.set_visible_child_name("results");
         }
     }
 
@@ -271,10 +450,27 @@ impl ApplicationWindow {
             #[weak(rename_to = obj)]
             self,
             move |map_is_empty| {
+                // Callers may still be iterating over the list store
+                glib::idle_add_local_once(clone!(
+                    #[weak]
+                    obj,
+                    move || {
+                        obj.sync_list_store();
+                    }
+                ));
+                obj.queue_regroup();
                 obj.refresh_view(map_is_empty);
             }
         ));
 
+        UserConfigManager::instance().connect_conflict(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.show_conflict_dialog();
+            }
+        ));
+
         self.imp()
             .vaults_list_box
             .bind_model(Some(&self.imp().list_store), |obj| {
@@ -284,6 +480,272 @@ impl ApplicationWindow {
         self.fill_list_store();
     }
 
+    fn setup_grouping(&self) {
+        self.add_action(&self.imp().settings.create_action("group-by-tag"));
+
+        self.imp().settings.connect_changed(
+            Some("group-by-tag"),
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.update_grouping();
+                }
+            ),
+        );
+
+        self.imp().list_store.connect_items_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_, _, _, _| {
+                obj.queue_regroup();
+            }
+        ));
+
+        // Tags may have changed
+        UserConfigManager::instance().connect_change_vault(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.queue_regroup();
+            }
+        ));
+
+        self.update_grouping();
+    }
+
+    fn is_grouped(&self) -> bool {
+        self.imp().settings.boolean("group-by-tag")
+    }
+
+    fn update_grouping(&self) {
+        let imp = self.imp();
+        let is_grouped = self.is_grouped();
+
+        // A row can only be shown in one list box
+        if is_grouped {
+            imp.vaults_list_box.bind_model(None::<&ListStore>, |obj| {
+                obj.clone().downcast::<gtk::Widget>().unwrap()
+            });
+            self.regroup();
+        } else {
+            self.clear_groups();
+            imp.vaults_list_box
+                .bind_model(Some(&imp.list_store), |obj| {
+                    obj.clone().downcast::<gtk::Widget>().unwrap()
+                });
+        }
+
+        imp.vaults_list_box.set_visible(!is_grouped);
+        imp.vaults_groups_list_box.set_visible(is_grouped);
+    }
+
+    // Rows are added and removed one at a time, group them once afterwards
+    fn queue_regroup(&self) {
+        if !self.is_grouped() || self.imp().regroup_queued.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.imp().regroup_queued.replace(false);
+                if obj.is_grouped() {
+                    obj.regroup();
+                }
+            }
+        ));
+    }
+
+    fn regroup(&self) {
+        log::trace!("regroup()");
+
+        self.clear_groups();
+
+        let mut groups: Vec<(Option<String>, Vec<VaultsPageRow>)> = vec![];
+        for row in self.imp().list_store.iter::<VaultsPageRow>().flatten() {
+            let config = row.get_vault().config();
+            for (i, group) in vault_tags::groups(&config).into_iter().enumerate() {
+                let group = group.map(str::to_string);
+                // A row can only be in one group, the others get a copy
+                let row = if i == 0 {
+                    row.clone()
+                } else {
+                    self.group_row(&row)
+                };
+
+                let existing = groups.iter_mut().find(|(g, _)| {
+                    vault_tags::compare_groups(g.as_deref(), group.as_deref()).is_eq()
+                });
+                match existing {
+                    Some((_, rows)) => rows.push(row),
+                    None => groups.push((group, vec![row])),
+                }
+            }
+        }
+        groups.sort_by(|(a, _), (b, _)| vault_tags::compare_groups(a.as_deref(), b.as_deref()));
+
+        for (group, rows) in groups {
+            let key = group.as_deref().unwrap_or_default().to_lowercase();
+            let expander_row = adw::ExpanderRow::builder()
+                .title(group.unwrap_or_else(|| gettext("Untagged")))
+                .use_markup(false)
+                .expanded(!self.imp().collapsed_groups.borrow().contains(&key))
+                .build();
+
+            expander_row.connect_expanded_notify(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |expander_row| {
+                    let mut collapsed_groups = obj.imp().collapsed_groups.borrow_mut();
+                    if expander_row.is_expanded() {
+                        collapsed_groups.remove(&key);
+                    } else {
+                        collapsed_groups.insert(key.clone());
+                    }
+                }
+            ));
+
+            for row in rows {
+                expander_row.add_row(&row);
+            }
+            self.imp().vaults_groups_list_box.append(&expander_row);
+        }
+    }
+
+    // Shows the same vault as `row`, and is selected together with it
+    fn group_row(&self, row: &VaultsPageRow) -> VaultsPageRow {
+        let group_row = VaultsPageRow::new(row.get_vault());
+        self.search_row_connect_signals(&group_row);
+
+        row.imp()
+            .select_vault_button
+            .bind_property(
+                "active",
+                &group_row.imp().select_vault_button.get(),
+                "active",
+            )
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        group_row
+    }
+
+    fn clear_groups(&self) {
+        // The rows belong to the list store, take them out of the groups
+        // before dropping them
+        for row in self.imp().list_store.iter::<gtk::Widget>().flatten() {
+            let Some(list_box) = row.parent().and_downcast::<gtk::ListBox>() else {
+                continue;
+            };
+            if list_box != *self.imp().vaults_list_box {
+                list_box.remove(&row);
+            }
+        }
+
+        self.imp().vaults_groups_list_box.remove_all();
+    }
+
+    fn setup_sorting(&self) {
+        self.add_action(&self.imp().settings.create_action("sort-order"));
+
+        self.imp().settings.connect_changed(
+            Some("sort-order"),
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.queue_sort();
+                    if obj.get_view().is_some_and(|view| view == "search") {
+                        obj.search();
+                    }
+                }
+            ),
+        );
+
+        // Names or favorites may have changed
+        UserConfigManager::instance().connect_change_vault(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.queue_sort();
+            }
+        ));
+    }
+
+    fn sort_rows(&self, rows: Vec<VaultsPageRow>) -> Vec<VaultsPageRow> {
+        let order = SortOrder::from_setting(&self.imp().settings.string("sort-order"));
+        let all_usage = match order {
+            SortOrder::LastOpened | SortOrder::MostOpened | SortOrder::Created => {
+                vault_usage::read_all()
+            }
+            _ => HashMap::new(),
+        };
+
+        let mut entries: Vec<_> = rows
+            .into_iter()
+            .map(|row| {
+                let vault = row.get_vault();
+                let usage = all_usage
+                    .get(&vault.get_uuid())
+                    .cloned()
+                    .unwrap_or_default();
+                (
+                    vault.config(),
+                    vault.state() == VaultState::Unlocked,
+                    usage,
+                    row,
+                )
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            vault_sort::compare(
+                order,
+                &SortEntry {
+                    config: &a.0,
+                    is_open: a.1,
+                    usage: &a.2,
+                },
+                &SortEntry {
+                    config: &b.0,
+                    is_open: b.1,
+                    usage: &b.2,
+                },
+            )
+        });
+
+        entries.into_iter().map(|(_, _, _, row)| row).collect()
+    }
+
+    fn queue_sort(&self) {
+        if self.imp().sort_queued.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.imp().sort_queued.replace(false);
+                obj.sort_list_store();
+            }
+        ));
+    }
+
+    fn sort_list_store(&self) {
+        let list_store = &self.imp().list_store;
+        let rows: Vec<VaultsPageRow> = list_store.iter::<VaultsPageRow>().flatten().collect();
+        let sorted_rows = self.sort_rows(rows.clone());
+
+        // Replacing the rows rebuilds the list box, only do it if one moved
+        if sorted_rows != rows {
+            list_store.splice(0, list_store.n_items(), &sorted_rows);
+        }
+    }
+
     fn setup_gactions(&self) {
         action!(
             self,
@@ -325,6 +787,18 @@ impl ApplicationWindow {
             )
         );
 
+        action!(
+            self,
+            "lock-all",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.lock_all_clicked();
+                }
+            )
+        );
+
         action!(
             self,
             "add_new_vault",
@@ -348,6 +822,78 @@ impl ApplicationWindow {
                 }
             )
         );
+
+        action!(
+            self,
+            "scan_vaults",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.scan_vaults_clicked();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "restore_backup",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.restore_backup_clicked();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "import_plasma_vault",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.import_from_application_clicked(ImportSource::PlasmaVault);
+                }
+            )
+        );
+
+        action!(
+            self,
+            "import_sirikali",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.import_from_application_clicked(ImportSource::SiriKali);
+                }
+            )
+        );
+
+        action!(
+            self,
+            "export_vaults",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.export_vaults_clicked();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "import_vaults",
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_, _| {
+                    obj.import_vaults_clicked();
+                }
+            )
+        );
     }
 
     fn setup_signals(&self) {
@@ -397,54 +943,91 @@ impl ApplicationWindow {
             #[weak(rename_to = obj)]
             self,
             move |_| {
-                if obj.get_view().unwrap() == "vaults" {
-                    for row in obj.imp().list_store.into_iter() {
-                        let vault_row = row.unwrap();
-                        let vault_row = vault_row.downcast_ref::<VaultsPageRow>().unwrap();
-                        if vault_row.selected() {
-                            let vault = vault_row.get_vault();
-                            UserConfigManager::instance().remove_vault(vault.get_uuid());
-                        }
-                    }
-                    obj.refresh_model();
-                } else if obj.get_view().unwrap() == "search" {
-                    for row in obj.imp().search_list_store.into_iter() {
-                        let vault_row = row.unwrap();
-                        let vault_row = vault_row.downcast_ref::<VaultsPageRow>().unwrap();
-                        if vault_row.selected() {
-                            let vault = vault_row.get_vault();
-                            UserConfigManager::instance().remove_vault(vault.get_uuid());
-                        }
-                    }
-                    obj.search();
-                }
+                let list_store = if obj.get_view().unwrap() == "search" {
+                    &obj.imp().search_list_store
+                } else {
+                    &obj.imp().list_store
+                };
+
+                let vaults = list_store
+                    .iter::<VaultsPageRow>()
+                    .flatten()
+                    .filter(|row| row.selected())
+                    .map(|row| row.get_vault())
+                    .collect();
+                obj.remove_vaults(vaults);
             }
         ));
     }
 
     fn fill_list_store(&self) {
         let map = UserConfigManager::instance().get_map();
-        for (k, v) in map.iter() {
-            let vault = Vault::new(
-                *k,
-                v.name.to_owned(),
-                v.backend,
-                v.encrypted_data_directory.to_owned(),
-                v.mount_directory.to_owned(),
-                v.session_lock,
-            );
+        let mut rows = vec![];
+        for k in map.keys() {
+            let Some(vault) = UserConfigManager::instance().get_vault(*k) else {
+                continue;
+            };
+            vault.refresh_state();
 
             let row = VaultsPageRow::new(vault);
             self.row_connect_signals(&row);
+            rows.push(row);
+        }
 
-            self.imp().list_store.insert_sorted(&row, |v1, v2| {
-                let row1 = v1.downcast_ref::<VaultsPageRow>().unwrap();
-                let name1 = row1.get_name();
-                let row2 = v2.downcast_ref::<VaultsPageRow>().unwrap();
-                let name2 = row2.get_name();
-                name1.cmp(&name2)
-            });
+        self.imp()
+            .list_store
+            .extend_from_slice(&self.sort_rows(rows));
+    }
+
+    pub fn find_row(&self, uuid: Uuid) -> Option<VaultsPageRow> {
+        self.imp()
+            .list_store
+            .iter::<VaultsPageRow>()
+            .flatten()
+            .find(|row| row.get_vault().get_uuid() == uuid)
+    }
+
+    // Rebuilds the list if vaults were added or removed outside of it, e.g. by
+    // editing the config file
+    fn sync_list_store(&self) {
+        let map = UserConfigManager::instance().get_map();
+        let list_store = &self.imp().list_store;
+
+        let is_in_sync = list_store.n_items() as usize == map.len()
+            && list_store
+                .iter::<VaultsPageRow>()
+                .all(|row| row.is_ok_and(|row| map.contains_key(&row.get_vault().get_uuid())));
+        if is_in_sync {
+            return;
         }
+
+        self.clear();
+        self.fill_list_store();
+    }
+
+    fn show_conflict_dialog(&self) {
+        glib::MainContext::default().spawn_local(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let use_file = gettext("Use File");
+                let keep_changes = gettext("Keep My Changes");
+                let dialog = gtk::AlertDialog::builder()
+                    .message(gettext("Configuration Changed"))
+                    .detail(gettext(
+                        "The vault configuration file was changed by another program while you were editing vaults.",
+                    ))
+                    .buttons([use_file.as_str(), keep_changes.as_str()])
+                    .cancel_button(0)
+                    .default_button(0)
+                    .modal(true)
+                    .build();
+
+                let keep_changes = dialog.choose_future(Some(&obj)).await == Ok(1);
+                UserConfigManager::instance().resolve_conflict(keep_changes);
+                obj.refresh_model();
+            }
+        ));
     }
 
     pub fn search_row_connect_signals(&self, row: &VaultsPageRow) {
@@ -454,27 +1037,7 @@ impl ApplicationWindow {
             #[weak]
             row,
             move || {
-                let obj_ = imp::ApplicationWindow::from_obj(&obj);
-                let index = obj_.search_list_store.find(&row);
-                if let Some(index) = index {
-                    obj_.search_list_store.remove(index);
-
-                    *obj_.search_results.borrow_mut() -= 1;
-
-                    if *obj_.search_results.borrow_mut() == 0 {
-                        if UserConfigManager::instance().get_map().is_empty() {
-                            obj_.search_stack.set_visible_child_name("start");
-                            obj_.search_entry.set_text("");
-                            return;
-                        }
-                        if obj.get_view().unwrap() != "search" {
-                            obj.set_view(View::Search);
-                        }
-                        obj_.search_stack.set_visible_child_name("no-results");
-                    }
-                } else {
-                    log::error!("Vault not initialised!");
-                }
+                obj.remove_vaults(vec![row.get_vault()]);
             }
         ));
 
@@ -488,21 +1051,22 @@ impl ApplicationWindow {
     }
 
     pub fn row_connect_signals(&self, row: &VaultsPageRow) {
+        // Opening and closing moves vaults when sorting by state or usage
+        row.connect_state_notify(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.queue_sort();
+            }
+        ));
+
         row.connect_remove(clone!(
             #[weak(rename_to = obj)]
             self,
             #[weak]
             row,
             move || {
-                let index = obj.imp().list_store.find(&row);
-                if let Some(index) = index {
-                    obj.imp().list_store.remove(index);
-                    if UserConfigManager::instance().get_map().is_empty() {
-                        obj.imp().search_entry.set_text("");
-                    }
-                } else {
-                    log::error!("Vault not initialised!");
-                }
+                obj.remove_vaults(vec![row.get_vault()]);
             }
         ));
 
@@ -515,12 +1079,74 @@ impl ApplicationWindow {
         .build();
     }
 
+    /// Removes the vaults from the config, keeping their encrypted data. The
+    /// toast offers to undo it.
+    pub fn remove_vaults(&self, vaults: Vec<Vault>) {
+        log::trace!("remove_vaults({})", vaults.len());
+
+        let removed: Vec<(Uuid, VaultConfig)> = vaults
+            .iter()
+            .map(|vault| (vault.get_uuid(), vault.config()))
+            .collect();
+        let title = match removed.as_slice() {
+            [] => return,
+            [(_, config)] => format!("{}: {}", gettext("Vault removed"), config.name),
+            _ => format!("{}: {}", gettext("Vaults removed"), removed.len()),
+        };
+
+        for (uuid, _) in &removed {
+            UserConfigManager::instance().remove_vault(*uuid);
+        }
+        self.refresh_after_change();
+
+        let toast = adw::Toast::builder()
+            .title(title)
+            .use_markup(false)
+            .button_label(gettext("_Undo"))
+            .priority(adw::ToastPriority::High)
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.restore_vaults(&removed);
+            }
+        ));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    // Adds removed vaults back under their old UUIDs
+    fn restore_vaults(&self, removed: &[(Uuid, VaultConfig)]) {
+        log::trace!("restore_vaults({})", removed.len());
+
+        let map = UserConfigManager::instance().get_map();
+        for (uuid, config) in removed {
+            if map.contains_key(uuid) {
+                log::warn!("Vault {} was added again, not restoring it", uuid);
+                continue;
+            }
+
+            UserConfigManager::instance().add_vault(Vault::from_config(*uuid, config.clone()));
+        }
+        self.refresh_after_change();
+    }
+
+    fn refresh_after_change(&self) {
+        if self.get_view().is_some_and(|view| view == "search") {
+            self.search();
+        } else {
+            self.refresh_model();
+        }
+    }
+
     pub fn refresh_view(&self, map_is_empty: bool) {
         if self.imp().search_toggle_button.is_active() {
             return;
         }
 
-        if map_is_empty {
+        if UserConfigManager::instance().parse_error().is_some() {
+            self.set_view(View::ConfigError);
+        } else if map_is_empty {
             self.set_view(View::Start);
         } else {
             self.set_view(View::Vaults);
@@ -528,7 +1154,9 @@ impl ApplicationWindow {
     }
 
     pub fn refresh_new(&self) {
-        if UserConfigManager::instance().get_map().is_empty() {
+        if UserConfigManager::instance().parse_error().is_some() {
+            self.set_view(View::ConfigError);
+        } else if UserConfigManager::instance().get_map().is_empty() {
             self.set_view(View::Start);
         } else {
             self.set_view(View::Vaults);
@@ -553,6 +1181,11 @@ impl ApplicationWindow {
                     let password = dialog.get_password();
                     match Backend::init(&vault.config(), password) {
                         Ok(_) => {
+                            if dialog.get_write_descriptor() {
+                                if let Err(e) = vault_descriptor::write(&vault.config()) {
+                                    log::error!("Could not write vault descriptor: {}", e);
+                                }
+                            }
                             UserConfigManager::instance().add_vault(vault);
                             obj.refresh_model();
                             obj.set_view(View::Vaults);
@@ -638,6 +1271,258 @@ impl ApplicationWindow {
         AdwDialogExt::present(&dialog, Some(&window));
     }
 
+    fn scan_vaults_clicked(&self) {
+        glib::spawn_future_local(clone!(
+            #[strong(rename_to = obj)]
+            self,
+            async move {
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Choose Folder to Scan"))
+                    .modal(true)
+                    .accept_label(gettext("Scan"))
+                    .build();
+
+                let Ok(folder) = dialog.select_folder_future(Some(&obj)).await else {
+                    return;
+                };
+                let Some(path) = folder.path() else {
+                    return;
+                };
+
+                let scan_dialog = VaultBundleDialog::new_scan(path);
+                scan_dialog.connect_closure(
+                    "import",
+                    false,
+                    closure_local!(
+                        #[weak]
+                        obj,
+                        move |_: VaultBundleDialog| {
+                            obj.refresh_model();
+                        }
+                    ),
+                );
+                AdwDialogExt::present(&scan_dialog, Some(&obj));
+            }
+        ));
+    }
+
+    fn restore_backup_clicked(&self) {
+        glib::spawn_future_local(clone!(
+            #[strong(rename_to = obj)]
+            self,
+            async move {
+                let filter = gtk::FileFilter::new();
+                filter.set_name(Some(&gettext("Vault Backups")));
+                filter.add_suffix(vault_backup::FILE_EXTENSION);
+                let filters = ListStore::new::<gtk::FileFilter>();
+                filters.append(&filter);
+
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Restore from Backup"))
+                    .modal(true)
+                    .accept_label(gettext("Open"))
+                    .filters(&filters)
+                    .build();
+
+                let Ok(file) = dialog.open_future(Some(&obj)).await else {
+                    return;
+                };
+                let Some(archive) = file.path() else {
+                    return;
+                };
+
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Choose Restore Location"))
+                    .modal(true)
+                    .accept_label(gettext("Select"))
+                    .build();
+
+                let Ok(folder) = dialog.select_folder_future(Some(&obj)).await else {
+                    return;
+                };
+                let Some(folder) = folder.path() else {
+                    return;
+                };
+
+                // The encrypted data gets the name of the archive
+                let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+                let name = file_name
+                    .strip_suffix(&format!(".{}", vault_backup::FILE_EXTENSION))
+                    .unwrap_or(&file_name);
+                let destination = folder.join(name);
+                if destination.exists() {
+                    let info_dialog = gtk::AlertDialog::builder()
+                        .modal(true)
+                        .message(gettext("Could not restore backup"))
+                        .detail(format!(
+                            "{}: {}",
+                            gettext("The folder already exists"),
+                            destination.display()
+                        ))
+                        .build();
+                    info_dialog.show(Some(&obj));
+                    return;
+                }
+
+                let restore_dialog = VaultBackupDialog::new_restore(archive, destination);
+                restore_dialog.connect_restore(clone!(
+                    #[weak]
+                    obj,
+                    move || {
+                        obj.refresh_model();
+                        obj.set_view(View::Vaults);
+                    }
+                ));
+                AdwDialogExt::present(&restore_dialog, Some(&obj));
+            }
+        ));
+    }
+
+    fn export_vaults_clicked(&self) {
+        let selected: Vec<Uuid> = self
+            .imp()
+            .list_store
+            .iter::<VaultsPageRow>()
+            .flatten()
+            .filter(|row| row.selected())
+            .map(|row| row.get_vault().get_uuid())
+            .collect();
+
+        let dialog = VaultBundleDialog::new_export(&selected);
+        AdwDialogExt::present(&dialog, Some(self));
+    }
+
+    fn import_vaults_clicked(&self) {
+        glib::spawn_future_local(clone!(
+            #[strong(rename_to = obj)]
+            self,
+            async move {
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Import Vaults"))
+                    .modal(true)
+                    .accept_label(gettext("Open"))
+                    .build();
+
+                let Ok(file) = dialog.open_future(Some(&obj)).await else {
+                    return;
+                };
+                let Some(path) = file.path() else {
+                    return;
+                };
+
+                let entries = match vault_bundle::read(
+                    &path,
+                    &glib::home_dir(),
+                    &UserConfigManager::instance().get_map(),
+                ) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        log::error!("Could not read vault bundle: {}", e);
+                        let info_dialog = gtk::AlertDialog::builder()
+                            .modal(true)
+                            .message(gettext("Could not read vaults"))
+                            .detail(e)
+                            .build();
+                        info_dialog.show(Some(&obj));
+                        return;
+                    }
+                };
+
+                obj.present_import_dialog(&gettext("Import Vaults"), entries);
+            }
+        ));
+    }
+
+    fn import_from_application_clicked(&self, source: ImportSource) {
+        let (title, path, result) = match source {
+            ImportSource::PlasmaVault => {
+                let path = importers::plasma_vault::config_path();
+                let result = std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| importers::plasma_vault::parse(&content));
+                (gettext("Import from Plasma Vault"), path, result)
+            }
+            ImportSource::SiriKali => {
+                let path = importers::sirikali::favorites_path();
+                let result = importers::sirikali::read(&path);
+                (gettext("Import from SiriKali"), path, result)
+            }
+        };
+
+        let detail = match result {
+            Ok(vaults) if !vaults.is_empty() => {
+                let entries =
+                    importers::to_entries(vaults, &UserConfigManager::instance().get_map());
+                self.present_import_dialog(&title, entries);
+                return;
+            }
+            Ok(_) => format!("{} {}", gettext("No vaults found in"), path.display()),
+            Err(e) => {
+                log::error!("Could not read {:?}: {}", path, e);
+                format!("{}: {}", path.display(), e)
+            }
+        };
+
+        let info_dialog = gtk::AlertDialog::builder()
+            .modal(true)
+            .message(gettext("Could not read vaults"))
+            .detail(detail)
+            .build();
+        info_dialog.show(Some(self));
+    }
+
+    fn present_import_dialog(&self, title: &str, entries: Vec<BundleEntry>) {
+        let dialog = VaultBundleDialog::new_import(title, entries);
+        dialog.connect_closure(
+            "import",
+            false,
+            closure_local!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_: VaultBundleDialog| {
+                    obj.refresh_model();
+                }
+            ),
+        );
+        AdwDialogExt::present(&dialog, Some(self));
+    }
+
+    fn lock_all_clicked(&self) {
+        log::trace!("lock_all_clicked()");
+
+        let vaults = UserConfigManager::instance().get_unlocked_vaults();
+        vault::lock_all(
+            vaults,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |failures| {
+                    obj.show_lock_failures(&failures);
+                }
+            ),
+        );
+    }
+
+    /// Lists the vaults that could not be locked, if any.
+    pub fn show_lock_failures(&self, failures: &[(Vault, BackendError)]) {
+        if failures.is_empty() {
+            return;
+        }
+
+        let detail = failures
+            .iter()
+            .map(|(vault, e)| format!("{}: {}", vault.name(), e))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let info_dialog = gtk::AlertDialog::builder()
+            .modal(true)
+            .message(gettext("Could Not Lock All Vaults"))
+            .detail(detail)
+            .build();
+        info_dialog.show(Some(self));
+    }
+
     pub fn refresh_clicked(&self) {
         self.refresh_model();
     }
@@ -645,10 +1530,10 @@ impl ApplicationWindow {
     pub fn refresh_model(&self) {
         log::info!("refresh_model()");
 
+        // Only the vaults in memory are shown. Reading the file here would drop
+        // a write that was refused because of a conflict.
         self.clear();
 
-        UserConfigManager::instance().read_config();
-
         self.fill_list_store();
 
         self.imp().add_menu_button.set_sensitive(true);
@@ -657,15 +1542,37 @@ impl ApplicationWindow {
             .start_page_status_page
             .set_description(Some(&gettext("Add or import a Vault.")));
 
-        if UserConfigManager::instance().get_map().is_empty() {
-            self.set_view(View::Start);
-        } else {
-            self.set_view(View::Vaults);
-        }
+        self.refresh_new();
     }
 
     pub fn set_view(&self, view: View) {
+        // Adding vaults would write over the file that could not be read
+        let can_add = view != View::ConfigError;
+        self.imp().add_menu_button.set_sensitive(can_add);
+        for name in [
+            "add_new_vault",
+            "import_vault",
+            "import_vaults",
+            "scan_vaults",
+            "restore_backup",
+            "import_plasma_vault",
+            "import_sirikali",
+        ] {
+            if let Some(action) = self
+                .lookup_action(name)
+                .and_then(|a| a.downcast::<gio::SimpleAction>().ok())
+            {
+                action.set_enabled(can_add);
+            }
+        }
+
         match view {
+            View::ConfigError => {
+                self.update_config_error_page();
+                self.imp()
+                    .window_stack
+                    .set_visible_child_name("config-error");
+            }
             View::Search => self.imp().window_stack.set_visible_child_name("search"),
             View::Start => {
                 self.imp().window_stack.set_visible_child_name("start");
@@ -17,19 +17,60 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::{legacy, vault::*};
+use crate::{config_file, legacy, migrations, vault::*, vault_usage};
+use gettextrs::gettext;
 use gtk::glib::Properties;
 use gtk::{
-    gio::subclass::prelude::*,
-    glib::{self, prelude::*, subclass::Signal, user_config_dir},
+    gio::{self, prelude::*, subclass::prelude::*},
+    glib::{self, subclass::Signal, user_config_dir},
 };
 use once_cell::sync::Lazy;
-use std::{cell::RefCell, collections::HashMap};
+use serde::{Deserialize, Serialize, de::Error as _};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 use toml::de::Error;
 use uuid::Uuid;
 
 static mut USER_CONFIG_MANAGER: Option<UserConfigManager> = None;
 
+#[derive(Debug, Deserialize, Serialize)]
+struct UserConfig {
+    schema_version: i64,
+    #[serde(default)]
+    vaults: HashMap<Uuid, VaultConfig>,
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum WriteError {
+        Unparsed {
+            display("{}", gettext("The list of vaults could not be read, so it is not overwritten."))
+        }
+        Conflict {
+            display("{}", gettext("The list of vaults was changed by another program."))
+        }
+        Serialize(e: toml::ser::Error) {
+            from()
+            display("{}", e)
+        }
+        Io(e: std::io::Error) {
+            from()
+            display("{}", e)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigParseError {
+    pub path: String,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
 mod imp {
     use super::*;
 
@@ -37,7 +78,13 @@ mod imp {
     #[properties(wrapper_type = super::UserConfigManager)]
     pub struct UserConfigManager {
         pub vaults: RefCell<HashMap<Uuid, VaultConfig>>,
+        pub vault_objects: RefCell<HashMap<Uuid, Vault>>,
         pub user_config_directory: RefCell<Option<String>>,
+        pub parse_error: RefCell<Option<ConfigParseError>>,
+        // Contents of the file as last read or written by us
+        pub known_contents: RefCell<Option<String>>,
+        pub has_conflict: RefCell<bool>,
+        pub monitor: RefCell<Option<gio::FileMonitor>>,
         #[property(name = "has-vaults", default = false, get, set)]
         pub has_vaults: RefCell<bool>,
     }
@@ -51,7 +98,12 @@ mod imp {
         fn new() -> Self {
             Self {
                 vaults: RefCell::new(HashMap::new()),
+                vault_objects: RefCell::new(HashMap::new()),
                 user_config_directory: RefCell::new(None),
+                parse_error: RefCell::new(None),
+                known_contents: RefCell::new(None),
+                has_conflict: RefCell::new(false),
+                monitor: RefCell::new(None),
                 has_vaults: RefCell::new(false),
             }
         }
@@ -68,6 +120,7 @@ mod imp {
                     Signal::builder("add-vault").build(),
                     Signal::builder("remove-vault").build(),
                     Signal::builder("change-vault").build(),
+                    Signal::builder("conflict").build(),
                 ]
             });
 
@@ -110,6 +163,13 @@ impl UserConfigManager {
         })
     }
 
+    pub fn connect_conflict<F: Fn() + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_local("conflict", false, move |_| {
+            callback();
+            None
+        })
+    }
+
     pub fn instance() -> Self {
         unsafe {
             #[allow(static_mut_refs)]
@@ -150,65 +210,348 @@ impl UserConfigManager {
         self.imp().vaults.borrow().clone()
     }
 
+    pub fn get_vault(&self, uuid: Uuid) -> Option<Vault> {
+        log::trace!("get_vault({:?})", uuid);
+
+        let config = self.imp().vaults.borrow().get(&uuid).cloned()?;
+
+        let vault = self.imp().vault_objects.borrow().get(&uuid).cloned();
+        match vault {
+            Some(vault) => {
+                if vault.config() != config {
+                    vault.set_config(config);
+                }
+                Some(vault)
+            }
+            None => {
+                let vault = Vault::from_config(uuid, config);
+                vault.refresh_state();
+                self.imp()
+                    .vault_objects
+                    .borrow_mut()
+                    .insert(uuid, vault.clone());
+                Some(vault)
+            }
+        }
+    }
+
+    /// Returns the vaults that are unlocked right now, sorted by name.
+    pub fn get_unlocked_vaults(&self) -> Vec<Vault> {
+        log::trace!("get_unlocked_vaults()");
+
+        let mut vaults: Vec<Vault> = self
+            .get_map()
+            .keys()
+            .filter_map(|uuid| self.get_vault(*uuid))
+            .filter(|vault| {
+                vault.refresh_state();
+                vault.state() == VaultState::Unlocked
+            })
+            .collect();
+        vaults.sort_by_key(|vault| vault.name().to_lowercase());
+
+        vaults
+    }
+
+    /// Replaces the vaults in memory with the ones in the file. A conflict is
+    /// kept until it is resolved.
     pub fn read_config(&self) {
         log::trace!("read_config()");
 
         if let Some(path) = self.imp().user_config_directory.borrow().as_ref() {
+            let map = &mut *self.imp().vaults.borrow_mut();
+            map.clear();
+            self.imp().parse_error.replace(None);
+            self.imp()
+                .known_contents
+                .replace(std::fs::read_to_string(path).ok());
+
             if !std::path::Path::new(path).exists() {
                 log::info!("User config file does not exist: {}", path);
                 log::info!("Trying to read legacy user config...");
-                let user_config = legacy::user_config::get_user_config_from_legacy_config();
-                let map = &mut *self.imp().vaults.borrow_mut();
-                map.clear();
-                *map = user_config;
+                if let Some(content) = legacy::user_config::read_legacy_user_config() {
+                    match parse_user_config(&content, Some(migrations::LEGACY_SCHEMA_VERSION)) {
+                        Ok((v, _)) => {
+                            *map = v;
+                            // Converted again on the next start
+                            let _ = self.write_config(map);
+                        }
+                        Err(e) => {
+                            log::error!("Failed to convert legacy user config: {}", e);
+                        }
+                    }
+                }
+                self.set_has_vaults(!map.is_empty());
                 return;
             }
 
-            let map = &mut *self.imp().vaults.borrow_mut();
-            map.clear();
-
             let contents = std::fs::read_to_string(path);
             match contents {
-                Ok(content) => {
-                    let res: Result<HashMap<Uuid, VaultConfig>, Error> =
-                        toml::from_str(&content.clone());
-                    match res {
-                        Ok(v) => {
-                            *map = v;
-                        }
-                        Err(e) => {
-                            log::error!("Failed to parse user data config: {}", e);
+                Ok(content) => match parse_user_config(&content, None) {
+                    Ok((v, migrated)) => {
+                        *map = v;
+                        if migrated {
+                            // Migrated again on the next start
+                            let _ = self.write_config(map);
                         }
                     }
+                    Err(e) => {
+                        log::error!("Failed to parse user data config: {}", e);
+                        let (line, column) = e
+                            .span()
+                            .map(|span| config_file::line_column(&content, span.start))
+                            .unzip();
+                        self.imp().parse_error.replace(Some(ConfigParseError {
+                            path: path.to_string(),
+                            message: e.message().to_string(),
+                            line,
+                            column,
+                        }));
+                    }
+                },
+                // Deleted meanwhile, so there are no vaults
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    log::warn!("Failed to read user data config: {}", e);
                 }
+                // The file is still there, so it must not be overwritten
                 Err(e) => {
-                    log::warn!("Failed to read user data config: {}", e);
+                    log::error!("Failed to read user data config: {}", e);
+                    self.imp().parse_error.replace(Some(ConfigParseError {
+                        path: path.to_string(),
+                        message: e.to_string(),
+                        line: None,
+                        column: None,
+                    }));
                 }
             }
 
+            self.imp()
+                .vault_objects
+                .borrow_mut()
+                .retain(|uuid, _| map.contains_key(uuid));
+
             self.set_has_vaults(!map.is_empty());
         }
     }
 
-    pub fn write_config(&self, map: &mut HashMap<Uuid, VaultConfig>) {
+    /// Writes the vaults to the file. Nothing is written while the file could
+    /// not be parsed or was changed by someone else.
+    pub fn write_config(&self, map: &mut HashMap<Uuid, VaultConfig>) -> Result<(), WriteError> {
         log::trace!("write_config({:?})", &map);
-        if let Some(path) = self.imp().user_config_directory.borrow().as_ref() {
-            match toml::to_string_pretty(&map) {
-                Ok(contents) => match std::fs::write(path, &contents) {
-                    Ok(_) => {
-                        log::debug!("Successfully wrote user config: {}", &contents);
-                    }
-                    Err(e) => {
-                        log::error!("Failed to write user config: {}", e);
-                    }
-                },
-                Err(e) => {
-                    log::error!("Failed to parse config: {}", e);
+        if self.imp().parse_error.borrow().is_some() {
+            log::error!("Not overwriting user config that could not be parsed");
+            return Err(WriteError::Unparsed);
+        }
+
+        let Some(path) = self.imp().user_config_directory.borrow().clone() else {
+            return Err(WriteError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No user config path",
+            )));
+        };
+
+        if *self.imp().has_conflict.borrow() {
+            log::warn!("Not overwriting user config with unresolved conflict");
+            return Err(WriteError::Conflict);
+        }
+
+        // The file changed since we last saw it, but the monitor has not
+        // caught up yet. Writing now would drop the other change.
+        let disk_contents = std::fs::read_to_string(&path).ok();
+        if disk_contents != *self.imp().known_contents.borrow() {
+            log::warn!("User config was changed by someone else");
+            self.imp().has_conflict.replace(true);
+            // Signal handlers may read the map, which is still borrowed
+            glib::idle_add_local_once(glib::clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move || {
+                    obj.emit_by_name::<()>("conflict", &[]);
                 }
+            ));
+            return Err(WriteError::Conflict);
+        }
+
+        let user_config = UserConfig {
+            schema_version: migrations::CURRENT_SCHEMA_VERSION,
+            vaults: map.clone(),
+        };
+        let contents = toml::to_string_pretty(&user_config).inspect_err(|e| {
+            log::error!("Failed to serialize config: {}", e);
+        })?;
+        config_file::write(Path::new(&path), &contents).inspect_err(|e| {
+            log::error!("Failed to write user config: {}", e);
+        })?;
+
+        log::debug!("Successfully wrote user config: {}", &contents);
+        self.imp().known_contents.replace(Some(contents));
+
+        Ok(())
+    }
+
+    /// Watches the config file and reloads it when it is changed by another
+    /// program.
+    pub fn start_monitoring(&self) {
+        log::trace!("start_monitoring()");
+
+        let Some(path) = self.imp().user_config_directory.borrow().clone() else {
+            return;
+        };
+
+        let file = gio::File::for_path(&path);
+        match file.monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE) {
+            Ok(monitor) => {
+                monitor.connect_changed(glib::clone!(
+                    #[weak(rename_to = obj)]
+                    self,
+                    move |_, _, _, event| {
+                        obj.file_changed(event);
+                    }
+                ));
+                self.imp().monitor.replace(Some(monitor));
+            }
+            Err(e) => {
+                log::error!("Failed to monitor user config: {}", e);
             }
         }
     }
 
+    fn file_changed(&self, event: gio::FileMonitorEvent) {
+        log::trace!("file_changed({:?})", event);
+
+        // Partial writes end with a hint, atomic writes are moved into place.
+        // A deleted file is left alone, it is recreated on the next write.
+        match event {
+            gio::FileMonitorEvent::ChangesDoneHint
+            | gio::FileMonitorEvent::Created
+            | gio::FileMonitorEvent::Renamed
+            | gio::FileMonitorEvent::MovedIn => {}
+            _ => return,
+        }
+
+        // The conflict dialog decides what happens with the file
+        if *self.imp().has_conflict.borrow() {
+            return;
+        }
+
+        let Some(path) = self.imp().user_config_directory.borrow().clone() else {
+            return;
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return;
+        };
+        if Some(&contents) == self.imp().known_contents.borrow().as_ref() {
+            return;
+        }
+
+        log::info!("User config was changed externally, reloading");
+        self.reload();
+    }
+
+    /// Resolves a conflict by either overwriting the file with the vaults in
+    /// memory or by discarding them in favor of the file.
+    pub fn resolve_conflict(&self, keep_changes: bool) {
+        log::trace!("resolve_conflict({})", keep_changes);
+
+        if !keep_changes {
+            self.imp().has_conflict.replace(false);
+            self.reload();
+            return;
+        }
+
+        let path = self.imp().user_config_directory.borrow().clone();
+        if let Some(path) = path {
+            self.imp()
+                .known_contents
+                .replace(std::fs::read_to_string(path).ok());
+        }
+        self.imp().has_conflict.replace(false);
+
+        if let Err(e) = self.write_config(&mut self.get_map()) {
+            log::error!("Could not keep changes: {}", e);
+        }
+    }
+
+    fn reload(&self) {
+        self.read_config();
+
+        // Shared vault objects pick up their changed configs
+        let uuids: Vec<Uuid> = self.imp().vault_objects.borrow().keys().cloned().collect();
+        for uuid in uuids {
+            self.get_vault(uuid);
+        }
+
+        let is_map_empty = self.imp().vaults.borrow().is_empty();
+        self.emit_by_name::<()>("refresh", &[&is_map_empty]);
+    }
+
+    pub fn parse_error(&self) -> Option<ConfigParseError> {
+        self.imp().parse_error.borrow().clone()
+    }
+
+    pub fn newest_valid_backup(&self) -> Option<PathBuf> {
+        log::trace!("newest_valid_backup()");
+
+        let path = PathBuf::from(self.imp().user_config_directory.borrow().as_ref()?);
+
+        config_file::backups(&path).into_iter().find(|backup| {
+            std::fs::read_to_string(backup)
+                .ok()
+                .and_then(|content| parse_user_config(&content, None).ok())
+                .is_some()
+        })
+    }
+
+    pub fn restore_backup(&self, backup: &Path) -> std::io::Result<()> {
+        log::trace!("restore_backup({:?})", backup);
+
+        let Some(path) = self.imp().user_config_directory.borrow().clone() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No user config path",
+            ));
+        };
+        let path = Path::new(&path);
+
+        let contents = std::fs::read_to_string(backup)?;
+
+        // Keep the unreadable file around instead of replacing it silently
+        if path.exists() {
+            config_file::set_aside(path)?;
+        }
+
+        config_file::write_atomic(path, &contents)?;
+
+        self.imp().has_conflict.replace(false);
+        self.reload();
+
+        Ok(())
+    }
+
+    pub fn reset_config(&self) -> std::io::Result<()> {
+        log::trace!("reset_config()");
+
+        let Some(path) = self.imp().user_config_directory.borrow().clone() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No user config path",
+            ));
+        };
+        let path = Path::new(&path);
+
+        if path.exists() {
+            config_file::set_aside(path)?;
+        }
+
+        config_file::write_atomic(path, "")?;
+
+        self.imp().has_conflict.replace(false);
+        self.read_config();
+        self.emit_by_name::<()>("refresh", &[&true]);
+
+        Ok(())
+    }
+
     pub fn add_vault(&self, vault: Vault) {
         log::debug!("Add vault: {:?}, {:?}", &vault.name(), &vault.config());
 
@@ -217,11 +560,14 @@ impl UserConfigManager {
         {
             let map = &mut self.imp().vaults.borrow_mut();
             map.insert(vault.get_uuid(), vault.config());
-            self.write_config(map);
+            // Kept in memory, a conflict lets the user write it later
+            let _ = self.write_config(map);
             is_map_empty = map.is_empty();
         };
         self.set_has_vaults(!is_map_empty);
 
+        vault_usage::record_created(vault.get_uuid());
+
         self.emit_by_name::<()>("add-vault", &[]);
     }
 
@@ -233,7 +579,9 @@ impl UserConfigManager {
         {
             let map = &mut self.imp().vaults.borrow_mut();
             map.remove(&uuid);
-            self.write_config(map);
+            // Kept in memory, a conflict lets the user write it later
+            let _ = self.write_config(map);
+            self.imp().vault_objects.borrow_mut().remove(&uuid);
             is_map_empty = map.is_empty();
         }
         self.set_has_vaults(!is_map_empty);
@@ -242,13 +590,56 @@ impl UserConfigManager {
         self.emit_by_name::<()>("refresh", &[&is_map_empty]);
     }
 
-    pub fn change_vault(&self, uuid: Uuid, new_vault_config: VaultConfig) {
+    /// Replaces the config of the vault. If it cannot be written, the old
+    /// config is kept, so callers can rely on the file once this succeeds.
+    pub fn change_vault(
+        &self,
+        uuid: Uuid,
+        new_vault_config: VaultConfig,
+    ) -> Result<(), WriteError> {
         log::trace!("change_vault({:?}, {:?})", &uuid, &new_vault_config);
 
-        let map = &mut self.imp().vaults.borrow_mut();
-        map.insert(uuid, new_vault_config);
-        self.write_config(map);
+        {
+            let map = &mut self.imp().vaults.borrow_mut();
+            let old_vault_config = map.insert(uuid, new_vault_config.clone());
+            if let Err(e) = self.write_config(map) {
+                match old_vault_config {
+                    Some(old_vault_config) => map.insert(uuid, old_vault_config),
+                    None => map.remove(&uuid),
+                };
+                return Err(e);
+            }
+        }
+
+        let vault = self.imp().vault_objects.borrow().get(&uuid).cloned();
+        if let Some(vault) = vault {
+            vault.set_config(new_vault_config);
+        }
 
         self.emit_by_name::<()>("change-vault", &[]);
+
+        Ok(())
     }
 }
+
+/// Parses the user config, migrating older schema versions first.
+///
+/// Returns the vaults and whether they were migrated. The schema version is
+/// read from the file unless given.
+fn parse_user_config(
+    content: &str,
+    schema_version: Option<i64>,
+) -> Result<(HashMap<Uuid, VaultConfig>, bool), Error> {
+    let mut table: toml::Table = toml::from_str(content)?;
+    let schema_version = schema_version.unwrap_or_else(|| migrations::schema_version(&table));
+
+    let migrated = migrations::migrate(&mut table, schema_version).map_err(Error::custom)?;
+    if !migrated {
+        // Parse the text again to keep line numbers in errors
+        let user_config: UserConfig = toml::from_str(content)?;
+        return Ok((user_config.vaults, false));
+    }
+
+    let user_config: UserConfig = toml::Value::Table(table).try_into()?;
+    Ok((user_config.vaults, true))
+}
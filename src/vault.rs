@@ -18,22 +18,55 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::backend::{Backend, BackendError};
-use gio::VolumeMonitor;
+use crate::mount_watcher::MountWatcher;
+use crate::vault_usage;
+use gettextrs::gettext;
 use gio::prelude::*;
 use gio::subclass::prelude::*;
-use gtk::{gio, glib, glib::Properties};
+use gtk::{
+    gio,
+    glib::{self, Properties, clone},
+};
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use uuid::Uuid;
 
-#[derive(Debug, Default, Deserialize, Clone, Serialize, glib::Boxed)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "VaultState")]
+pub enum VaultState {
+    #[default]
+    Locked,
+    Unlocking,
+    Unlocked,
+    Locking,
+    BackendMissing,
+    Disconnected,
+    Error,
+    /// The encrypted data is moved, backed up or converted
+    Busy,
+}
+
+// Fields added after the first release need `#[serde(default)]`, so older
+// files keep loading without a migration step
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Serialize, glib::Boxed)]
 #[boxed_type(name = "VaultConfig")]
 pub struct VaultConfig {
     pub name: String,
     pub backend: Backend,
     pub encrypted_data_directory: String,
     pub mount_directory: String,
+    #[serde(default)]
     pub session_lock: bool,
+    #[serde(default)]
+    pub use_custom_binary: bool,
+    #[serde(default)]
+    pub custom_binary_path: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Pinned to the top of the list
+    #[serde(default)]
+    pub favorite: bool,
 }
 
 mod imp {
@@ -50,6 +83,14 @@ mod imp {
         #[property(name = "mount-directory", get, set, type = String, member = mount_directory)]
         #[property(name = "session-lock", get, set, type = bool, member = session_lock)]
         pub config: RefCell<VaultConfig>,
+        #[property(get, set, builder(VaultState::Locked))]
+        #[serde(skip)]
+        pub state: Cell<VaultState>,
+        #[property(get, set)]
+        #[serde(skip)]
+        pub error_message: RefCell<String>,
+        #[serde(skip)]
+        pub previous_state: Cell<VaultState>,
     }
 
     #[glib::object_subclass]
@@ -60,7 +101,15 @@ mod imp {
     }
 
     #[glib::derived_properties]
-    impl ObjectImpl for Vault {}
+    impl ObjectImpl for Vault {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.obj().connect_state_notify(|vault| {
+                vault.state_changed();
+            });
+        }
+    }
 }
 
 glib::wrapper! {
@@ -85,11 +134,21 @@ impl Vault {
             encrypted_data_directory,
             mount_directory,
             session_lock,
+            ..Default::default()
         });
 
         object
     }
 
+    pub fn from_config(uuid: Uuid, config: VaultConfig) -> Vault {
+        let object: Self = glib::Object::new();
+
+        object.imp().uuid.replace(uuid);
+        object.imp().config.replace(config);
+
+        object
+    }
+
     pub fn new_none() -> Vault {
         let object: Self = glib::Object::new();
 
@@ -107,6 +166,81 @@ impl Vault {
         *self.imp().uuid.borrow_mut() = uuid;
     }
 
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self.state(),
+            VaultState::Unlocking | VaultState::Locking | VaultState::Busy
+        )
+    }
+
+    /// Keeps the vault locked while its encrypted data is worked on. Returns
+    /// false if the vault is in use.
+    pub fn mark_busy(&self) -> bool {
+        log::trace!("mark_busy");
+
+        self.refresh_state();
+        if self.is_busy() || self.is_mounted() {
+            return false;
+        }
+
+        self.set_state(VaultState::Busy);
+        true
+    }
+
+    pub fn clear_busy(&self) {
+        log::trace!("clear_busy");
+
+        if self.state() != VaultState::Busy {
+            return;
+        }
+
+        self.set_state(VaultState::Locked);
+        self.refresh_state();
+    }
+
+    pub fn refresh_state(&self) {
+        log::trace!("refresh_state");
+
+        if self.is_busy() {
+            log::debug!("Vault is busy, keeping state {:?}", self.state());
+            return;
+        }
+
+        let state = if !self.is_backend_available() {
+            VaultState::BackendMissing
+        } else if self.is_disconnected() {
+            VaultState::Disconnected
+        } else if self.is_mounted() {
+            VaultState::Unlocked
+        } else {
+            VaultState::Locked
+        };
+        log::debug!("Vault state: {:?}", state);
+
+        self.set_state(state);
+    }
+
+    // Only what Vaults did is recorded, not mounts found at startup
+    fn state_changed(&self) {
+        let state = self.state();
+        let previous_state = self.imp().previous_state.replace(state);
+
+        match (previous_state, state) {
+            (VaultState::Unlocking, VaultState::Unlocked) => {
+                vault_usage::record_unlock(self.get_uuid())
+            }
+            (VaultState::Locking, VaultState::Locked) => vault_usage::record_lock(self.get_uuid()),
+            _ => {}
+        }
+    }
+
+    pub fn set_error(&self, message: String) {
+        log::trace!("set_error({})", message);
+
+        self.set_error_message(message);
+        self.set_state(VaultState::Error);
+    }
+
     pub fn init(&self, password: String) -> Result<(), BackendError> {
         log::trace!("init(password: <redacted>)");
         Backend::init(&self.config(), password)
@@ -114,6 +248,7 @@ impl Vault {
 
     pub fn unlock(&self, password: String) -> Result<(), BackendError> {
         log::trace!("unlock(password: <redacted>)");
+        self.check_not_busy()?;
         Backend::open(&self.config(), password)
     }
 
@@ -122,76 +257,117 @@ impl Vault {
         Backend::close(&self.config())
     }
 
-    pub fn is_mounted(&self) -> bool {
-        log::trace!("is_mounted");
+    /// Unlocks the vault on a worker thread. `callback` is called on the main
+    /// thread after the state was updated.
+    pub fn unlock_in_background<F>(&self, password: String, callback: F)
+    where
+        F: FnOnce(&Vault, Result<(), BackendError>) + 'static,
+    {
+        log::trace!("unlock_in_background(password: <redacted>)");
+
+        if let Err(e) = self.check_not_busy() {
+            callback(self, Err(e));
+            return;
+        }
 
-        let config_mount_directory = self.config().mount_directory;
+        let vault_config = self.config();
+        self.set_state(VaultState::Unlocking);
+        self.run_in_background(
+            move || Backend::open(&vault_config, password),
+            VaultState::Unlocked,
+            callback,
+        );
+    }
+
+    /// Locks the vault on a worker thread. `callback` is called on the main
+    /// thread after the state was updated.
+    pub fn lock_in_background<F>(&self, callback: F)
+    where
+        F: FnOnce(&Vault, Result<(), BackendError>) + 'static,
+    {
+        log::trace!("lock_in_background");
 
-        if self.is_mount_hidden() {
-            log::debug!("Vault is hidden");
-            let is_vault_mounted_all = self.is_mounted_all();
-            log::debug!("Vault is mounted (all): {}", is_vault_mounted_all);
-            return is_vault_mounted_all;
+        let vault_config = self.config();
+        self.set_state(VaultState::Locking);
+        self.run_in_background(
+            move || Backend::close(&vault_config),
+            VaultState::Locked,
+            callback,
+        );
+    }
+
+    fn check_not_busy(&self) -> Result<(), BackendError> {
+        if self.state() == VaultState::Busy {
+            return Err(BackendError::ToUser(gettext(
+                "The vault cannot be opened while its encrypted data is moved, backed up or converted.",
+            )));
         }
+        Ok(())
+    }
 
-        let canon_config_path = std::path::Path::new(&config_mount_directory)
-            .canonicalize()
-            .ok();
-
-        if let Some(canon_config_path) = canon_config_path {
-            log::info!(
-                "Opening canonical path: {}",
-                &canon_config_path.as_os_str().to_str().unwrap()
-            );
-            for mount in VolumeMonitor::get().mounts() {
-                let is_configured_mount = mount
-                    .default_location()
-                    .path()
-                    .map(|mount_path| std::path::Path::canonicalize(&mount_path))
-                    .and_then(Result::ok)
-                    .map(|canon_mount_path| canon_mount_path == canon_config_path)
-                    .unwrap_or(false);
-                if is_configured_mount {
-                    return true;
+    fn run_in_background<O, F>(&self, operation: O, finished_state: VaultState, callback: F)
+    where
+        O: FnOnce() -> Result<(), BackendError> + Send + 'static,
+        F: FnOnce(&Vault, Result<(), BackendError>) + 'static,
+    {
+        let (sender, receiver) = async_channel::bounded(1);
+        glib::spawn_future_local(clone!(
+            #[strong(rename_to = vault)]
+            self,
+            async move {
+                if let Ok(result) = receiver.recv().await {
+                    match &result {
+                        Ok(_) => vault.set_state(finished_state),
+                        Err(e) => vault.set_error(e.to_string()),
+                    }
+                    callback(&vault, result);
                 }
             }
-        } else {
-            log::error!("Could not get canonical mount directory path");
-        }
+        ));
 
-        log::debug!("Vault is not mounted");
-        false
+        std::thread::spawn(move || {
+            sender
+                .send_blocking(operation())
+                .expect("Channel needs to be open");
+        });
     }
 
-    pub fn is_mount_hidden(&self) -> bool {
-        log::trace!("is_mount_hidden");
+    pub fn open_folder(&self) {
+        log::trace!("open_folder");
 
-        let vault_config = self.config();
+        let output_res = std::process::Command::new("xdg-open")
+            .arg(self.mount_directory())
+            .output();
 
-        let components: Vec<_> = std::path::Path::new(&vault_config.mount_directory)
-            .components()
-            .map(|c| c.as_os_str().to_str())
-            .collect();
+        if let Err(e) = output_res {
+            log::error!("Failed to open folder: {}", e);
+        }
+    }
 
-        components
-            .iter()
-            .flatten()
-            .any(|c| c.starts_with(".") && !c.eq(&"..".to_string()) && c.len() > 1)
+    pub fn cleanup(&self) -> Result<(), BackendError> {
+        log::trace!("cleanup");
+        Backend::cleanup(&self.config())
     }
 
-    pub fn is_mounted_all(&self) -> bool {
-        log::trace!("is_mounted_all");
+    pub fn is_mounted(&self) -> bool {
+        log::trace!("is_mounted");
+
+        MountWatcher::instance().is_mounted(&self.config())
+    }
 
-        use proc_mounts::*;
+    pub fn is_disconnected(&self) -> bool {
+        log::trace!("is_disconnected");
 
-        let mount_list = MountList::new();
-        match mount_list {
-            Ok(mount_list) => {
-                MountList::get_mount_by_dest(&mount_list, &self.config().mount_directory).is_some()
-            }
+        if !self.is_mounted() {
+            return false;
+        }
+
+        // A crashed backend leaves its mount behind, but every access to it fails
+        match std::fs::metadata(self.config().mount_directory) {
+            Ok(_) => false,
             Err(e) => {
-                log::error!("Could not check if there exists any mounted vaults: {}", e);
-                false
+                log::debug!("Could not access mount directory: {}", e);
+                e.kind() == std::io::ErrorKind::NotConnected
             }
         }
     }
@@ -204,92 +380,66 @@ impl Vault {
         false
     }
 
-    pub fn delete_encrypted_data(&self) -> std::io::Result<()> {
+    /// Moves the encrypted data directory to the trash. Refuses while the vault
+    /// is in use.
+    pub async fn delete_encrypted_data(&self) -> Result<(), glib::Error> {
         log::trace!("delete_encrypted_data");
+
+        if self.is_busy() || self.is_mounted() {
+            return Err(glib::Error::new(
+                gio::IOErrorEnum::Busy,
+                "Cannot delete the encrypted data of a mounted vault",
+            ));
+        }
+
         let encrypted_data_directory = self.encrypted_data_directory();
-        let path = std::path::Path::new(&encrypted_data_directory);
-        log::debug!("Deleting encrypted data directory: {:?}", path);
-        std::fs::remove_dir_all(path)
+        log::debug!(
+            "Moving encrypted data directory to trash: {:?}",
+            encrypted_data_directory
+        );
+        gio::File::for_path(encrypted_data_directory)
+            .trash_future(glib::Priority::DEFAULT)
+            .await
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Locks the vaults in parallel. `callback` is called with the vaults that
+/// could not be locked once all of them finished.
+pub fn lock_all<F>(vaults: Vec<Vault>, callback: F)
+where
+    F: FnOnce(Vec<(Vault, BackendError)>) + 'static,
+{
+    log::trace!("lock_all({})", vaults.len());
+
+    let vaults: Vec<Vault> = vaults.into_iter().filter(|v| !v.is_busy()).collect();
+    if vaults.is_empty() {
+        callback(vec![]);
+        return;
+    }
 
-    #[test]
-    fn check_hidden_paths() {
-        let vault = Vault::new(
-            Uuid::nil(),
-            "".to_string(),
-            Backend::Gocryptfs,
-            "".to_string(),
-            "".to_string(),
-            false,
-        );
-        assert!(!vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: ".".to_string(),
-            session_lock: false,
-        });
-        assert!(!vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: "..".to_string(),
-            session_lock: false,
-        });
-        assert!(!vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: "./".to_string(),
-            session_lock: false,
-        });
-        assert!(!vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: "./Hidden".to_string(),
-            session_lock: false,
-        });
-        assert!(!vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: "Test/.Test".to_string(),
-            session_lock: false,
-        });
-        assert!(vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: "./Test/.Test".to_string(),
-            session_lock: false,
-        });
-        assert!(vault.is_mount_hidden());
-
-        vault.set_config(VaultConfig {
-            name: "".to_string(),
-            backend: Backend::Gocryptfs,
-            encrypted_data_directory: "".to_string(),
-            mount_directory: "../.Test".to_string(),
-            session_lock: false,
+    let remaining = Rc::new(Cell::new(vaults.len()));
+    let failures = Rc::new(RefCell::new(vec![]));
+    let callback = Rc::new(RefCell::new(Some(callback)));
+
+    for vault in vaults {
+        let remaining = remaining.clone();
+        let failures = failures.clone();
+        let callback = callback.clone();
+
+        vault.lock_in_background(move |vault, result| {
+            if let Err(e) = result {
+                log::error!("Error closing vault {}: {}", vault.name(), &e);
+                failures.borrow_mut().push((vault.clone(), e));
+            }
+
+            remaining.set(remaining.get() - 1);
+            if remaining.get() > 0 {
+                return;
+            }
+
+            if let Some(callback) = callback.take() {
+                callback(failures.take());
+            }
         });
-        assert!(vault.is_mount_hidden());
     }
 }
@@ -0,0 +1,742 @@
+// vault_backup.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    vault::VaultConfig,
+    vault_descriptor::{self, VaultDescriptor},
+    vault_relocation::{self, EntryKind, Phase, Progress, RelocationError},
+};
+use gettextrs::gettext;
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    io::{self, Read, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{MetadataExt, PermissionsExt},
+    },
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tar::{Archive, Builder, EntryType, Header};
+
+pub const MANIFEST_NAME: &str = "vaults-backup.toml";
+pub const MANIFEST_VERSION: i64 = 1;
+pub const FILE_EXTENSION: &str = "tar.gz";
+
+// Backups are tar archives with the encrypted data below this directory and
+// the manifest last, so both can be written in one pass
+const DATA_DIRECTORY: &str = "data";
+const BUFFER_SIZE: usize = 1024 * 1024;
+// The manifest is read into memory
+const MAX_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum BackupError {
+        Relocation(e: RelocationError) {
+            from()
+            display("{}", e)
+        }
+        Io(e: io::Error) {
+            from()
+            display("{}", e)
+        }
+        Invalid(message: String) {
+            display("{}", message)
+        }
+    }
+}
+
+impl BackupError {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, BackupError::Relocation(RelocationError::Cancelled))
+    }
+}
+
+/// Describes the backup and lists every file with its checksum, so a restored
+/// copy can be checked before it is used.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Manifest {
+    pub manifest_version: i64,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub vault: VaultDescriptor,
+    #[serde(default)]
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Checks that the archive is not written into the data it backs up.
+pub fn check_archive_path(source: &Path, archive: &Path) -> Result<(), String> {
+    if archive.starts_with(source) {
+        return Err(gettext(
+            "The backup must not be saved inside the encrypted data directory",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Writes the encrypted data in `source` and a manifest as tar archive. The
+/// vault has to be locked, so the data does not change meanwhile.
+pub fn create(
+    source: &Path,
+    config: &VaultConfig,
+    writer: &mut dyn Write,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Manifest, BackupError> {
+    log::trace!("create({:?})", source);
+
+    let entries = vault_relocation::scan(source)?;
+    let mut current = vault_relocation::new_progress(Phase::Archiving, &entries);
+    progress(current);
+
+    let mut builder = Builder::new(writer);
+    let mut files = vec![];
+    for entry in &entries {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(RelocationError::Cancelled.into());
+        }
+
+        let path = source.join(&entry.path);
+        let metadata =
+            fs::symlink_metadata(&path).map_err(|e| RelocationError::Io(path.clone(), e))?;
+        let name = Path::new(DATA_DIRECTORY).join(&entry.path);
+
+        let mut header = Header::new_gnu();
+        header.set_mode(metadata.permissions().mode() & 0o777);
+        header.set_mtime(metadata.mtime().max(0) as u64);
+        header.set_size(0);
+
+        match &entry.kind {
+            EntryKind::Directory => {
+                header.set_entry_type(EntryType::Directory);
+                builder.append_data(&mut header, &name, io::empty())?;
+            }
+            EntryKind::Symlink { target } => {
+                header.set_entry_type(EntryType::Symlink);
+                builder.append_link(&mut header, &name, target)?;
+            }
+            EntryKind::File { size } => {
+                header.set_entry_type(EntryType::Regular);
+                header.set_size(*size);
+
+                let file =
+                    fs::File::open(&path).map_err(|e| RelocationError::Io(path.clone(), e))?;
+                let mut reader = FileReader {
+                    path: &path,
+                    file,
+                    remaining: *size,
+                    checksum: glib::Checksum::new(glib::ChecksumType::Sha256).unwrap(),
+                    cancelled,
+                    read: &mut |bytes| {
+                        current.done_bytes += bytes;
+                        progress(current);
+                    },
+                };
+                builder
+                    .append_data(&mut header, &name, &mut reader)
+                    .map_err(unwrap_error)?;
+                let sha256 = reader.checksum.string().unwrap_or_default();
+
+                files.push(ManifestFile {
+                    path: entry.path.to_string_lossy().to_string(),
+                    size: *size,
+                    sha256,
+                });
+                current.done_files += 1;
+                progress(current);
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        manifest_version: MANIFEST_VERSION,
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        vault: VaultDescriptor::from_config(config),
+        files,
+    };
+    let contents = toml::to_string_pretty(&manifest).map_err(io::Error::other)?;
+
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created);
+    header.set_size(contents.len() as u64);
+    builder.append_data(&mut header, MANIFEST_NAME, contents.as_bytes())?;
+
+    builder.into_inner()?.flush()?;
+
+    Ok(manifest)
+}
+
+// Reads a file into the archive and computes its checksum on the way. The
+// size in the header was written before, so the file must not change.
+struct FileReader<'a> {
+    path: &'a Path,
+    file: fs::File,
+    remaining: u64,
+    checksum: glib::Checksum,
+    cancelled: &'a AtomicBool,
+    read: &'a mut dyn FnMut(u64),
+}
+
+impl Read for FileReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buffer.is_empty() {
+            return Ok(0);
+        }
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(io::Error::other(BackupError::from(
+                RelocationError::Cancelled,
+            )));
+        }
+
+        let len = self.remaining.min(buffer.len().min(BUFFER_SIZE) as u64) as usize;
+        let len = self.file.read(&mut buffer[..len]).map_err(|e| {
+            io::Error::other(BackupError::from(RelocationError::Io(
+                self.path.to_owned(),
+                e,
+            )))
+        })?;
+        if len == 0 {
+            return Err(io::Error::other(BackupError::Invalid(format!(
+                "{}: {}",
+                gettext("File changed during backup"),
+                self.path.display()
+            ))));
+        }
+
+        self.checksum.update(&buffer[..len]);
+        self.remaining -= len as u64;
+        (self.read)(len as u64);
+
+        Ok(len)
+    }
+}
+
+// Errors of the file reader come back wrapped by the archive builder
+fn unwrap_error(e: io::Error) -> BackupError {
+    match e.get_ref().map(|inner| inner.is::<BackupError>()) {
+        Some(true) => *e.into_inner().unwrap().downcast::<BackupError>().unwrap(),
+        _ => e.into(),
+    }
+}
+
+/// Unpacks a backup into `destination`, which must not exist yet, and checks
+/// every file against the manifest. Nothing is left behind if this fails.
+pub fn restore(
+    reader: &mut dyn Read,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Manifest, BackupError> {
+    log::trace!("restore({:?})", destination);
+
+    fs::create_dir(destination).map_err(|e| RelocationError::Io(destination.to_owned(), e))?;
+
+    let result = extract(reader, destination, cancelled, progress)
+        .and_then(|manifest| verify(&manifest, destination, cancelled, progress).map(|_| manifest));
+
+    if result.is_ok() {
+        return result;
+    }
+
+    if let Err(e) = fs::remove_dir_all(destination) {
+        log::error!("Could not remove incomplete restore: {}", e);
+    }
+    result
+}
+
+fn extract(
+    reader: &mut dyn Read,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Manifest, BackupError> {
+    // The total is not known before the whole archive was read
+    let mut current = vault_relocation::new_progress(Phase::Extracting, &[]);
+    progress(current);
+
+    let mut manifest = None;
+    let mut directories = vec![];
+    let mut symlinks = vec![];
+
+    let mut archive = Archive::new(reader);
+    for entry in archive.entries().map_err(damaged)? {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(RelocationError::Cancelled.into());
+        }
+
+        let mut entry = entry.map_err(damaged)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_pax_global_extensions() {
+            continue;
+        }
+        let mode = entry.header().mode().map_err(damaged)?;
+        let mtime = entry.header().mtime().map_err(damaged)?;
+        let size = entry.size();
+        let name = entry.path_bytes().into_owned();
+
+        if name == MANIFEST_NAME.as_bytes() {
+            if size > MAX_MANIFEST_SIZE {
+                return Err(BackupError::Invalid(gettext("The manifest is too large")));
+            }
+            let mut data = vec![0; size as usize];
+            read_exact(&mut entry, &mut data)?;
+            manifest = Some(parse_manifest(&String::from_utf8_lossy(&data))?);
+            continue;
+        }
+
+        let relative = data_path(&name)?;
+        let path = destination.join(&relative);
+        match entry_type {
+            EntryType::Directory => {
+                fs::create_dir_all(&path).map_err(|e| RelocationError::Io(path.clone(), e))?;
+                directories.push((path, mode, mtime));
+            }
+            // Links are created last, so nothing is written through them
+            EntryType::Symlink => {
+                let target = entry
+                    .link_name_bytes()
+                    .map(|target| target.into_owned())
+                    .unwrap_or_default();
+                symlinks.push((path, target));
+            }
+            EntryType::Regular => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| RelocationError::Io(parent.to_owned(), e))?;
+                }
+                extract_file(&mut entry, size, &path, cancelled, &mut |bytes| {
+                    current.done_bytes += bytes;
+                    progress(current);
+                })?;
+                set_attributes(&path, mode, mtime)?;
+                current.done_files += 1;
+                progress(current);
+            }
+            _ => {
+                return Err(BackupError::Invalid(format!(
+                    "{}: {}",
+                    gettext("Unsupported entry"),
+                    relative.display()
+                )));
+            }
+        }
+    }
+
+    // Reading to the end lets the decompressor check its checksum
+    io::copy(archive.into_inner(), &mut io::sink())?;
+
+    let manifest =
+        manifest.ok_or_else(|| BackupError::Invalid(gettext("The backup has no manifest")))?;
+
+    for (path, target) in symlinks {
+        std::os::unix::fs::symlink(OsStr::from_bytes(&target), &path)
+            .map_err(|e| RelocationError::Io(path.clone(), e))?;
+    }
+
+    // Directories get their permissions last, in case they are read-only
+    for (path, mode, mtime) in directories.iter().rev() {
+        set_attributes(path, *mode, *mtime)?;
+    }
+
+    Ok(manifest)
+}
+
+fn extract_file(
+    reader: &mut dyn Read,
+    size: u64,
+    path: &Path,
+    cancelled: &AtomicBool,
+    extracted: &mut dyn FnMut(u64),
+) -> Result<(), BackupError> {
+    let mut file = fs::File::create(path).map_err(|e| RelocationError::Io(path.to_owned(), e))?;
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut remaining = size;
+    while remaining > 0 {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(RelocationError::Cancelled.into());
+        }
+
+        let len = remaining.min(BUFFER_SIZE as u64) as usize;
+        read_exact(reader, &mut buffer[..len])?;
+        file.write_all(&buffer[..len])
+            .map_err(|e| RelocationError::Io(path.to_owned(), e))?;
+
+        remaining -= len as u64;
+        extracted(len as u64);
+    }
+
+    file.sync_all()
+        .map_err(|e| RelocationError::Io(path.to_owned(), e).into())
+}
+
+fn set_attributes(path: &Path, mode: u32, mtime: u64) -> Result<(), BackupError> {
+    let file = fs::File::open(path).map_err(|e| RelocationError::Io(path.to_owned(), e))?;
+    file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime))
+        .map_err(|e| RelocationError::Io(path.to_owned(), e))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+        .map_err(|e| RelocationError::Io(path.to_owned(), e).into())
+}
+
+// Compares the restored files with the manifest: the same files, the same
+// sizes and the same SHA-256 checksums
+fn verify(
+    manifest: &Manifest,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(), BackupError> {
+    log::trace!("verify({:?})", destination);
+
+    let entries = vault_relocation::scan(destination)?;
+    let mut restored: HashMap<String, u64> = entries
+        .iter()
+        .filter_map(|entry| match entry.kind {
+            EntryKind::File { size } => Some((entry.path.to_string_lossy().to_string(), size)),
+            _ => None,
+        })
+        .collect();
+
+    let mut current = vault_relocation::new_progress(Phase::Verifying, &entries);
+    progress(current);
+
+    let mismatch = |message: String, path: &str| -> BackupError {
+        RelocationError::Mismatch(format!("{}: {}", message, path)).into()
+    };
+
+    for file in &manifest.files {
+        match restored.remove(&file.path) {
+            Some(size) if size == file.size => {}
+            Some(_) => return Err(mismatch(gettext("Size differs"), &file.path)),
+            None => return Err(mismatch(gettext("Missing"), &file.path)),
+        }
+
+        if vault_relocation::checksum(&destination.join(&file.path), cancelled)? != file.sha256 {
+            return Err(mismatch(gettext("Checksums differ"), &file.path));
+        }
+
+        current.done_bytes += file.size;
+        current.done_files += 1;
+        progress(current);
+    }
+
+    if let Some(path) = restored.keys().next() {
+        return Err(mismatch(gettext("Not in the manifest"), path));
+    }
+
+    Ok(())
+}
+
+fn parse_manifest(content: &str) -> Result<Manifest, BackupError> {
+    let invalid =
+        |e: String| BackupError::Invalid(format!("{}: {}", gettext("The manifest is invalid"), e));
+
+    let manifest: Manifest = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+
+    if manifest.manifest_version > MANIFEST_VERSION {
+        return Err(BackupError::Invalid(gettext(
+            "The backup was made with a newer version of Vaults",
+        )));
+    }
+    vault_descriptor::check(&manifest.vault).map_err(invalid)?;
+
+    Ok(manifest)
+}
+
+// Entries outside of the data directory would be written anywhere
+fn data_path(name: &[u8]) -> Result<PathBuf, BackupError> {
+    let path = Path::new(OsStr::from_bytes(name));
+    let unexpected = || {
+        BackupError::Invalid(format!(
+            "{}: {}",
+            gettext("Unexpected entry"),
+            path.display()
+        ))
+    };
+
+    let relative = path
+        .strip_prefix(DATA_DIRECTORY)
+        .map_err(|_| unexpected())?;
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(unexpected());
+    }
+
+    Ok(relative.to_owned())
+}
+
+fn damaged(e: io::Error) -> BackupError {
+    BackupError::Invalid(format!("{}: {}", gettext("The archive is damaged"), e))
+}
+
+fn read_exact(reader: &mut dyn Read, buffer: &mut [u8]) -> Result<(), BackupError> {
+    reader.read_exact(buffer).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            BackupError::Invalid(gettext("The archive is incomplete"))
+        } else {
+            e.into()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+
+    fn config(encrypted_data_directory: &Path) -> VaultConfig {
+        VaultConfig {
+            name: "Backup".to_string(),
+            backend: Backend::Gocryptfs,
+            encrypted_data_directory: encrypted_data_directory.to_string_lossy().to_string(),
+            mount_directory: "/home/alice/Vaults/Backup".to_string(),
+            session_lock: false,
+            use_custom_binary: false,
+            custom_binary_path: String::new(),
+            tags: vec![],
+            favorite: false,
+        }
+    }
+
+    fn test_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("vaults-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    // Written by hand, as the builder refuses unsafe names
+    fn entry(archive: &mut Vec<u8>, name: &[u8], entry_type: EntryType, link: &[u8], data: &[u8]) {
+        let mut header = Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link);
+        header.set_entry_type(entry_type);
+        header.set_mode(0o755);
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(512), 0);
+    }
+
+    #[test]
+    fn backup_and_restore() {
+        let directory = test_directory("backup");
+        let source = directory.join("source");
+        let long_name = "n".repeat(150);
+        fs::create_dir_all(source.join("d").join(&long_name)).unwrap();
+        fs::write(source.join("gocryptfs.conf"), "{}").unwrap();
+        fs::write(source.join("d").join("a"), vec![7; BUFFER_SIZE + 10]).unwrap();
+        fs::write(source.join("d").join(&long_name).join("b"), "").unwrap();
+        std::os::unix::fs::symlink(format!("d/{}", long_name), source.join("link")).unwrap();
+        fs::set_permissions(
+            source.join("d").join("a"),
+            fs::Permissions::from_mode(0o600),
+        )
+        .unwrap();
+
+        assert!(check_archive_path(&source, &source.join("backup.tar.gz")).is_err());
+        assert!(check_archive_path(&source, &directory.join("backup.tar.gz")).is_ok());
+
+        let cancelled = AtomicBool::new(false);
+        let mut archive = vec![];
+        let manifest = create(
+            &source,
+            &config(&source),
+            &mut archive,
+            &cancelled,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(manifest.vault.name, "Backup");
+        assert_eq!(archive.len() % 512, 0);
+
+        let destination = directory.join("restored");
+        let mut last = None;
+        let restored = restore(
+            &mut archive.as_slice(),
+            &destination,
+            &cancelled,
+            &mut |p| last = Some(p),
+        )
+        .unwrap();
+        assert_eq!(restored, manifest);
+        assert_eq!(last.unwrap().done_files, 3);
+        vault_relocation::verify(&source, &destination, &cancelled, &mut |_| {}).unwrap();
+        let mode = fs::metadata(destination.join("d").join("a"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // The destination must be new
+        assert!(
+            restore(
+                &mut archive.as_slice(),
+                &destination,
+                &cancelled,
+                &mut |_| {}
+            )
+            .is_err()
+        );
+
+        // Damaged and truncated archives leave nothing behind
+        let damaged_destination = directory.join("damaged");
+        let mut damaged = archive.clone();
+        let position = damaged.windows(3).position(|w| w == [7, 7, 7]).unwrap();
+        damaged[position] = 8;
+        let result = restore(
+            &mut damaged.as_slice(),
+            &damaged_destination,
+            &cancelled,
+            &mut |_| {},
+        );
+        assert!(matches!(
+            result,
+            Err(BackupError::Relocation(RelocationError::Mismatch(_)))
+        ));
+        assert!(!damaged_destination.exists());
+
+        // In the middle of file data, of a header and between two entries
+        for len in [archive.len() / 2, 512 + 100, 1024] {
+            let result = restore(
+                &mut &archive[..len],
+                &damaged_destination,
+                &cancelled,
+                &mut |_| {},
+            );
+            assert!(matches!(result, Err(BackupError::Invalid(_))), "{}", len);
+            assert!(!damaged_destination.exists());
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unsafe_paths_are_rejected() {
+        assert!(data_path(b"data/d/a").is_ok());
+        assert!(data_path(b"data/").unwrap().as_os_str().is_empty());
+        assert!(data_path(b"data/../etc/passwd").is_err());
+        assert!(data_path(b"/data/a").is_err());
+        assert!(data_path(b"other/a").is_err());
+    }
+
+    #[test]
+    fn malicious_archives_are_rejected() {
+        let directory = test_directory("malicious");
+        let destination = directory.join("restored");
+        let cancelled = AtomicBool::new(false);
+        let check = |archive: &[u8]| {
+            let result = restore(&mut &archive[..], &destination, &cancelled, &mut |_| {});
+            assert!(!destination.exists());
+            result
+        };
+
+        for name in [
+            &b"data/../escaped"[..],
+            b"/tmp/escaped",
+            b"data/d/../../escaped",
+            b"escaped",
+        ] {
+            let mut archive = vec![];
+            entry(&mut archive, name, EntryType::Regular, b"", b"x");
+            assert!(matches!(check(&archive), Err(BackupError::Invalid(_))));
+        }
+
+        // Nothing is written through a link, even if it comes first
+        let mut archive = vec![];
+        entry(&mut archive, b"data/d", EntryType::Symlink, b"/tmp", b"");
+        entry(
+            &mut archive,
+            b"data/d/escaped",
+            EntryType::Regular,
+            b"",
+            b"x",
+        );
+        assert!(check(&archive).is_err());
+        assert!(!std::env::temp_dir().join("escaped").exists());
+
+        // Devices and other special files
+        let mut archive = vec![];
+        entry(&mut archive, b"data/null", EntryType::Char, b"", b"");
+        assert!(matches!(check(&archive), Err(BackupError::Invalid(_))));
+
+        // A size larger than the data that follows
+        let mut archive = vec![];
+        entry(&mut archive, b"data/a", EntryType::Regular, b"", b"x");
+        let mut header = Header::from_byte_slice(&archive[..512]).clone();
+        header.set_size(1024 * 1024);
+        header.set_cksum();
+        archive[..512].copy_from_slice(header.as_bytes());
+        assert!(matches!(check(&archive), Err(BackupError::Invalid(_))));
+
+        // A header that does not match its checksum
+        let mut archive = vec![];
+        entry(&mut archive, b"data/a", EntryType::Regular, b"", b"x");
+        archive[0] = b'b';
+        assert!(matches!(check(&archive), Err(BackupError::Invalid(_))));
+
+        // A manifest that would not fit into memory
+        let mut archive = vec![];
+        entry(
+            &mut archive,
+            MANIFEST_NAME.as_bytes(),
+            EntryType::Regular,
+            b"",
+            b"",
+        );
+        let mut header = Header::from_byte_slice(&archive[..512]).clone();
+        header.set_size(MAX_MANIFEST_SIZE + 1);
+        header.set_cksum();
+        archive[..512].copy_from_slice(header.as_bytes());
+        assert!(matches!(check(&archive), Err(BackupError::Invalid(_))));
+
+        // Only data and no manifest
+        let mut archive = vec![];
+        entry(&mut archive, b"data/a", EntryType::Regular, b"", b"x");
+        archive.extend_from_slice(&[0; 1024]);
+        assert!(matches!(check(&archive), Err(BackupError::Invalid(_))));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
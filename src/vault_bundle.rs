@@ -0,0 +1,300 @@
+// vault_bundle.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    backend::{self, Backend},
+    vault::VaultConfig,
+};
+use gettextrs::gettext;
+use serde::{Deserialize, Serialize, de::Error as _};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+pub const BUNDLE_VERSION: i64 = 1;
+
+// Paths below the home directory are stored relative to it, so a bundle can be
+// imported on a machine with a different user name
+#[derive(Debug, Deserialize, Serialize)]
+struct Bundle {
+    bundle_version: i64,
+    #[serde(default)]
+    vaults: HashMap<Uuid, VaultConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportIssue {
+    UuidExists,
+    NameExists,
+    EncryptedDataDirectoryNotFound,
+    NoBackendFound,
+    BackendMismatch(Backend),
+    /// The name of a backend Vaults does not support, empty if unknown
+    UnsupportedBackend(String),
+}
+
+impl ImportIssue {
+    pub fn description(&self) -> String {
+        match self {
+            ImportIssue::UuidExists => gettext("Vault already exists."),
+            ImportIssue::NameExists => gettext("Name already exists."),
+            ImportIssue::EncryptedDataDirectoryNotFound => {
+                gettext("Encrypted data directory does not exist.")
+            }
+            ImportIssue::NoBackendFound => gettext("No configuration file found."),
+            ImportIssue::BackendMismatch(backend) => gettext("Configuration file belongs to {}")
+                .replace("{}", &backend::get_ui_string_from_backend(backend)),
+            ImportIssue::UnsupportedBackend(backend) if backend.is_empty() => {
+                gettext("Backend could not be detected.")
+            }
+            ImportIssue::UnsupportedBackend(backend) => {
+                gettext("Unsupported backend: {}").replace("{}", backend)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleEntry {
+    pub uuid: Uuid,
+    pub config: VaultConfig,
+    pub issues: Vec<ImportIssue>,
+}
+
+pub fn export(
+    vaults: &HashMap<Uuid, VaultConfig>,
+    home: &Path,
+) -> Result<String, toml::ser::Error> {
+    log::trace!("export({:?}, {:?})", vaults, home);
+
+    let vaults = vaults
+        .iter()
+        .map(|(uuid, config)| {
+            let mut config = without_custom_binary(config.clone());
+            config.encrypted_data_directory =
+                relative_to_home(&config.encrypted_data_directory, home);
+            config.mount_directory = relative_to_home(&config.mount_directory, home);
+            (*uuid, config)
+        })
+        .collect();
+
+    toml::to_string_pretty(&Bundle {
+        bundle_version: BUNDLE_VERSION,
+        vaults,
+    })
+}
+
+pub fn parse(content: &str, home: &Path) -> Result<HashMap<Uuid, VaultConfig>, toml::de::Error> {
+    log::trace!("parse({:?})", home);
+
+    let bundle: Bundle = toml::from_str(content)?;
+    if bundle.bundle_version > BUNDLE_VERSION {
+        return Err(toml::de::Error::custom(format!(
+            "Bundle version {} is newer than the supported version {}",
+            bundle.bundle_version, BUNDLE_VERSION
+        )));
+    }
+
+    Ok(bundle
+        .vaults
+        .into_iter()
+        .map(|(uuid, config)| {
+            let mut config = without_custom_binary(config);
+            config.encrypted_data_directory = resolve(&config.encrypted_data_directory, home);
+            config.mount_directory = resolve(&config.mount_directory, home);
+            (uuid, config)
+        })
+        .collect())
+}
+
+/// Reads a bundle and checks each of its vaults against the existing ones.
+pub fn read(
+    path: &Path,
+    home: &Path,
+    existing: &HashMap<Uuid, VaultConfig>,
+) -> Result<Vec<BundleEntry>, String> {
+    log::trace!("read({:?}, {:?})", path, home);
+
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let vaults = parse(&content, home).map_err(|e| e.to_string())?;
+
+    Ok(vaults
+        .into_iter()
+        .map(|(uuid, config)| BundleEntry {
+            issues: check(&uuid, &config, existing),
+            uuid,
+            config,
+        })
+        .collect())
+}
+
+/// Checks whether a vault from a bundle can be added next to the existing ones.
+pub fn check(
+    uuid: &Uuid,
+    config: &VaultConfig,
+    existing: &HashMap<Uuid, VaultConfig>,
+) -> Vec<ImportIssue> {
+    log::trace!("check({:?}, {:?})", uuid, config);
+
+    let mut issues = vec![];
+
+    if existing.contains_key(uuid) {
+        issues.push(ImportIssue::UuidExists);
+    }
+
+    if existing.values().any(|v| v.name == config.name) {
+        issues.push(ImportIssue::NameExists);
+    }
+
+    let encrypted_data_directory = Path::new(&config.encrypted_data_directory);
+    if !encrypted_data_directory.is_dir() {
+        issues.push(ImportIssue::EncryptedDataDirectoryNotFound);
+    } else {
+        match backend::detect_backend(encrypted_data_directory) {
+            Some(backend) if backend != config.backend => {
+                issues.push(ImportIssue::BackendMismatch(backend));
+            }
+            Some(_) => {}
+            None => issues.push(ImportIssue::NoBackendFound),
+        }
+    }
+
+    issues
+}
+
+// The password is passed to the binary, so a bundle must not choose it
+fn without_custom_binary(config: VaultConfig) -> VaultConfig {
+    VaultConfig {
+        use_custom_binary: false,
+        custom_binary_path: String::new(),
+        ..config
+    }
+}
+
+fn relative_to_home(path: &str, home: &Path) -> String {
+    match Path::new(path).strip_prefix(home) {
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+fn resolve(path: &str, home: &Path) -> String {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        return path.to_string_lossy().to_string();
+    }
+
+    home.join(path).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, encrypted_data_directory: &str) -> VaultConfig {
+        VaultConfig {
+            name: name.to_string(),
+            backend: Backend::Gocryptfs,
+            encrypted_data_directory: encrypted_data_directory.to_string(),
+            mount_directory: "/home/alice/Vaults/Private".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn paths_are_relative_to_home() {
+        let uuid = Uuid::new_v4();
+        let mut vaults = HashMap::new();
+        vaults.insert(uuid, config("Private", "/media/usb/.Private"));
+
+        let content = export(&vaults, Path::new("/home/alice")).unwrap();
+        assert!(content.contains("\"Vaults/Private\""));
+        assert!(content.contains("\"/media/usb/.Private\""));
+
+        let imported = parse(&content, Path::new("/home/bob")).unwrap();
+        assert_eq!(imported[&uuid].mount_directory, "/home/bob/Vaults/Private");
+        assert_eq!(
+            imported[&uuid].encrypted_data_directory,
+            "/media/usb/.Private"
+        );
+    }
+
+    #[test]
+    fn custom_binaries_are_not_carried() {
+        let uuid = Uuid::new_v4();
+        let mut vaults = HashMap::new();
+        vaults.insert(
+            uuid,
+            VaultConfig {
+                use_custom_binary: true,
+                custom_binary_path: "/opt/gocryptfs/bin/gocryptfs".to_string(),
+                ..config("Private", "/media/usb/.Private")
+            },
+        );
+
+        let content = export(&vaults, Path::new("/home/alice")).unwrap();
+        assert!(!content.contains("/opt/gocryptfs"));
+
+        let content = content
+            .replace("use_custom_binary = false", "use_custom_binary = true")
+            .replace(
+                "custom_binary_path = \"\"",
+                "custom_binary_path = \"/tmp/steal-password\"",
+            );
+        assert!(content.contains("/tmp/steal-password"));
+        let imported = parse(&content, Path::new("/home/alice")).unwrap();
+        assert!(!imported[&uuid].use_custom_binary);
+        assert!(imported[&uuid].custom_binary_path.is_empty());
+    }
+
+    #[test]
+    fn newer_bundle_version_is_rejected() {
+        assert!(parse("bundle_version = 2", Path::new("/home/alice")).is_err());
+    }
+
+    #[test]
+    fn conflicts_are_reported() {
+        let directory = std::env::temp_dir().join(format!("vaults-bundle-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("cryfs.config"), "").unwrap();
+
+        let uuid = Uuid::new_v4();
+        let config = config("Private", directory.to_str().unwrap());
+        let mut existing = HashMap::new();
+
+        assert_eq!(
+            check(&uuid, &config, &existing),
+            vec![ImportIssue::BackendMismatch(Backend::Cryfs)]
+        );
+
+        existing.insert(uuid, config.clone());
+        let issues = check(&uuid, &config, &existing);
+        assert!(issues.contains(&ImportIssue::UuidExists));
+        assert!(issues.contains(&ImportIssue::NameExists));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(
+            check(&Uuid::new_v4(), &config, &HashMap::new()),
+            vec![ImportIssue::EncryptedDataDirectoryNotFound]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+// vault_conversion.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{backend::Backend, config_file, vault::VaultConfig};
+use gtk::glib::user_data_dir;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+// Both vaults are mounted next to the usual mount directory while the files
+// are copied, so the vault itself stays locked meanwhile
+const TEMPORARY_MOUNT_SUFFIX: &str = "-converting";
+const SOURCE_MOUNT_SUFFIX: &str = "-converting-source";
+
+/// The encrypted data a vault used before it was converted to another
+/// backend. It is kept until the user deletes it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PendingConversion {
+    pub encrypted_data_directory: String,
+    pub backend: Backend,
+}
+
+/// The configuration of the converted vault. It keeps everything but the
+/// backend specific settings.
+pub fn converted_config(
+    config: &VaultConfig,
+    backend: Backend,
+    encrypted_data_directory: &str,
+) -> VaultConfig {
+    VaultConfig {
+        backend,
+        encrypted_data_directory: encrypted_data_directory.to_string(),
+        use_custom_binary: false,
+        custom_binary_path: String::new(),
+        ..config.clone()
+    }
+}
+
+/// Next to the old encrypted data, named after the new backend.
+pub fn default_destination(encrypted_data_directory: &Path, backend: Backend) -> PathBuf {
+    with_suffix(
+        encrypted_data_directory,
+        &format!("-{}", backend.to_string().to_lowercase()),
+    )
+}
+
+pub fn temporary_mount_directory(mount_directory: &Path) -> PathBuf {
+    with_suffix(mount_directory, TEMPORARY_MOUNT_SUFFIX)
+}
+
+pub fn source_mount_directory(mount_directory: &Path) -> PathBuf {
+    with_suffix(mount_directory, SOURCE_MOUNT_SUFFIX)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.file_name().unwrap_or_default().to_owned();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+pub fn pending_path() -> PathBuf {
+    user_data_dir().join("vaults").join("conversions.toml")
+}
+
+fn read_pending_all() -> HashMap<Uuid, PendingConversion> {
+    config_file::read_map(&pending_path()).unwrap_or_else(|e| {
+        log::warn!("Could not read conversions file: {}", e);
+        HashMap::new()
+    })
+}
+
+/// The old encrypted data of the vault, if it was converted and the data was
+/// not deleted yet.
+pub fn pending(uuid: &Uuid) -> Option<PendingConversion> {
+    read_pending_all().remove(uuid)
+}
+
+pub fn set_pending(uuid: Uuid, pending: PendingConversion) -> io::Result<()> {
+    log::trace!("set_pending({}, {:?})", uuid, pending);
+
+    config_file::update_map(
+        &pending_path(),
+        |all: &mut HashMap<Uuid, PendingConversion>| {
+            all.insert(uuid, pending);
+        },
+    )
+}
+
+pub fn clear_pending(uuid: &Uuid) {
+    log::trace!("clear_pending({})", uuid);
+
+    if let Err(e) = config_file::remove_from_map::<PendingConversion>(&pending_path(), uuid) {
+        log::error!("Could not update conversions file: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converted_config_keeps_vault_settings() {
+        let config = VaultConfig {
+            name: "Private".to_string(),
+            backend: Backend::Cryfs,
+            encrypted_data_directory: "/home/alice/Vaults/Private".to_string(),
+            mount_directory: "/home/alice/Vaults/Private Files".to_string(),
+            session_lock: true,
+            use_custom_binary: true,
+            custom_binary_path: "/opt/cryfs/bin/cryfs".to_string(),
+            tags: vec!["Work".to_string()],
+            favorite: true,
+        };
+
+        let destination = default_destination(
+            Path::new(&config.encrypted_data_directory),
+            Backend::Gocryptfs,
+        );
+        assert_eq!(
+            destination,
+            PathBuf::from("/home/alice/Vaults/Private-gocryptfs")
+        );
+
+        let converted =
+            converted_config(&config, Backend::Gocryptfs, &destination.to_string_lossy());
+        assert_eq!(converted.backend, Backend::Gocryptfs);
+        assert_eq!(
+            converted.encrypted_data_directory,
+            "/home/alice/Vaults/Private-gocryptfs"
+        );
+        assert_eq!(converted.mount_directory, config.mount_directory);
+        assert_eq!(converted.tags, config.tags);
+        assert!(converted.session_lock && converted.favorite);
+        assert!(!converted.use_custom_binary);
+        assert!(converted.custom_binary_path.is_empty());
+
+        assert_eq!(
+            temporary_mount_directory(Path::new(&config.mount_directory)),
+            PathBuf::from("/home/alice/Vaults/Private Files-converting")
+        );
+        assert_eq!(
+            source_mount_directory(Path::new(&config.mount_directory)),
+            PathBuf::from("/home/alice/Vaults/Private Files-converting-source")
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// vault_descriptor.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{backend::Backend, config_file, vault::VaultConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "vaults-descriptor.toml";
+pub const DESCRIPTOR_VERSION: i64 = 1;
+
+/// Describes a vault to everyone who can read its encrypted data directory,
+/// e.g. through a synced folder. It is stored in plain text next to the
+/// backend's config file, so it must never hold passwords, key files or
+/// anything specific to one machine.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VaultDescriptor {
+    pub descriptor_version: i64,
+    pub name: String,
+    /// Name of the mount directory, placed in the user's default mount
+    /// directory on import
+    pub mount_directory_name: String,
+    pub backend: Backend,
+    #[serde(default)]
+    pub session_lock: bool,
+}
+
+impl VaultDescriptor {
+    pub fn from_config(config: &VaultConfig) -> Self {
+        let mount_directory_name = Path::new(&config.mount_directory)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| config.name.clone());
+
+        Self {
+            descriptor_version: DESCRIPTOR_VERSION,
+            name: config.name.clone(),
+            mount_directory_name,
+            backend: config.backend,
+            session_lock: config.session_lock,
+        }
+    }
+}
+
+pub fn path(encrypted_data_directory: &Path) -> PathBuf {
+    encrypted_data_directory.join(FILE_NAME)
+}
+
+pub fn exists(encrypted_data_directory: &Path) -> bool {
+    path(encrypted_data_directory).is_file()
+}
+
+pub fn write(config: &VaultConfig) -> std::io::Result<()> {
+    log::trace!("write({:?})", config);
+
+    let descriptor = VaultDescriptor::from_config(config);
+    let contents = toml::to_string_pretty(&descriptor).map_err(std::io::Error::other)?;
+
+    config_file::write_atomic(
+        &path(Path::new(&config.encrypted_data_directory)),
+        &contents,
+    )
+}
+
+/// Returns `None` if the vault has no descriptor.
+pub fn read(encrypted_data_directory: &Path) -> Option<Result<VaultDescriptor, String>> {
+    log::trace!("read({:?})", encrypted_data_directory);
+
+    let content = std::fs::read_to_string(path(encrypted_data_directory)).ok()?;
+
+    Some(parse(&content))
+}
+
+pub fn remove(encrypted_data_directory: &Path) -> std::io::Result<()> {
+    log::trace!("remove({:?})", encrypted_data_directory);
+
+    match std::fs::remove_file(path(encrypted_data_directory)) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn parse(content: &str) -> Result<VaultDescriptor, String> {
+    let descriptor: VaultDescriptor = toml::from_str(content).map_err(|e| e.to_string())?;
+    check(&descriptor)?;
+
+    Ok(descriptor)
+}
+
+/// Rejects descriptors of newer versions and mount directory names that would
+/// leave the default mount directory.
+pub fn check(descriptor: &VaultDescriptor) -> Result<(), String> {
+    if descriptor.descriptor_version > DESCRIPTOR_VERSION {
+        return Err(format!(
+            "Descriptor version {} is newer than the supported version {}",
+            descriptor.descriptor_version, DESCRIPTOR_VERSION
+        ));
+    }
+
+    let name = &descriptor.mount_directory_name;
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(format!(
+            "Invalid mount directory name: {}",
+            descriptor.mount_directory_name
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptor_holds_no_local_settings() {
+        let config = VaultConfig {
+            name: "Team".to_string(),
+            backend: Backend::Gocryptfs,
+            encrypted_data_directory: "/home/alice/Nextcloud/Team".to_string(),
+            mount_directory: "/home/alice/Vaults/Team Files".to_string(),
+            session_lock: true,
+            use_custom_binary: true,
+            custom_binary_path: "/opt/gocryptfs/bin/gocryptfs".to_string(),
+            tags: vec!["Team".to_string()],
+            favorite: true,
+        };
+
+        let contents = toml::to_string_pretty(&VaultDescriptor::from_config(&config)).unwrap();
+        assert!(!contents.contains("/home/alice"));
+        assert!(!contents.contains("/opt/gocryptfs"));
+
+        let descriptor = parse(&contents).unwrap();
+        assert_eq!(descriptor.name, "Team");
+        assert_eq!(descriptor.mount_directory_name, "Team Files");
+        assert_eq!(descriptor.backend, Backend::Gocryptfs);
+        assert!(descriptor.session_lock);
+    }
+
+    #[test]
+    fn invalid_descriptors_are_rejected() {
+        let descriptor = r#"
+descriptor_version = 1
+name = "Team"
+mount_directory_name = "../../etc"
+backend = "Cryfs"
+"#;
+        assert!(parse(descriptor).is_err());
+        assert!(
+            parse(
+                &descriptor
+                    .replace("../../etc", "Team")
+                    .replace("= 1", "= 2")
+            )
+            .is_err()
+        );
+        assert!(parse(&descriptor.replace("../../etc", "Team")).is_ok());
+    }
+}
@@ -0,0 +1,522 @@
+// vault_relocation.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::config_file;
+use gettextrs::gettext;
+use gtk::glib::{self, user_data_dir};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use uuid::Uuid;
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+const PARTIAL_SUFFIX: &str = ".vaults-partial";
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum RelocationError {
+        Cancelled {
+            display("{}", gettext("Cancelled"))
+        }
+        Io(path: PathBuf, e: io::Error) {
+            display("{}: {}", path.display(), e)
+        }
+        Mismatch(message: String) {
+            display("{}", message)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Copying,
+    Verifying,
+    Archiving,
+    Extracting,
+}
+
+impl Phase {
+    pub fn label(&self) -> String {
+        match self {
+            Phase::Copying => gettext("Copying"),
+            Phase::Verifying => gettext("Verifying"),
+            Phase::Archiving => gettext("Archiving"),
+            Phase::Extracting => gettext("Extracting"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub phase: Phase,
+    pub done_bytes: u64,
+    pub total_bytes: u64,
+    pub done_files: u64,
+    pub total_files: u64,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        self.done_bytes as f64 / self.total_bytes as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryKind {
+    Directory,
+    File { size: u64 },
+    Symlink { target: PathBuf },
+}
+
+/// A file below the encrypted data directory, relative to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+}
+
+/// A move that was started, but not finished. Kept so that it can be resumed
+/// after being cancelled or interrupted.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PendingRelocation {
+    pub source: String,
+    pub destination: String,
+}
+
+/// Lists everything below `root` sorted by path, without following symlinks.
+pub fn scan(root: &Path) -> Result<Vec<Entry>, RelocationError> {
+    log::trace!("scan({:?})", root);
+
+    let mut entries = vec![];
+    scan_directory(root, Path::new(""), &mut entries)?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(entries)
+}
+
+fn scan_directory(
+    root: &Path,
+    relative: &Path,
+    entries: &mut Vec<Entry>,
+) -> Result<(), RelocationError> {
+    let directory = root.join(relative);
+    let read_dir =
+        fs::read_dir(&directory).map_err(|e| RelocationError::Io(directory.clone(), e))?;
+
+    for dir_entry in read_dir {
+        let dir_entry = dir_entry.map_err(|e| RelocationError::Io(directory.clone(), e))?;
+        let path = relative.join(dir_entry.file_name());
+        let file_type = dir_entry
+            .file_type()
+            .map_err(|e| RelocationError::Io(dir_entry.path(), e))?;
+
+        if file_type.is_dir() {
+            entries.push(Entry {
+                path: path.clone(),
+                kind: EntryKind::Directory,
+            });
+            scan_directory(root, &path, entries)?;
+        } else if file_type.is_symlink() {
+            let target = fs::read_link(dir_entry.path())
+                .map_err(|e| RelocationError::Io(dir_entry.path(), e))?;
+            entries.push(Entry {
+                path,
+                kind: EntryKind::Symlink { target },
+            });
+        } else {
+            let metadata = dir_entry
+                .metadata()
+                .map_err(|e| RelocationError::Io(dir_entry.path(), e))?;
+            entries.push(Entry {
+                path,
+                kind: EntryKind::File {
+                    size: metadata.len(),
+                },
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that data can be moved from `source` to `destination`. The
+/// destination must not exist yet or be empty, unless a move to it is resumed.
+pub fn check_destination(source: &Path, destination: &Path, resume: bool) -> Result<(), String> {
+    if !destination.is_absolute() {
+        return Err(gettext("The new location must be an absolute path"));
+    }
+    if destination.starts_with(source) || source.starts_with(destination) {
+        return Err(gettext(
+            "The new location must not contain or be inside the old one",
+        ));
+    }
+
+    if !resume {
+        if let Ok(mut read_dir) = fs::read_dir(destination) {
+            if read_dir.next().is_some() {
+                return Err(gettext("The new location is not empty"));
+            }
+        } else if destination.exists() {
+            return Err(gettext("The new location is not a folder"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies everything from `source` to `destination`, keeping permissions and
+/// modification times. Files that already have the size and modification
+/// time of their source are skipped, so an interrupted copy can continue.
+pub fn copy(
+    source: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(), RelocationError> {
+    log::trace!("copy({:?}, {:?})", source, destination);
+
+    let entries = scan(source)?;
+    let mut current = new_progress(Phase::Copying, &entries);
+    progress(current);
+
+    fs::create_dir_all(destination).map_err(|e| RelocationError::Io(destination.to_owned(), e))?;
+
+    for entry in &entries {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(RelocationError::Cancelled);
+        }
+
+        let from = source.join(&entry.path);
+        let to = destination.join(&entry.path);
+        match &entry.kind {
+            EntryKind::Directory => {
+                fs::create_dir_all(&to).map_err(|e| RelocationError::Io(to.clone(), e))?;
+            }
+            EntryKind::Symlink { target } => {
+                if fs::symlink_metadata(&to).is_err() {
+                    std::os::unix::fs::symlink(target, &to)
+                        .map_err(|e| RelocationError::Io(to.clone(), e))?;
+                }
+            }
+            EntryKind::File { size } => {
+                if !is_copied(&from, &to) {
+                    copy_file(&from, &to, cancelled, &mut |bytes| {
+                        current.done_bytes += bytes;
+                        progress(current);
+                    })?;
+                } else {
+                    current.done_bytes += size;
+                }
+                current.done_files += 1;
+                progress(current);
+            }
+        }
+    }
+
+    // Directories get their permissions last, in case they are read-only
+    for entry in entries.iter().rev() {
+        if entry.kind == EntryKind::Directory {
+            copy_attributes(&source.join(&entry.path), &destination.join(&entry.path))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_copied(from: &Path, to: &Path) -> bool {
+    match (fs::metadata(from), fs::metadata(to)) {
+        (Ok(from), Ok(to)) => from.len() == to.len() && from.modified().ok() == to.modified().ok(),
+        _ => false,
+    }
+}
+
+// Copies to a temporary file first, so a file only has its final name once
+// it is complete
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    cancelled: &AtomicBool,
+    copied: &mut dyn FnMut(u64),
+) -> Result<(), RelocationError> {
+    let mut partial_name = to.file_name().unwrap_or_default().to_owned();
+    partial_name.push(PARTIAL_SUFFIX);
+    let partial = to.with_file_name(partial_name);
+
+    let mut reader = fs::File::open(from).map_err(|e| RelocationError::Io(from.to_owned(), e))?;
+    let mut writer =
+        fs::File::create(&partial).map_err(|e| RelocationError::Io(partial.clone(), e))?;
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            drop(writer);
+            let _ = fs::remove_file(&partial);
+            return Err(RelocationError::Cancelled);
+        }
+
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| RelocationError::Io(from.to_owned(), e))?;
+        if read == 0 {
+            break;
+        }
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| RelocationError::Io(partial.clone(), e))?;
+        copied(read as u64);
+    }
+
+    writer
+        .sync_all()
+        .map_err(|e| RelocationError::Io(partial.clone(), e))?;
+    drop(writer);
+
+    copy_attributes(from, &partial)?;
+    fs::rename(&partial, to).map_err(|e| RelocationError::Io(to.to_owned(), e))
+}
+
+fn copy_attributes(from: &Path, to: &Path) -> Result<(), RelocationError> {
+    let metadata = fs::metadata(from).map_err(|e| RelocationError::Io(from.to_owned(), e))?;
+
+    if let Ok(modified) = metadata.modified() {
+        let file = fs::File::open(to).map_err(|e| RelocationError::Io(to.to_owned(), e))?;
+        file.set_modified(modified)
+            .map_err(|e| RelocationError::Io(to.to_owned(), e))?;
+    }
+    fs::set_permissions(to, metadata.permissions())
+        .map_err(|e| RelocationError::Io(to.to_owned(), e))
+}
+
+/// Compares both trees: the same files and folders, the same sizes and the
+/// same SHA-256 checksums.
+pub fn verify(
+    source: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(), RelocationError> {
+    log::trace!("verify({:?}, {:?})", source, destination);
+
+    let source_entries = scan(source)?;
+    let destination_entries = scan(destination)?;
+    compare_entries(&source_entries, &destination_entries)?;
+
+    let mut current = new_progress(Phase::Verifying, &source_entries);
+    progress(current);
+
+    for entry in &source_entries {
+        let EntryKind::File { size } = entry.kind else {
+            continue;
+        };
+
+        let source_checksum = checksum(&source.join(&entry.path), cancelled)?;
+        let destination_checksum = checksum(&destination.join(&entry.path), cancelled)?;
+        if source_checksum != destination_checksum {
+            return Err(RelocationError::Mismatch(format!(
+                "{}: {}",
+                gettext("Checksums differ"),
+                entry.path.display()
+            )));
+        }
+
+        current.done_bytes += size;
+        current.done_files += 1;
+        progress(current);
+    }
+
+    Ok(())
+}
+
+fn compare_entries(source: &[Entry], destination: &[Entry]) -> Result<(), RelocationError> {
+    let count_files = |entries: &[Entry]| {
+        entries
+            .iter()
+            .filter(|e| matches!(e.kind, EntryKind::File { .. }))
+            .count()
+    };
+    if count_files(source) != count_files(destination) {
+        return Err(RelocationError::Mismatch(format!(
+            "{}: {} / {}",
+            gettext("File count differs"),
+            count_files(destination),
+            count_files(source)
+        )));
+    }
+
+    for (a, b) in source.iter().zip(destination) {
+        if a.path != b.path {
+            return Err(RelocationError::Mismatch(format!(
+                "{}: {}",
+                gettext("Missing"),
+                a.path.display()
+            )));
+        }
+        if a.kind != b.kind {
+            return Err(RelocationError::Mismatch(format!(
+                "{}: {}",
+                gettext("Size or type differs"),
+                a.path.display()
+            )));
+        }
+    }
+
+    if source.len() != destination.len() {
+        return Err(RelocationError::Mismatch(gettext("Folder count differs")));
+    }
+
+    Ok(())
+}
+
+pub fn checksum(path: &Path, cancelled: &AtomicBool) -> Result<String, RelocationError> {
+    let mut file = fs::File::open(path).map_err(|e| RelocationError::Io(path.to_owned(), e))?;
+    let mut checksum = glib::Checksum::new(glib::ChecksumType::Sha256).unwrap();
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(RelocationError::Cancelled);
+        }
+
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| RelocationError::Io(path.to_owned(), e))?;
+        if read == 0 {
+            break;
+        }
+        checksum.update(&buffer[..read]);
+    }
+
+    Ok(checksum.string().unwrap_or_default())
+}
+
+/// Progress over all files in `entries`, with nothing done yet.
+pub fn new_progress(phase: Phase, entries: &[Entry]) -> Progress {
+    let mut progress = Progress {
+        phase,
+        done_bytes: 0,
+        total_bytes: 0,
+        done_files: 0,
+        total_files: 0,
+    };
+    for entry in entries {
+        if let EntryKind::File { size } = entry.kind {
+            progress.total_bytes += size;
+            progress.total_files += 1;
+        }
+    }
+    progress
+}
+
+pub fn pending_path() -> PathBuf {
+    user_data_dir().join("vaults").join("relocations.toml")
+}
+
+fn read_pending_all() -> HashMap<Uuid, PendingRelocation> {
+    config_file::read_map(&pending_path()).unwrap_or_else(|e| {
+        log::warn!("Could not read relocations file: {}", e);
+        HashMap::new()
+    })
+}
+
+/// The unfinished move of the vault, if its data was not moved since.
+pub fn pending(uuid: &Uuid, source: &str) -> Option<PendingRelocation> {
+    read_pending_all()
+        .remove(uuid)
+        .filter(|pending| pending.source == source)
+}
+
+pub fn set_pending(uuid: Uuid, pending: PendingRelocation) -> io::Result<()> {
+    log::trace!("set_pending({}, {:?})", uuid, pending);
+
+    config_file::update_map(
+        &pending_path(),
+        |all: &mut HashMap<Uuid, PendingRelocation>| {
+            all.insert(uuid, pending);
+        },
+    )
+}
+
+pub fn clear_pending(uuid: &Uuid) {
+    log::trace!("clear_pending({})", uuid);
+
+    if let Err(e) = config_file::remove_from_map::<PendingRelocation>(&pending_path(), uuid) {
+        log::error!("Could not update relocations file: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_resume_and_verify() {
+        let directory =
+            std::env::temp_dir().join(format!("vaults-relocation-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let source = directory.join("source");
+        let destination = directory.join("destination");
+        fs::create_dir_all(source.join("d").join("e")).unwrap();
+        fs::write(source.join("gocryptfs.conf"), "{}").unwrap();
+        fs::write(source.join("d").join("a"), vec![7; BUFFER_SIZE + 10]).unwrap();
+        fs::write(source.join("d").join("e").join("b"), "").unwrap();
+        std::os::unix::fs::symlink("d/a", source.join("link")).unwrap();
+
+        assert!(check_destination(&source, &source.join("d"), false).is_err());
+        assert!(check_destination(&source, &destination, false).is_ok());
+
+        let cancelled = AtomicBool::new(true);
+        let result = copy(&source, &destination, &cancelled, &mut |_| {});
+        assert!(matches!(result, Err(RelocationError::Cancelled)));
+        assert!(check_destination(&source, &destination, true).is_ok());
+
+        let cancelled = AtomicBool::new(false);
+        let mut last = None;
+        copy(&source, &destination, &cancelled, &mut |p| last = Some(p)).unwrap();
+        let last = last.unwrap();
+        assert_eq!(last.total_files, 3);
+        assert_eq!(last.done_bytes, last.total_bytes);
+        verify(&source, &destination, &cancelled, &mut |_| {}).unwrap();
+
+        // Already copied files are kept when resuming
+        copy(&source, &destination, &cancelled, &mut |_| {}).unwrap();
+        assert_eq!(
+            fs::read_link(destination.join("link")).unwrap(),
+            PathBuf::from("d/a")
+        );
+
+        fs::write(destination.join("gocryptfs.conf"), "[]").unwrap();
+        assert!(matches!(
+            verify(&source, &destination, &cancelled, &mut |_| {}),
+            Err(RelocationError::Mismatch(_))
+        ));
+        fs::write(destination.join("gocryptfs.conf"), "{}").unwrap();
+        fs::write(destination.join("extra"), "").unwrap();
+        assert!(verify(&source, &destination, &cancelled, &mut |_| {}).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
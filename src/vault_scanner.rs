@@ -0,0 +1,231 @@
+// vault_scanner.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    backend::{self, Backend},
+    vault::VaultConfig,
+    vault_descriptor,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use uuid::Uuid;
+
+pub const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundVault {
+    pub encrypted_data_directory: PathBuf,
+    pub backend: Backend,
+}
+
+/// Walks the directory tree below `root` and returns every encrypted data
+/// directory. Vaults are not descended into and symlinks are not followed.
+pub fn scan(root: &Path, is_cancelled: &AtomicBool) -> Vec<FoundVault> {
+    log::trace!("scan({:?})", root);
+
+    let mut found = vec![];
+    let mut directories = vec![(root.to_path_buf(), 0)];
+
+    while let Some((directory, depth)) = directories.pop() {
+        if is_cancelled.load(Ordering::Relaxed) {
+            log::debug!("Scan cancelled");
+            break;
+        }
+
+        if let Some(backend) = backend::detect_backend(&directory) {
+            log::debug!("Found {} vault: {:?}", backend, directory);
+            found.push(FoundVault {
+                encrypted_data_directory: directory,
+                backend,
+            });
+            continue;
+        }
+
+        if depth >= MAX_DEPTH {
+            continue;
+        }
+
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                directories.push((entry.path(), depth + 1));
+            }
+        }
+    }
+
+    found.sort_by(|a, b| a.encrypted_data_directory.cmp(&b.encrypted_data_directory));
+    found
+}
+
+/// Guesses the name of a vault from its directory, as the import dialog does.
+pub fn guess_name(encrypted_data_directory: &Path) -> String {
+    encrypted_data_directory
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Turns found vaults into vault configs with unique names and mount
+/// directories below `mount_directory`. Vaults that already exist are skipped.
+pub fn suggest(
+    found: &[FoundVault],
+    mount_directory: &Path,
+    existing: &HashMap<Uuid, VaultConfig>,
+) -> Vec<(Uuid, VaultConfig)> {
+    log::trace!("suggest({:?}, {:?})", found, mount_directory);
+
+    let existing_directories: HashSet<PathBuf> = existing
+        .values()
+        .map(|config| canonical(Path::new(&config.encrypted_data_directory)))
+        .collect();
+    let mut names: HashSet<String> = existing.values().map(|v| v.name.clone()).collect();
+    let mut uuids: HashSet<Uuid> = existing.keys().cloned().collect();
+
+    let mut suggestions = vec![];
+    for vault in found {
+        if existing_directories.contains(&canonical(&vault.encrypted_data_directory)) {
+            log::debug!(
+                "Skipping existing vault: {:?}",
+                vault.encrypted_data_directory
+            );
+            continue;
+        }
+
+        let guessed_name = match vault_descriptor::read(&vault.encrypted_data_directory) {
+            Some(Ok(descriptor)) => descriptor.name,
+            _ => guess_name(&vault.encrypted_data_directory),
+        };
+        let mut name = guessed_name.clone();
+        let mut i = 2;
+        while names.contains(&name) || mount_directory.join(&name).exists() {
+            name = format!("{} {}", guessed_name, i);
+            i += 1;
+        }
+        names.insert(name.clone());
+
+        let mut uuid = Uuid::new_v4();
+        while uuids.contains(&uuid) {
+            uuid = Uuid::new_v4();
+        }
+        uuids.insert(uuid);
+
+        suggestions.push((
+            uuid,
+            VaultConfig {
+                mount_directory: mount_directory.join(&name).to_string_lossy().to_string(),
+                name,
+                backend: vault.backend,
+                encrypted_data_directory: vault
+                    .encrypted_data_directory
+                    .to_string_lossy()
+                    .to_string(),
+                ..Default::default()
+            },
+        ));
+    }
+
+    suggestions
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_finds_nested_vaults() {
+        let root = std::env::temp_dir().join(format!("vaults-scan-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let gocryptfs = root.join("Nextcloud").join("Private");
+        let cryfs = root.join("Work");
+        let nested = gocryptfs.join("inner");
+        for directory in [&gocryptfs, &cryfs, &nested, &root.join("Empty")] {
+            std::fs::create_dir_all(directory).unwrap();
+        }
+        std::fs::write(gocryptfs.join("gocryptfs.conf"), "").unwrap();
+        std::fs::write(cryfs.join("cryfs.config"), "").unwrap();
+        // Inside a vault, so never reported
+        std::fs::write(nested.join("cryfs.config"), "").unwrap();
+
+        let found = scan(&root, &AtomicBool::new(false));
+        assert_eq!(
+            found,
+            vec![
+                FoundVault {
+                    encrypted_data_directory: gocryptfs.clone(),
+                    backend: Backend::Gocryptfs,
+                },
+                FoundVault {
+                    encrypted_data_directory: cryfs.clone(),
+                    backend: Backend::Cryfs,
+                },
+            ]
+        );
+
+        assert!(scan(&root, &AtomicBool::new(true)).is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn suggestions_skip_existing_vaults() {
+        let found = vec![
+            FoundVault {
+                encrypted_data_directory: PathBuf::from("/data/a/Private"),
+                backend: Backend::Gocryptfs,
+            },
+            FoundVault {
+                encrypted_data_directory: PathBuf::from("/data/b/Private"),
+                backend: Backend::Cryfs,
+            },
+            FoundVault {
+                encrypted_data_directory: PathBuf::from("/data/Work"),
+                backend: Backend::Cryfs,
+            },
+        ];
+
+        let mut existing = HashMap::new();
+        existing.insert(
+            Uuid::new_v4(),
+            VaultConfig {
+                name: "Work".to_string(),
+                encrypted_data_directory: "/data/Work".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let suggestions = suggest(&found, Path::new("/nonexistent/Vaults"), &existing);
+        let names: Vec<&str> = suggestions.iter().map(|(_, c)| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Private", "Private 2"]);
+        assert_eq!(
+            suggestions[1].1.mount_directory,
+            "/nonexistent/Vaults/Private 2"
+        );
+        assert_eq!(suggestions[1].1.backend, Backend::Cryfs);
+    }
+}
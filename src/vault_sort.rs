@@ -0,0 +1,157 @@
+// vault_sort.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{vault::VaultConfig, vault_usage::VaultUsage};
+use std::cmp::Ordering;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    Name,
+    LastOpened,
+    MostOpened,
+    Created,
+    Backend,
+    OpenFirst,
+}
+
+impl SortOrder {
+    /// Maps the values of the `sort-order` setting.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "last-opened" => Self::LastOpened,
+            "most-opened" => Self::MostOpened,
+            "created" => Self::Created,
+            "backend" => Self::Backend,
+            "open-first" => Self::OpenFirst,
+            _ => Self::Name,
+        }
+    }
+}
+
+/// What vaults are sorted by.
+#[derive(Debug, Clone, Copy)]
+pub struct SortEntry<'a> {
+    pub config: &'a VaultConfig,
+    pub is_open: bool,
+    pub usage: &'a VaultUsage,
+}
+
+/// Favorites come first, then the given order. Vaults that are equal in that
+/// order are sorted by name.
+pub fn compare(order: SortOrder, a: &SortEntry, b: &SortEntry) -> Ordering {
+    let by_order = match order {
+        SortOrder::Name => Ordering::Equal,
+        // Most recent first, unknown last
+        SortOrder::LastOpened => b.usage.last_unlock.cmp(&a.usage.last_unlock),
+        SortOrder::MostOpened => b.usage.unlock_count.cmp(&a.usage.unlock_count),
+        SortOrder::Created => b.usage.created.cmp(&a.usage.created),
+        SortOrder::Backend => a
+            .config
+            .backend
+            .to_string()
+            .cmp(&b.config.backend.to_string()),
+        SortOrder::OpenFirst => b.is_open.cmp(&a.is_open),
+    };
+
+    b.config
+        .favorite
+        .cmp(&a.config.favorite)
+        .then(by_order)
+        .then_with(|| a.config.name.cmp(&b.config.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+
+    fn config(name: &str, backend: Backend, favorite: bool) -> VaultConfig {
+        VaultConfig {
+            name: name.to_string(),
+            backend,
+            favorite,
+            ..Default::default()
+        }
+    }
+
+    fn usage(created: Option<i64>, last_unlock: Option<i64>, unlock_count: u64) -> VaultUsage {
+        VaultUsage {
+            created,
+            last_unlock,
+            unlock_count,
+            ..Default::default()
+        }
+    }
+
+    fn sorted(order: SortOrder, entries: &[SortEntry]) -> Vec<String> {
+        let mut entries = entries.to_vec();
+        entries.sort_by(|a, b| compare(order, a, b));
+        entries.iter().map(|e| e.config.name.clone()).collect()
+    }
+
+    #[test]
+    fn favorites_are_pinned() {
+        let a = config("A", Backend::Gocryptfs, false);
+        let b = config("B", Backend::Cryfs, false);
+        let c = config("C", Backend::Gocryptfs, true);
+        let d = config("D", Backend::Cryfs, false);
+        let usage_a = usage(None, None, 0);
+        let usage_b = usage(Some(10), Some(30), 1);
+        let usage_c = usage(Some(20), None, 0);
+        let usage_d = usage(Some(5), Some(20), 4);
+        let entries = [
+            SortEntry {
+                config: &d,
+                is_open: true,
+                usage: &usage_d,
+            },
+            SortEntry {
+                config: &c,
+                is_open: false,
+                usage: &usage_c,
+            },
+            SortEntry {
+                config: &b,
+                is_open: false,
+                usage: &usage_b,
+            },
+            SortEntry {
+                config: &a,
+                is_open: true,
+                usage: &usage_a,
+            },
+        ];
+
+        assert_eq!(sorted(SortOrder::Name, &entries), ["C", "A", "B", "D"]);
+        assert_eq!(
+            sorted(SortOrder::LastOpened, &entries),
+            ["C", "B", "D", "A"]
+        );
+        assert_eq!(
+            sorted(SortOrder::MostOpened, &entries),
+            ["C", "D", "B", "A"]
+        );
+        assert_eq!(sorted(SortOrder::Created, &entries), ["C", "B", "D", "A"]);
+        assert_eq!(sorted(SortOrder::Backend, &entries), ["C", "B", "D", "A"]);
+        assert_eq!(sorted(SortOrder::OpenFirst, &entries), ["C", "A", "D", "B"]);
+        assert_eq!(SortOrder::from_setting("backend"), SortOrder::Backend);
+        assert_eq!(SortOrder::from_setting("unknown"), SortOrder::Name);
+    }
+}
@@ -0,0 +1,99 @@
+// vault_tags.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::vault::VaultConfig;
+use std::cmp::Ordering;
+
+/// Splits comma separated tags. Tags differing only in case are kept once,
+/// with the spelling used first.
+pub fn parse(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for tag in text.split(',').map(str::trim) {
+        if tag.is_empty() || tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            continue;
+        }
+        tags.push(tag.to_string());
+    }
+
+    tags
+}
+
+pub fn to_text(tags: &[String]) -> String {
+    tags.join(", ")
+}
+
+pub fn has_tag(config: &VaultConfig, tag: &str) -> bool {
+    let tag = tag.to_lowercase();
+    config.tags.iter().any(|t| t.to_lowercase() == tag)
+}
+
+/// The groups a vault is listed in when grouping by tag: one for each of its
+/// tags, or only the untagged group.
+pub fn groups(config: &VaultConfig) -> Vec<Option<&str>> {
+    if config.tags.is_empty() {
+        return vec![None];
+    }
+
+    config.tags.iter().map(|tag| Some(tag.as_str())).collect()
+}
+
+/// Orders groups by name, with untagged vaults last.
+pub fn compare_groups(a: Option<&str>, b: Option<&str>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tags() {
+        assert_eq!(
+            parse(" Work, private,,work , Backup"),
+            vec!["Work", "private", "Backup"]
+        );
+        assert!(parse(" , ").is_empty());
+        assert_eq!(to_text(&parse("a,b")), "a, b");
+    }
+
+    #[test]
+    fn vaults_are_in_every_tag_group() {
+        let mut config = VaultConfig {
+            tags: parse("Team, Work"),
+            ..Default::default()
+        };
+        assert_eq!(groups(&config), vec![Some("Team"), Some("Work")]);
+
+        config.tags.clear();
+        assert_eq!(groups(&config), vec![None]);
+    }
+
+    #[test]
+    fn untagged_group_is_last() {
+        let mut groups = vec![None, Some("work"), Some("Backup")];
+        groups.sort_by(|a, b| compare_groups(*a, *b));
+        assert_eq!(groups, vec![Some("Backup"), Some("work"), None]);
+    }
+}
@@ -0,0 +1,137 @@
+// vault_usage.rs
+//
+// Copyright 2026 Martin Pobaschnig
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::config_file;
+use gtk::glib::user_data_dir;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+/// How a vault is used. Kept apart from the user config, which should only
+/// change when the user edits a vault.
+///
+/// Times are seconds since the Unix epoch and missing if never recorded, e.g.
+/// for vaults added before usage was tracked.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VaultUsage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_unlock: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_lock: Option<i64>,
+    #[serde(default)]
+    pub unlock_count: u64,
+}
+
+pub fn path() -> PathBuf {
+    user_data_dir().join("vaults").join("usage.toml")
+}
+
+/// Vaults without recorded usage are missing from the map.
+pub fn read_all() -> HashMap<Uuid, VaultUsage> {
+    config_file::read_map(&path()).unwrap_or_else(|e| {
+        log::warn!("Could not read usage file: {}", e);
+        HashMap::new()
+    })
+}
+
+pub fn read(uuid: &Uuid) -> VaultUsage {
+    read_all().remove(uuid).unwrap_or_default()
+}
+
+/// Keeps the first date, so re-adding a vault does not make it new.
+pub fn record_created(uuid: Uuid) {
+    log::trace!("record_created({})", uuid);
+
+    update(uuid, |usage| {
+        usage.created.get_or_insert_with(now);
+    });
+}
+
+pub fn record_unlock(uuid: Uuid) {
+    log::trace!("record_unlock({})", uuid);
+
+    update(uuid, |usage| {
+        usage.last_unlock = Some(now());
+        usage.unlock_count += 1;
+    });
+}
+
+pub fn record_lock(uuid: Uuid) {
+    log::trace!("record_lock({})", uuid);
+
+    update(uuid, |usage| usage.last_lock = Some(now()));
+}
+
+// An unreadable usage file is left as it is rather than replaced by the
+// usage of a single vault
+fn update(uuid: Uuid, f: impl FnOnce(&mut VaultUsage)) {
+    let result = config_file::update_map(&path(), |all_usage: &mut HashMap<Uuid, VaultUsage>| {
+        f(all_usage.entry(uuid).or_default())
+    });
+
+    if let Err(e) = result {
+        log::error!("Could not update usage file: {}", e);
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<HashMap<Uuid, VaultUsage>, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    #[test]
+    fn usage_round_trip() {
+        let uuid = Uuid::new_v4();
+        let mut all_usage = HashMap::new();
+        all_usage.insert(
+            uuid,
+            VaultUsage {
+                created: Some(1_600_000_000),
+                last_unlock: Some(1_700_000_000),
+                last_lock: None,
+                unlock_count: 3,
+            },
+        );
+        all_usage.insert(Uuid::new_v4(), VaultUsage::default());
+
+        let content = toml::to_string_pretty(&all_usage).unwrap();
+        assert_eq!(parse(&content).unwrap(), all_usage);
+        assert!(parse("").unwrap().is_empty());
+
+        let usage = parse(&format!("[{}]\nlast_unlock = 5\n", uuid)).unwrap();
+        assert_eq!(usage[&uuid].unlock_count, 0);
+        assert_eq!(usage[&uuid].created, None);
+    }
+}